@@ -1,9 +1,11 @@
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Mutex;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use atomic_refcell::AtomicRefCell;
 
+use crate::plan::UserCollectionKind;
+
 /// This stores some global states for an MMTK instance.
 /// Some MMTK components like plans and allocators may keep an reference to the struct, and can access it.
 // This used to be a part of the `BasePlan`. In that case, any component that accesses
@@ -25,6 +27,14 @@ pub struct GlobalState {
     pub(crate) emergency_collection: AtomicBool,
     /// Is the current GC triggered by the user?
     pub(crate) user_triggered_collection: AtomicBool,
+    /// Has the user requested a GC since the last GC took the requests? It is taken by
+    /// `set_collection_kind` together with the requested flags.
+    pub(crate) user_collection_requested: AtomicBool,
+    /// The flags of the [`UserCollectionKind`]s requested by the user since the last GC.
+    pub(crate) requested_collection_flags: AtomicU8,
+    /// The flags of the [`UserCollectionKind`]s that the current GC is doing. They are taken from
+    /// the requested flags in `set_collection_kind`.
+    pub(crate) collection_flags: AtomicU8,
     /// Is the current GC triggered internally by MMTK? This is unused for now. We may have internally triggered GC
     /// for a concurrent plan.
     pub(crate) internal_triggered_collection: AtomicBool,
@@ -45,6 +55,14 @@ pub struct GlobalState {
     /// A counteer that keeps tracks of the number of bytes allocated by malloc
     #[cfg(feature = "malloc_counted_size")]
     pub(crate) malloc_bytes: AtomicUsize,
+    /// When did the last GC end? This is the time when MMTk was created if no GC has happened yet.
+    pub(crate) last_gc_end_time: Mutex<Instant>,
+    /// How long did the last full heap GC take? `None` if no full heap GC has happened yet.
+    pub(crate) last_full_heap_gc_duration: Mutex<Option<Duration>>,
+    /// The reserved pages at the end of the last GC.
+    pub(crate) reserved_pages_at_last_gc: AtomicUsize,
+    /// Was the last GC a full heap GC?
+    pub(crate) last_gc_full_heap: AtomicBool,
     /// This stores the size in bytes for all the live objects in last GC. This counter is only updated in the GC release phase.
     #[cfg(feature = "count_live_bytes_in_gc")]
     pub(crate) live_bytes_in_last_gc: AtomicUsize,
@@ -56,6 +74,20 @@ impl GlobalState {
         self.initialized.load(Ordering::SeqCst)
     }
 
+    /// Request a GC of the given kind on behalf of the user. The next GC will be user triggered,
+    /// and it will do what all the kinds requested before it starts ask for.
+    pub(crate) fn request_user_collection(&self, kind: UserCollectionKind) {
+        self.requested_collection_flags
+            .fetch_or(kind.flags(), Ordering::SeqCst);
+        self.user_collection_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Return true if the user has requested a GC that no GC has taken yet, for example, a GC
+    /// requested after the current GC has set its kind.
+    pub(crate) fn is_user_collection_requested(&self) -> bool {
+        self.user_collection_requested.load(Ordering::SeqCst)
+    }
+
     /// Set the collection kind for the current GC. This is called before
     /// scheduling collection to determin what kind of collection it will be.
     pub fn set_collection_kind(
@@ -63,6 +95,14 @@ impl GlobalState {
         last_collection_was_exhaustive: bool,
         heap_can_grow: bool,
     ) -> bool {
+        self.collection_flags.store(
+            self.requested_collection_flags.swap(0, Ordering::SeqCst),
+            Ordering::SeqCst,
+        );
+        self.user_triggered_collection.store(
+            self.user_collection_requested.swap(false, Ordering::SeqCst),
+            Ordering::Relaxed,
+        );
         self.cur_collection_attempts.store(
             if self.user_triggered_collection.load(Ordering::Relaxed) {
                 1
@@ -114,6 +154,24 @@ impl GlobalState {
         self.user_triggered_collection.load(Ordering::Relaxed)
     }
 
+    /// Return true if the user requested a full heap collection for the current GC.
+    pub fn is_full_heap_collection_requested(&self) -> bool {
+        self.collection_flags.load(Ordering::SeqCst) & UserCollectionKind::FULL_HEAP != 0
+    }
+
+    /// Return true if the user requested the current GC to compact the heap.
+    pub fn is_compaction_requested(&self) -> bool {
+        self.collection_flags.load(Ordering::SeqCst) & UserCollectionKind::COMPACT != 0
+    }
+
+    /// Return true if the current GC should clear soft references, because it is an emergency
+    /// collection, or because the user requested so.
+    pub fn should_clear_soft_references(&self) -> bool {
+        self.is_emergency_collection()
+            || self.collection_flags.load(Ordering::SeqCst) & UserCollectionKind::CLEAR_SOFT_REFS
+                != 0
+    }
+
     /// Reset collection state information.
     pub fn reset_collection_trigger(&self) {
         self.last_internal_triggered_collection.store(
@@ -124,6 +182,9 @@ impl GlobalState {
             .store(false, Ordering::SeqCst);
         self.user_triggered_collection
             .store(false, Ordering::Relaxed);
+        // The requests were taken by `set_collection_kind`. Requests made during the GC are kept
+        // for the next GC.
+        self.collection_flags.store(0, Ordering::SeqCst);
     }
 
     /// Are the stacks scanned?
@@ -169,6 +230,34 @@ impl GlobalState {
         old_allocation_bytes + size
     }
 
+    /// Record the end of a GC. This is called at the end of each GC, before resuming mutators.
+    pub(crate) fn record_gc_end(&self, duration: Duration, reserved_pages: usize, full_heap: bool) {
+        *self.last_gc_end_time.lock().unwrap() = Instant::now();
+        if full_heap {
+            *self.last_full_heap_gc_duration.lock().unwrap() = Some(duration);
+        }
+        self.reserved_pages_at_last_gc
+            .store(reserved_pages, Ordering::SeqCst);
+        self.last_gc_full_heap.store(full_heap, Ordering::SeqCst);
+    }
+
+    /// Return the time elapsed since the end of the last GC (or since MMTk was created).
+    pub(crate) fn time_since_last_gc(&self) -> Duration {
+        self.last_gc_end_time.lock().unwrap().elapsed()
+    }
+
+    /// Return the duration of the last full heap GC, or `None` if no full heap GC has happened yet.
+    pub(crate) fn last_full_heap_gc_duration(&self) -> Option<Duration> {
+        *self.last_full_heap_gc_duration.lock().unwrap()
+    }
+
+    /// Can a full heap GC reclaim anything now? This is true if more pages are reserved since the
+    /// last GC, or if the last GC was not a full heap GC.
+    pub(crate) fn may_have_garbage_since_last_gc(&self, reserved_pages: usize) -> bool {
+        reserved_pages > self.reserved_pages_at_last_gc.load(Ordering::SeqCst)
+            || !self.last_gc_full_heap.load(Ordering::SeqCst)
+    }
+
     #[cfg(feature = "malloc_counted_size")]
     pub fn get_malloc_bytes_in_pages(&self) -> usize {
         crate::util::conversions::bytes_to_pages_up(self.malloc_bytes.load(Ordering::Relaxed))
@@ -204,6 +293,9 @@ impl Default for GlobalState {
             stacks_prepared: AtomicBool::new(false),
             emergency_collection: AtomicBool::new(false),
             user_triggered_collection: AtomicBool::new(false),
+            user_collection_requested: AtomicBool::new(false),
+            requested_collection_flags: AtomicU8::new(0),
+            collection_flags: AtomicU8::new(0),
             internal_triggered_collection: AtomicBool::new(false),
             last_internal_triggered_collection: AtomicBool::new(false),
             allocation_success: AtomicBool::new(false),
@@ -211,6 +303,10 @@ impl Default for GlobalState {
             cur_collection_attempts: AtomicUsize::new(0),
             scanned_stacks: AtomicUsize::new(0),
             allocation_bytes: AtomicUsize::new(0),
+            last_gc_end_time: Mutex::new(Instant::now()),
+            last_full_heap_gc_duration: Mutex::new(None),
+            reserved_pages_at_last_gc: AtomicUsize::new(0),
+            last_gc_full_heap: AtomicBool::new(true),
             #[cfg(feature = "malloc_counted_size")]
             malloc_bytes: AtomicUsize::new(0),
             #[cfg(feature = "count_live_bytes_in_gc")]
@@ -225,3 +321,63 @@ pub enum GcStatus {
     GcPrepare,
     GcProper,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_collection_kinds() {
+        let state = GlobalState::default();
+        state.request_user_collection(UserCollectionKind::Nursery);
+        state.set_collection_kind(false, true);
+        assert!(state.is_user_triggered_collection());
+        assert!(!state.is_full_heap_collection_requested());
+        assert!(!state.is_compaction_requested());
+        assert!(!state.should_clear_soft_references());
+        state.reset_collection_trigger();
+
+        // Requests made before the GC starts are merged.
+        state.request_user_collection(UserCollectionKind::FullCompact);
+        state.request_user_collection(UserCollectionKind::FullClearSoftRefs);
+        // Nothing is requested for the current GC until its kind is set.
+        assert!(!state.is_full_heap_collection_requested());
+        state.set_collection_kind(false, true);
+        assert!(state.is_full_heap_collection_requested());
+        assert!(state.is_compaction_requested());
+        assert!(state.should_clear_soft_references());
+
+        state.reset_collection_trigger();
+        assert!(!state.is_user_triggered_collection());
+        assert!(!state.is_full_heap_collection_requested());
+        assert!(!state.is_compaction_requested());
+        assert!(!state.should_clear_soft_references());
+    }
+
+    #[test]
+    fn test_user_collection_request_during_gc() {
+        let state = GlobalState::default();
+        state.request_user_collection(UserCollectionKind::Full);
+        state.set_collection_kind(false, true);
+        // The request is made after the current GC has taken the requested kinds.
+        state.request_user_collection(UserCollectionKind::FullCompact);
+        assert!(!state.is_compaction_requested());
+        assert!(state.is_user_collection_requested());
+        state.reset_collection_trigger();
+        assert!(state.is_user_collection_requested());
+
+        // The next GC is user triggered, and does what was requested during the last GC.
+        state.set_collection_kind(false, true);
+        assert!(!state.is_user_collection_requested());
+        assert!(state.is_user_triggered_collection());
+        assert!(state.is_full_heap_collection_requested());
+        assert!(state.is_compaction_requested());
+        assert!(!state.should_clear_soft_references());
+        state.reset_collection_trigger();
+
+        state.set_collection_kind(false, true);
+        assert!(!state.is_user_triggered_collection());
+        assert!(!state.is_full_heap_collection_requested());
+        assert!(!state.is_compaction_requested());
+    }
+}
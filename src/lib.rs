@@ -63,4 +63,5 @@ pub mod vm;
 
 pub use crate::plan::{
     AllocationSemantics, BarrierSelector, Mutator, MutatorContext, ObjectQueue, Plan,
+    UserCollectionKind,
 };
@@ -14,13 +14,14 @@
 use crate::mmtk::MMTKBuilder;
 use crate::mmtk::MMTK;
 use crate::plan::AllocationSemantics;
-use crate::plan::{Mutator, MutatorContext};
+use crate::plan::{Mutator, MutatorContext, UserCollectionKind};
 use crate::scheduler::WorkBucketStage;
 use crate::scheduler::{GCWork, GCWorker};
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::constants::{LOG_BYTES_IN_PAGE, MIN_OBJECT_SIZE};
 use crate::util::heap::layout::vm_layout::vm_layout;
 use crate::util::opaque_pointer::*;
+use crate::util::retention_path::{QueryInProgress, RetentionPath};
 use crate::util::{Address, ObjectReference};
 use crate::vm::edge_shape::MemorySlice;
 use crate::vm::ReferenceGlue;
@@ -34,8 +35,9 @@ use crate::vm::VMBinding;
 /// 1. Create an [`crate::MMTKBuilder`] instance.
 /// 2. Set command line options for MMTKBuilder by [`crate::memory_manager::process`] or [`crate::memory_manager::process_bulk`].
 /// 3. Initialize MMTk by calling this function, `mmtk_init()`, and pass the builder earlier. This call will return an MMTK instance.
-///    Usually a binding store the MMTK instance statically as a singleton. We plan to allow multiple instances, but this is not yet fully
-///    supported. Currently we assume a binding will only need one MMTk instance. Note that GC is enabled by default and the binding should
+///    Usually a binding store the MMTK instance statically as a singleton. A binding may create more than one instance. The instances
+///    share the virtual memory layout and each reserves a distinct range of the address space, which is only supported with the 64-bit
+///    layout without compressed pointers (see [`crate::MMTK`]). Note that GC is enabled by default and the binding should
 ///    implement `VMCollection::is_collection_enabled()` if it requires that the GC should be disabled at a particular time.
 ///
 /// Note that this method will attempt to initialize a logger. If the VM would like to use its own logger, it should initialize the logger before calling this method.
@@ -50,6 +52,13 @@ use crate::vm::VMBinding;
 ///
 /// Arguments:
 /// * `builder`: The reference to a MMTk builder.
+///
+/// # Panics
+///
+/// This function panics if the spaces of the new instance do not fit in the part of the heap range that
+/// other MMTk instances have not reserved. This limits the number of instances that can be alive at the same
+/// time (see [`crate::MMTK`]). The existing instances are not affected, and a binding may destroy some of them
+/// with [`mmtk_destroy`] before creating more instances.
 pub fn mmtk_init<VM: VMBinding>(builder: &MMTKBuilder) -> Box<MMTK<VM>> {
     match crate::util::logger::try_init() {
         Ok(_) => debug!("MMTk initialized the logger."),
@@ -85,6 +94,32 @@ pub fn mmtk_init<VM: VMBinding>(builder: &MMTKBuilder) -> Box<MMTK<VM>> {
     Box::new(mmtk)
 }
 
+/// Destroy an MMTk instance, and release all the resources it uses.  The memory of its spaces and
+/// their side metadata is unmapped, and the address ranges of the spaces can be used by MMTk
+/// instances created afterwards.
+///
+/// If the collection has been initialized, the VM must call [`crate::MMTK::prepare_to_destroy`] and
+/// wait for the underlying native threads of the GC threads to exit before calling this function.
+///
+/// # Panics
+///
+/// An instance that uses the MarkSweep plan with the `malloc_mark_sweep` feature cannot be
+/// destroyed, because its memory is allocated by the malloc library.  This function and
+/// [`crate::MMTK::prepare_to_destroy`] panic for such an instance, before they change any state.
+///
+/// # Safety
+///
+/// * `mmtk` must be the box returned by [`mmtk_init`].  If the binding leaked the box to get a
+///   static reference, it can reconstruct the box with `Box::from_raw`.
+/// * All the mutators of the instance must have been destroyed by [`destroy_mutator`], and the
+///   VM must not use the instance, or any object or address in its heap, after this call.
+///
+/// Arguments:
+/// * `mmtk`: The MMTk instance to destroy.
+pub unsafe fn mmtk_destroy<VM: VMBinding>(mmtk: Box<MMTK<VM>>) {
+    mmtk.destroy()
+}
+
 /// Add an externally mmapped region to the VM space. A VM space can be set through MMTk options (`vm_space_start` and `vm_space_size`),
 /// and can also be set through this function call. A VM space can be discontiguous. This function can be called multiple times,
 /// and all the address ranges passed as arguments in the function will be considered as part of the VM space.
@@ -386,6 +421,48 @@ pub fn memory_region_copy_post<VM: VMBinding>(
     mutator.barrier().memory_region_copy_post(src, dst);
 }
 
+/// The *subsuming* read barrier by MMTk. It loads the object reference held in `slot`, and
+/// returns the object reference the mutator should use, which may be different from the value
+/// held in the slot. For example, a concurrent copying plan may return the to-space copy of the
+/// loaded object.
+///
+/// A binding only needs to call this if the plan needs a load barrier (see
+/// [`crate::plan::PlanConstraints::needs_load_barrier`]). For performance reasons, a VM may
+/// implement the read barrier fast-path on their side, and call `object_reference_read_slow` only
+/// if necessary. See [`crate::plan::LoadBarrier`] for the fast-path.
+///
+/// Arguments:
+/// * `mutator`: The mutator for the current thread.
+/// * `src`: The source object that holds `slot`.
+/// * `slot`: The location of the field to be read.
+pub fn object_reference_read<VM: VMBinding>(
+    mutator: &mut Mutator<VM>,
+    src: ObjectReference,
+    slot: VM::VMEdge,
+) -> Option<ObjectReference> {
+    mutator.barrier().object_reference_read(src, slot)
+}
+
+/// The read barrier slow-path by MMTk. A binding that implements the read barrier fast-path on
+/// their side should call this when the fast-path check fails, and use the returned object
+/// reference instead of `target`.
+///
+/// Arguments:
+/// * `mutator`: The mutator for the current thread.
+/// * `src`: The source object that holds `slot`.
+/// * `slot`: The location of the field that has been read.
+/// * `target`: The object reference loaded from `slot`.
+pub fn object_reference_read_slow<VM: VMBinding>(
+    mutator: &mut Mutator<VM>,
+    src: ObjectReference,
+    slot: VM::VMEdge,
+    target: ObjectReference,
+) -> ObjectReference {
+    mutator
+        .barrier()
+        .object_reference_read_slow(src, slot, target)
+}
+
 /// Return an AllocatorSelector for the given allocation semantic. This method is provided
 /// so that VM compilers may call it to help generate allocation fast-path.
 ///
@@ -546,6 +623,36 @@ pub fn live_bytes_in_last_gc<VM: VMBinding>(mmtk: &MMTK<VM>) -> usize {
     mmtk.state.live_bytes_in_last_gc.load(Ordering::SeqCst)
 }
 
+/// Report the occupancy and fragmentation of each space: the reserved bytes, the live bytes, and
+/// details specific to the policy of the space, such as the holes in Immix blocks, the free cells
+/// of each size class in mark-sweep spaces, and the free extents in the free list of the page
+/// resource. See [`crate::util::fragmentation`].
+///
+/// The numbers are as of the last GC. They are computed by walking the metadata of each space, so
+/// this method is not cheap. If mutators are allocating at the same time, the numbers may be
+/// inconsistent. A recommended timing to call this method is at the end of a GC (e.g. when the
+/// runtime is about to resume threads), or when an allocation fails with an out-of-memory error.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn fragmentation_report<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+) -> Vec<crate::util::fragmentation::SpaceReport> {
+    mmtk.fragmentation_report()
+}
+
+/// Return the report of [`fragmentation_report`] computed at the end of the last GC. This is only
+/// computed if the option `fragmentation_report` is enabled. Otherwise, or before the first GC,
+/// this returns an empty vector.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn last_fragmentation_report<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+) -> Vec<crate::util::fragmentation::SpaceReport> {
+    mmtk.last_fragmentation_report.lock().unwrap().clone()
+}
+
 /// Return the starting address of the heap. *Note that currently MMTk uses
 /// a fixed address range as heap.*
 pub fn starting_heap_address() -> Address {
@@ -568,11 +675,118 @@ pub fn total_bytes<VM: VMBinding>(mmtk: &MMTK<VM>) -> usize {
 
 /// Trigger a garbage collection as requested by the user.
 ///
+/// The request may be ignored if the option `ignore_system_gc` is set, or if collection is
+/// disabled.  Requests of different kinds made before the GC starts are merged, and the GC does
+/// what all of them ask for.
+///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 /// * `tls`: The thread that triggers this collection request.
-pub fn handle_user_collection_request<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMMutatorThread) {
-    mmtk.handle_user_collection_request(tls, false, false);
+/// * `kind`: The kind of GC requested, e.g. [`UserCollectionKind::FullCompact`] to compact the
+///   heap before taking a snapshot.
+pub fn handle_user_collection_request<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMMutatorThread,
+    kind: UserCollectionKind,
+) {
+    mmtk.handle_user_collection_request(tls, false, kind);
+}
+
+/// Get the soft reference clock, in milliseconds.  When the application accesses a soft reference,
+/// the binding records the clock in the reference, and reports it in
+/// [`crate::vm::ReferenceGlue::get_soft_reference_timestamp`].  The clock only matters if the option
+/// `soft_reference_policy` is `LRU`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn soft_reference_clock<VM: VMBinding>(mmtk: &MMTK<VM>) -> u64 {
+    mmtk.reference_processors.soft_reference_clock()
+}
+
+/// Inform MMTk that the application is idle until `deadline`, for example when a service is
+/// waiting for requests. MMTk uses the idle time to run a full heap GC, so the application does
+/// not carry a full nursery and garbage in the mature space into the next burst of allocation.
+///
+/// The GC is only run if there may be garbage to reclaim since the last GC, and if it is expected
+/// to finish before `deadline`, based on the duration of the last full heap GC. If a GC is run,
+/// the calling mutator is blocked with `Collection::block_for_gc` until the GC finishes. This is
+/// not affected by the option `ignore_system_gc`. The GC is not interrupted if it exceeds the
+/// deadline.
+///
+/// Return `true` if a GC was run, `false` otherwise.
+///
+/// See also the option `periodic_gc_interval`, which triggers a full heap GC if there has been no
+/// GC for a while.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The mutator thread that is idle.
+/// * `deadline`: The time when the application expects to become busy again.
+pub fn notify_idle<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMMutatorThread,
+    deadline: std::time::Instant,
+) -> bool {
+    mmtk.notify_idle(tls, deadline)
+}
+
+/// Change the lower and upper bound of the heap size at run time, for example when the memory
+/// limit of the container is changed. The new bounds are used by the current GC trigger from the
+/// next allocation poll. For `FixedHeapSize`, the heap size is set to `max`, and `min` is ignored.
+/// For `DynamicHeapSize`, the current heap size is clamped to the new bounds. The bounds cannot be
+/// changed for `Delegated`.
+///
+/// If `max` is below the current heap usage, a full heap collection is triggered, and the calling
+/// mutator is blocked until the GC finishes. If the reserved pages are still above `max` after the
+/// GC, or if GC cannot be triggered, the request is rejected and the bounds are unchanged.
+///
+/// Return `true` if the bounds are changed, `false` if the request is rejected.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The mutator thread that changes the heap size.
+/// * `min`: The new lower bound of the heap size in bytes.
+/// * `max`: The new upper bound of the heap size in bytes.
+pub fn set_heap_size_bounds<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMMutatorThread,
+    min: usize,
+    max: usize,
+) -> bool {
+    mmtk.set_heap_size_bounds(tls, min, max)
+}
+
+/// Find out why an object is alive. This is intended for debugging memory leaks.
+///
+/// This stops the world like a GC, scans roots with `Scanning::scan_roots_in_mutator_thread` and
+/// `Scanning::scan_vm_specific_roots`, and walks the object graph breadth-first from the roots
+/// using `Scanning::scan_object` until it finds `max_paths` paths to `object`. It does not move,
+/// mark or reclaim any object. The calling mutator is blocked with `Collection::block_for_gc`
+/// until the query is done, so the binding must have called `initialize_collection` before.
+///
+/// The paths are returned from the shortest to the longest. Each path records the kind of its
+/// root, and the field of each object that leads to the next object. An empty vector is returned
+/// if the object is not reachable from any root.
+///
+/// If a GC is requested at the same time, the GC is done first, and the query is done in the next
+/// pause.
+///
+/// Only one query can be in progress at a time. If another mutator is making a query, this
+/// returns [`QueryInProgress`] without blocking,
+/// and the binding may try again later.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The mutator thread that makes the query.
+/// * `object`: The object to find the paths to.
+/// * `max_paths`: The maximum number of paths to return.
+pub fn find_retention_paths<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMMutatorThread,
+    object: ObjectReference,
+    max_paths: usize,
+) -> Result<Vec<RetentionPath<VM::VMEdge>>, QueryInProgress> {
+    mmtk.find_retention_paths(tls, object, max_paths)
 }
 
 /// Is the object alive?
@@ -630,6 +844,66 @@ pub fn is_mmtk_object(addr: Address) -> bool {
     SFT_MAP.get_checked(addr).is_mmtk_object(addr)
 }
 
+/// Find the object that `internal_ptr` points into, if any.  This is the counterpart of
+/// [`is_mmtk_object`] for internal (interior) pointers, and is useful for conservative scanning and
+/// debugging.
+///
+/// The search walks the valid object (VO) bits backwards from `internal_ptr` for at most
+/// `max_search_bytes` bytes, and validates the nearest object it finds with
+/// [`crate::vm::ObjectModel::get_current_size`].  If `internal_ptr` points to an object reference
+/// or into the storage of an object (from [`crate::vm::ObjectModel::ref_to_object_start`] to the
+/// end of the object), the object reference is returned.  Return `None` otherwise.
+///
+/// The VO bit of an object is at its [`crate::vm::ObjectModel::ref_to_address`].  Pointers into
+/// the object before that address are resolved as long as
+/// [`crate::vm::ObjectModel::OBJECT_REF_OFFSET_LOWER_BOUND`] is the actual offset of the object
+/// reference from the object start.  Like `is_mmtk_object`, the object returned may be dead if the
+/// VO bits of dead objects have not been cleared yet.
+///
+/// Arguments:
+/// * `internal_ptr`: An arbitrary address.
+/// * `max_search_bytes`: The maximum number of bytes we search backwards from `internal_ptr`.
+///   The larger it is, the larger objects we can resolve, but the search may take longer.
+#[cfg(feature = "is_mmtk_object")]
+pub fn find_object_from_internal_pointer<VM: VMBinding>(
+    internal_ptr: Address,
+    max_search_bytes: usize,
+) -> Option<ObjectReference> {
+    use crate::mmtk::SFT_MAP;
+    SFT_MAP
+        .get_checked(internal_ptr)
+        .find_object_from_internal_pointer(internal_ptr, max_search_bytes)
+}
+
+/// Conservatively scan a range of memory (such as a native stack or a register dump) for
+/// references to MMTk objects, and report them as roots through `factory`.
+///
+/// Every word in `[start, end)` is treated as an ambiguous root.  A word is recognized if it is an
+/// object reference to an MMTk object (see [`is_mmtk_object`]) or if it is an internal pointer to
+/// an MMTk object (see [`find_object_from_internal_pointer`]), searching at most
+/// [`crate::util::is_mmtk_object::MAX_INTERIOR_POINTER_OFFSET`] bytes backwards.
+/// The objects found are deduplicated and reported by calling
+/// [`RootsWorkFactory::create_process_pinning_roots_work`](crate::vm::RootsWorkFactory::create_process_pinning_roots_work),
+/// so they will not be moved during this GC.  Their children may still be moved.
+///
+/// A VM binding usually calls this function in [`crate::vm::Scanning::scan_roots_in_mutator_thread`]
+/// or [`crate::vm::Scanning::scan_vm_specific_roots`] with the factory it is given.
+///
+/// Arguments:
+/// * `start`: The start of the memory range.  It will be aligned up to the word size.
+/// * `end`: The end (exclusive) of the memory range.
+/// * `factory`: The roots work factory to which the found objects are reported.
+///
+/// Returns statistics about the scan, including the number of ambiguous roots found.
+#[cfg(feature = "is_mmtk_object")]
+pub fn scan_conservative_range<VM: VMBinding>(
+    start: Address,
+    end: Address,
+    factory: &mut impl crate::vm::RootsWorkFactory<VM::VMEdge>,
+) -> crate::util::is_mmtk_object::ConservativeScanStats {
+    crate::util::is_mmtk_object::scan_conservative_range::<VM, _>(start, end, factory)
+}
+
 /// Return true if the `object` lies in a region of memory where
 /// -   only MMTk can allocate into, or
 /// -   only MMTk's delegated memory allocator (such as a malloc implementation) can allocate into
@@ -683,6 +957,9 @@ pub fn is_mapped_address(address: Address) -> bool {
 /// Add a reference to the list of weak references. A binding may
 /// call this either when a weak reference is created, or when a weak reference is traced during GC.
 ///
+/// Once a reference in the list survives a GC, it is not processed in nursery GCs, so the binding
+/// must not change its referent to a nursery object. See [`ReferenceGlue::set_referent`].
+///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 /// * `reff`: The weak reference to add.
@@ -693,6 +970,9 @@ pub fn add_weak_candidate<VM: VMBinding>(mmtk: &MMTK<VM>, reff: ObjectReference)
 /// Add a reference to the list of soft references. A binding may
 /// call this either when a weak reference is created, or when a weak reference is traced during GC.
 ///
+/// Once a reference in the list survives a GC, it is not processed in nursery GCs, so the binding
+/// must not change its referent to a nursery object. See [`ReferenceGlue::set_referent`].
+///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 /// * `reff`: The soft reference to add.
@@ -703,6 +983,9 @@ pub fn add_soft_candidate<VM: VMBinding>(mmtk: &MMTK<VM>, reff: ObjectReference)
 /// Add a reference to the list of phantom references. A binding may
 /// call this either when a weak reference is created, or when a weak reference is traced during GC.
 ///
+/// Once a reference in the list survives a GC, it is not processed in nursery GCs, so the binding
+/// must not change its referent to a nursery object. See [`ReferenceGlue::set_referent`].
+///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 /// * `reff`: The phantom reference to add.
@@ -729,6 +1012,16 @@ pub fn harness_end<VM: VMBinding>(mmtk: &'static MMTK<VM>) {
     mmtk.harness_end();
 }
 
+/// Write the GC timeline recorded so far to the path given by the `timeline_output` option, in the
+/// Chrome Trace Event format. MMTk also writes the timeline at [`harness_end`]. Return `Ok(false)`
+/// without writing anything if the `timeline_output` option is not set.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn write_timeline<VM: VMBinding>(mmtk: &MMTK<VM>) -> std::io::Result<bool> {
+    mmtk.write_timeline()
+}
+
 /// Register a finalizable object. MMTk will retain the liveness of
 /// the object even if it is not reachable from the program.
 /// Note that finalization upon exit is not supported.
@@ -871,6 +1164,11 @@ pub fn add_work_packet<VM: VMBinding, W: GCWork<VM>>(
     bucket: WorkBucketStage,
     packet: W,
 ) {
+    debug_assert!(
+        mmtk.scheduler.is_stage_registered(bucket),
+        "{:?} is not registered",
+        bucket
+    );
     mmtk.scheduler.work_buckets[bucket].add(packet)
 }
 
@@ -886,5 +1184,32 @@ pub fn add_work_packets<VM: VMBinding>(
     bucket: WorkBucketStage,
     packets: Vec<Box<dyn GCWork<VM>>>,
 ) {
+    debug_assert!(
+        mmtk.scheduler.is_stage_registered(bucket),
+        "{:?} is not registered",
+        bucket
+    );
     mmtk.scheduler.work_buckets[bucket].bulk_add(packets)
 }
+
+/// Set the sentinel packet of the given work bucket. When the bucket is open and all the work packets
+/// in it are done, the sentinel packet is executed before the next bucket is opened. A sentinel packet
+/// may add more work packets to the bucket, and set another sentinel. It is typically used for
+/// stages that may iterate, such as processing ephemerons in a custom stage.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `bucket`: Which work bucket to set the sentinel for.
+/// * `packet`: The sentinel packet.
+pub fn set_work_bucket_sentinel<VM: VMBinding, W: GCWork<VM>>(
+    mmtk: &'static MMTK<VM>,
+    bucket: WorkBucketStage,
+    packet: W,
+) {
+    debug_assert!(
+        mmtk.scheduler.is_stage_registered(bucket),
+        "{:?} is not registered",
+        bucket
+    );
+    mmtk.scheduler.work_buckets[bucket].set_sentinel(Box::new(packet))
+}
@@ -3,24 +3,30 @@ use crate::global_state::{GcStatus, GlobalState};
 use crate::plan::gc_requester::GCRequester;
 use crate::plan::CreateGeneralPlanArgs;
 use crate::plan::Plan;
+use crate::plan::UserCollectionKind;
 use crate::policy::sft_map::{create_sft_map, SFTMap};
-use crate::scheduler::GCWorkScheduler;
+use crate::scheduler::{CustomStage, GCWorkScheduler, WorkBucketStage, MAX_CUSTOM_STAGES};
 
 #[cfg(feature = "analysis")]
 use crate::util::analysis::AnalysisManager;
 #[cfg(feature = "extreme_assertions")]
 use crate::util::edge_logger::EdgeLogger;
 use crate::util::finalizable_processor::FinalizableProcessor;
+use crate::util::fragmentation::SpaceReport;
 use crate::util::heap::gc_trigger::GCTrigger;
-use crate::util::heap::layout::vm_layout::VMLayout;
+use crate::util::heap::layout::vm_layout::{vm_layout, VMLayout};
 use crate::util::heap::layout::{self, Mmapper, VMMap};
-use crate::util::heap::HeapMeta;
+use crate::util::heap::{HeapMeta, HeapReservation};
+use crate::util::numa::Numa;
 use crate::util::opaque_pointer::*;
 use crate::util::options::Options;
 use crate::util::reference_processor::ReferenceProcessors;
+use crate::util::retention_path::{QueryInProgress, RetentionPath, RetentionQuery};
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::SanityChecker;
 use crate::util::statistics::stats::Stats;
+use crate::util::timeline::Timeline;
+use crate::util::ObjectReference;
 use crate::vm::ReferenceGlue;
 use crate::vm::VMBinding;
 use std::cell::UnsafeCell;
@@ -28,15 +34,15 @@ use std::default::Default;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
 
 lazy_static! {
     // I am not sure if we should include these mmappers as part of MMTk struct.
     // The considerations are:
     // 1. We need VMMap and Mmapper to create spaces. It is natural that the mappers are not
     //    part of MMTK, as creating MMTK requires these mappers. We could use Rc/Arc for these mappers though.
-    // 2. These mmappers are possibly global across multiple MMTk instances, as they manage the
-    //    entire address space.
-    // TODO: We should refactor this when we know more about how multiple MMTK instances work.
+    // 2. These mmappers are global across multiple MMTk instances, as they manage the
+    //    entire address space.  Each MMTk instance reserves distinct address ranges for its spaces.
 
     /// A global VMMap that manages the mapping of spaces to virtual memory ranges.
     pub static ref VM_MAP: Box<dyn VMMap + Send + Sync> = layout::create_vm_map();
@@ -48,12 +54,15 @@ lazy_static! {
 use crate::util::rust_util::InitializeOnce;
 
 // A global space function table that allows efficient dispatch space specific code for addresses in our heap.
+// It is shared by all MMTk instances, as their spaces are in distinct address ranges.
 pub static SFT_MAP: InitializeOnce<Box<dyn SFTMap>> = InitializeOnce::new();
 
 /// MMTk builder. This is used to set options and other settings before actually creating an MMTk instance.
 pub struct MMTKBuilder {
     /// The options for this instance.
     pub options: Options,
+    /// The stage that each custom stage is placed after. `custom_stages[i]` is for `CustomStage(i)`.
+    custom_stages: Vec<WorkBucketStage>,
 }
 
 impl MMTKBuilder {
@@ -70,6 +79,7 @@ impl MMTKBuilder {
     pub fn new_no_env_vars() -> Self {
         MMTKBuilder {
             options: Options::default(),
+            custom_stages: vec![],
         }
     }
 
@@ -85,14 +95,48 @@ impl MMTKBuilder {
     }
 
     /// Custom VM layout constants. VM bindings may use this function for compressed or 39-bit heap support.
-    /// This function must be called before MMTk::new()
+    /// This function must be called before MMTk::new(). The layout is shared by all MMTk instances in the process,
+    /// so it only takes effect for the first instance, and later instances must use the same layout.
     pub fn set_vm_layout(&mut self, constants: VMLayout) {
         VMLayout::set_custom_vm_layout(constants)
     }
 
+    /// Register a work bucket stage for the VM binding, and return the new stage. The stage is
+    /// opened after the stage `after` is drained, and before the stage that used to follow `after`.
+    /// `after` can be a built-in stop-the-world stage other than `Final`, or a custom stage registered
+    /// earlier. If multiple custom stages are placed after the same stage, they are opened in the
+    /// order of registration.
+    ///
+    /// Like built-in stages, the binding can add work packets to the returned stage with
+    /// [`crate::memory_manager::add_work_packet`], and set a sentinel packet with
+    /// [`crate::memory_manager::set_work_bucket_sentinel`].
+    ///
+    /// This panics if more than [`crate::scheduler::MAX_CUSTOM_STAGES`] stages are registered, or
+    /// if `after` is not a valid predecessor.
+    pub fn add_custom_stage(&mut self, after: WorkBucketStage) -> WorkBucketStage {
+        let valid_predecessor = match after {
+            WorkBucketStage::Unconstrained | WorkBucketStage::Final => false,
+            WorkBucketStage::Custom(custom) => custom.index() < self.custom_stages.len(),
+            _ => true,
+        };
+        assert!(
+            valid_predecessor,
+            "A custom stage cannot be placed after {:?}",
+            after
+        );
+        let index = self.custom_stages.len();
+        assert!(
+            index < MAX_CUSTOM_STAGES,
+            "Cannot register more than {} custom stages",
+            MAX_CUSTOM_STAGES
+        );
+        self.custom_stages.push(after);
+        WorkBucketStage::Custom(CustomStage(index as u8))
+    }
+
     /// Build an MMTk instance from the builder.
     pub fn build<VM: VMBinding>(&self) -> MMTK<VM> {
-        MMTK::new(Arc::new(self.options.clone()))
+        MMTK::new(Arc::new(self.options.clone()), &self.custom_stages)
     }
 }
 
@@ -103,7 +147,19 @@ impl Default for MMTKBuilder {
 }
 
 /// An MMTk instance. MMTk allows multiple instances to run independently, and each instance gives users a separate heap.
-/// *Note that multi-instances is not fully supported yet*
+///
+/// All instances share the virtual memory layout, the SFT map and the memory mappers, and each instance
+/// reserves its spaces in a distinct part of the address range. Multiple instances are only supported with
+/// the 64-bit layout that does not use compressed pointers, and not with the `malloc_mark_sweep` feature.
+/// As the memory mappers are shared, all the instances that are alive at the same time must use the same
+/// mmap strategy, i.e. the same `transparent_hugepages` and `explicit_hugepages` options.
+///
+/// The number of instances that can be alive at the same time is limited. Each space of an instance reserves
+/// [`crate::util::heap::vm_layout::VMLayout::max_space_extent`] bytes of the heap range. The default 64-bit
+/// layout has room for 15 spaces in total, and a plan has at least four spaces (e.g. Immix has the Immix space,
+/// and the immortal, large object and non-moving spaces), so there can be at most three instances. Creating an
+/// instance panics if its spaces do not fit in the part of the heap range that is not reserved by other
+/// instances. The other instances are not affected, and instances can still be created after some are destroyed.
 pub struct MMTK<VM: VMBinding> {
     pub(crate) options: Arc<Options>,
     pub(crate) state: Arc<GlobalState>,
@@ -116,6 +172,13 @@ pub struct MMTK<VM: VMBinding> {
     pub(crate) sanity_checker: Mutex<SanityChecker<VM::VMEdge>>,
     #[cfg(feature = "extreme_assertions")]
     pub(crate) edge_logger: EdgeLogger<VM::VMEdge>,
+    /// The retention path query that is pending or being processed.
+    pub(crate) retention_query: Mutex<Option<RetentionQuery<VM::VMEdge>>>,
+    /// Is the current pause doing the walk for `retention_query`?  This is not in the mutex
+    /// because the last parked GC worker checks it while holding the mutex of the worker monitor.
+    pub(crate) retention_walking: AtomicBool,
+    /// The fragmentation report computed at the end of the last GC (see the option `fragmentation_report`).
+    pub(crate) last_fragmentation_report: Mutex<Vec<SpaceReport>>,
     pub(crate) gc_trigger: Arc<GCTrigger<VM>>,
     pub(crate) gc_requester: Arc<GCRequester<VM>>,
     pub(crate) stats: Arc<Stats>,
@@ -125,6 +188,9 @@ pub struct MMTK<VM: VMBinding> {
     /// Analysis counters. The feature analysis allows us to periodically stop the world and collect some statistics.
     #[cfg(feature = "analysis")]
     pub(crate) analysis_manager: Arc<AnalysisManager<VM>>,
+    /// The address ranges reserved for the spaces of this instance.  They are released when the
+    /// instance is destroyed.
+    heap_reservation: HeapReservation,
 }
 
 unsafe impl<VM: VMBinding> Sync for MMTK<VM> {}
@@ -132,7 +198,7 @@ unsafe impl<VM: VMBinding> Send for MMTK<VM> {}
 
 impl<VM: VMBinding> MMTK<VM> {
     /// Create an MMTK instance. This is not public. Bindings should use [`MMTKBuilder::build`].
-    pub(crate) fn new(options: Arc<Options>) -> Self {
+    pub(crate) fn new(options: Arc<Options>, custom_stages: &[WorkBucketStage]) -> Self {
         // Initialize SFT first in case we need to use this in the constructor.
         // The first call will initialize SFT map. Other calls will be blocked until SFT map is initialized.
         crate::policy::sft_map::SFTRefStorage::pre_use_check();
@@ -144,7 +210,15 @@ impl<VM: VMBinding> MMTK<VM> {
             *options.threads
         };
 
-        let scheduler = GCWorkScheduler::new(num_workers, (*options.thread_affinity).clone());
+        let numa = Numa::new(&options.numa, num_workers, &options.thread_affinity).map(Arc::new);
+
+        let scheduler = GCWorkScheduler::new(
+            num_workers,
+            (*options.thread_affinity).clone(),
+            numa.clone(),
+            Timeline::new(&options, num_workers),
+            custom_stages,
+        );
 
         let state = Arc::new(GlobalState::default());
 
@@ -160,21 +234,60 @@ impl<VM: VMBinding> MMTK<VM> {
 
         // We need this during creating spaces, but we do not use this once the MMTk instance is created.
         // So we do not save it in MMTK. This may change in the future.
-        let mut heap = HeapMeta::new();
-
-        let plan = crate::plan::create_plan(
-            *options.plan,
-            CreateGeneralPlanArgs {
-                vm_map: VM_MAP.as_ref(),
-                mmapper: MMAPPER.as_ref(),
-                options: options.clone(),
-                state: state.clone(),
-                gc_trigger: gc_trigger.clone(),
-                scheduler: scheduler.clone(),
-                stats: &stats,
-                heap: &mut heap,
-            },
-        );
+        // The heap range is shared by all MMTk instances. We reserve our spaces from the part that
+        // previous instances have not reserved, and we hold the lock until the spaces are ready.
+        // We must not panic while holding the lock.  That would poison the lock, and no MMTk
+        // instance could be created or destroyed afterwards.
+        let mut heap = HeapMeta::lock_unreserved();
+        let mut heap_reservation = heap.begin_reservation();
+        if VM_MAP.is_finalized() && !vm_layout().force_use_contiguous_spaces {
+            drop(heap);
+            panic!(
+                "Multiple MMTk instances are only supported with contiguous spaces, i.e. 64-bit \
+                 layouts without compressed pointers"
+            );
+        }
+        let mmap_strategy = crate::util::memory::MmapStrategy::from_options(&options);
+        if let Err(existing) = heap.use_mmap_strategy(mmap_strategy) {
+            drop(heap);
+            panic!(
+                "All MMTk instances share the mmapper and must use the same mmap strategy, but this \
+                 instance uses {:?} and the existing instances use {:?}. Check the \
+                 transparent_hugepages and explicit_hugepages options",
+                mmap_strategy, existing
+            );
+        }
+
+        let plan = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crate::plan::create_plan(
+                *options.plan,
+                CreateGeneralPlanArgs {
+                    vm_map: VM_MAP.as_ref(),
+                    mmapper: MMAPPER.as_ref(),
+                    options: options.clone(),
+                    state: state.clone(),
+                    gc_trigger: gc_trigger.clone(),
+                    scheduler: scheduler.clone(),
+                    numa,
+                    stats: &stats,
+                    heap: &mut heap,
+                },
+            )
+        }));
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(payload) => {
+                // For example, the heap range left is too small for the spaces of the plan.
+                // Give back the address ranges that the spaces have reserved, and unlock.
+                for range in heap.cancel_reservation(heap_reservation) {
+                    if !range.is_empty() {
+                        unsafe { VM_MAP.release_space(range.start, range.end - range.start) };
+                    }
+                }
+                drop(heap);
+                std::panic::resume_unwind(payload);
+            }
+        };
 
         // We haven't finished creating MMTk. No one is using the GC trigger. We cast the arc into a mutable reference.
         {
@@ -187,20 +300,21 @@ impl<VM: VMBinding> MMTK<VM> {
             gc_trigger.set_plan(static_plan);
         }
 
-        // TODO: This probably does not work if we have multiple MMTk instances.
+        // This only boots and finalizes the spaces of this instance.
         VM_MAP.boot();
         // This needs to be called after we create Plan. It needs to use HeapMeta, which is gradually built when we create spaces.
         VM_MAP.finalize_static_space_map(heap.get_discontig_start(), heap.get_discontig_end());
+        heap.end_reservation(&mut heap_reservation);
 
-        if *options.transparent_hugepages {
-            MMAPPER.set_mmap_strategy(crate::util::memory::MmapStrategy::TransparentHugePages);
-        }
+        // The strategy is the same as the existing instances (if any). Set it anyway, as the
+        // strategy of destroyed instances may be different.
+        MMAPPER.set_mmap_strategy(mmap_strategy);
 
         MMTK {
             options,
             state,
             plan: UnsafeCell::new(plan),
-            reference_processors: ReferenceProcessors::new(),
+            reference_processors: ReferenceProcessors::new(num_workers),
             finalizable_processor: Mutex::new(FinalizableProcessor::<
                 <VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType,
             >::new()),
@@ -212,11 +326,15 @@ impl<VM: VMBinding> MMTK<VM> {
             inside_harness: AtomicBool::new(false),
             #[cfg(feature = "extreme_assertions")]
             edge_logger: EdgeLogger::new(),
+            retention_query: Mutex::new(None),
+            retention_walking: AtomicBool::new(false),
+            last_fragmentation_report: Mutex::new(vec![]),
             #[cfg(feature = "analysis")]
             analysis_manager: Arc::new(AnalysisManager::new(stats.clone())),
             gc_trigger,
             gc_requester,
             stats,
+            heap_reservation,
         }
     }
 
@@ -307,12 +425,73 @@ impl<VM: VMBinding> MMTK<VM> {
         self.scheduler.respawn_gc_threads_after_forking(tls);
     }
 
+    /// Prepare an MMTk instance for being destroyed by [`crate::memory_manager::mmtk_destroy`].
+    ///
+    /// This function instructs all GC threads to exit, in the same way as
+    /// [`MMTK::prepare_to_fork`].  If the collection has not been initialized, there are no GC
+    /// threads and this function does nothing.
+    ///
+    /// # Caution!
+    ///
+    /// This function sends an asynchronous message to GC threads and returns immediately.  The VM
+    /// should wait for the underlying native threads of the GC threads to exit before calling
+    /// `mmtk_destroy`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the instance cannot be destroyed, i.e. if it uses the MarkSweep
+    /// plan with the `malloc_mark_sweep` feature.
+    pub fn prepare_to_destroy(&'static self) {
+        self.assert_can_be_destroyed();
+        if !self.state.is_initialized() {
+            return;
+        }
+        probe!(mmtk, prepare_to_destroy);
+        self.scheduler.stop_gc_threads_for_destroying();
+    }
+
+    /// Release all the resources of this MMTk instance.  See [`crate::memory_manager::mmtk_destroy`].
+    pub(crate) unsafe fn destroy(self: Box<Self>) {
+        self.assert_can_be_destroyed();
+        self.scheduler.release_gc_workers();
+
+        // Hold the lock so no MMTk instance is created while we release the address ranges.
+        let mut heap = HeapMeta::lock_unreserved();
+        self.get_plan()
+            .for_each_space(&mut |space| space.release_memory());
+        let sft_map: &dyn crate::policy::sft_map::SFTMap = SFT_MAP.as_ref();
+        self.get_plan()
+            .for_each_space(&mut |space| sft_map.notify_space_destruction(space.as_sft()));
+
+        let heap_reservation = self.heap_reservation.clone();
+        // This drops the spaces and their page resources.
+        drop(self);
+        if heap.release_reservation(heap_reservation) {
+            // No MMTk instance is left.  Reset the VM map so that a new instance starts afresh.
+            VM_MAP.reset();
+        }
+    }
+
+    /// Check that this instance can be destroyed.  A malloc mark sweep space (the MarkSweep plan
+    /// with the `malloc_mark_sweep` feature) gets its memory from the malloc library, and we cannot
+    /// release that memory as a whole.
+    fn assert_can_be_destroyed(&self) {
+        assert!(
+            !(cfg!(feature = "malloc_mark_sweep")
+                && matches!(
+                    *self.options.plan,
+                    crate::util::options::PlanSelector::MarkSweep
+                )),
+            "An MMTk instance that uses MarkSweep with the malloc_mark_sweep feature cannot be destroyed"
+        );
+    }
+
     /// Generic hook to allow benchmarks to be harnessed. MMTk will trigger a GC
     /// to clear any residual garbage and start collecting statistics for the benchmark.
     /// This is usually called by the benchmark harness as its last step before the actual benchmark.
     pub fn harness_begin(&self, tls: VMMutatorThread) {
         probe!(mmtk, harness_begin);
-        self.handle_user_collection_request(tls, true, true);
+        self.handle_user_collection_request(tls, true, UserCollectionKind::Full);
         self.inside_harness.store(true, Ordering::SeqCst);
         self.stats.start_all();
         self.scheduler.enable_stat();
@@ -325,6 +504,18 @@ impl<VM: VMBinding> MMTK<VM> {
         self.stats.stop_all(self);
         self.inside_harness.store(false, Ordering::SeqCst);
         probe!(mmtk, harness_end);
+        if let Err(e) = self.write_timeline() {
+            warn!("Failed to write the GC timeline: {}", e);
+        }
+    }
+
+    /// Write the GC timeline to the path given by the `timeline_output` option.
+    /// Return `Ok(false)` if the option is not set.
+    pub fn write_timeline(&self) -> std::io::Result<bool> {
+        match self.scheduler.timeline.as_ref() {
+            Some(timeline) => timeline.write().map(|_| true),
+            None => Ok(false),
+        }
     }
 
     #[cfg(feature = "sanity")]
@@ -397,12 +588,12 @@ impl<VM: VMBinding> MMTK<VM> {
     /// # Arguments
     /// * `tls`: The mutator thread that requests the GC
     /// * `force`: The request cannot be ignored (except for NoGC)
-    /// * `exhaustive`: The requested GC should be exhaustive. This is also a hint.
+    /// * `kind`: The kind of GC requested. See [`UserCollectionKind`].
     pub fn handle_user_collection_request(
         &self,
         tls: VMMutatorThread,
         force: bool,
-        exhaustive: bool,
+        kind: UserCollectionKind,
     ) {
         use crate::vm::Collection;
         if !self.get_plan().constraints().collects_garbage {
@@ -411,21 +602,151 @@ impl<VM: VMBinding> MMTK<VM> {
         }
 
         if force || !*self.options.ignore_system_gc && VM::VMCollection::is_collection_enabled() {
-            info!("User triggering collection");
-            if exhaustive {
-                if let Some(gen) = self.get_plan().generational() {
-                    gen.force_full_heap_collection();
-                }
+            info!("User triggering collection ({:?})", kind);
+            self.state.request_user_collection(kind);
+            self.gc_requester.request();
+            VM::VMCollection::block_for_gc(tls);
+        }
+    }
+
+    /// The application is idle until `deadline`. Run a full heap GC if it is expected to finish
+    /// before the deadline. See [`crate::memory_manager::notify_idle`].
+    ///
+    /// # Arguments
+    /// * `tls`: The mutator thread that is idle
+    /// * `deadline`: The time when the application expects to become busy again
+    pub fn notify_idle(&self, tls: VMMutatorThread, deadline: Instant) -> bool {
+        use crate::vm::Collection;
+        if !self.get_plan().constraints().collects_garbage
+            || !self.state.is_initialized()
+            || !VM::VMCollection::is_collection_enabled()
+        {
+            return false;
+        }
+
+        if !self
+            .state
+            .may_have_garbage_since_last_gc(self.get_plan().get_reserved_pages())
+        {
+            debug!("Idle: nothing to collect since the last GC");
+            return false;
+        }
+
+        // Use the duration of the last full heap GC as the estimate. If we have not done any full
+        // heap GC yet, we have no idea how long it takes, and we just do it.
+        let estimate = self.state.last_full_heap_gc_duration().unwrap_or_default();
+        if Instant::now() + estimate > deadline {
+            debug!(
+                "Idle: not enough time for a GC (estimated {} ms)",
+                estimate.as_millis()
+            );
+            return false;
+        }
+
+        info!("Idle: triggering collection");
+        self.state.request_user_collection(UserCollectionKind::Full);
+        self.gc_requester.request();
+        VM::VMCollection::block_for_gc(tls);
+        true
+    }
+
+    /// Change the lower and upper bound of the heap size (in bytes) at run time.
+    /// See [`crate::memory_manager::set_heap_size_bounds`].
+    ///
+    /// # Arguments
+    /// * `tls`: The mutator thread that changes the heap size
+    /// * `min`: The new lower bound of the heap size
+    /// * `max`: The new upper bound of the heap size
+    pub fn set_heap_size_bounds(&self, tls: VMMutatorThread, min: usize, max: usize) -> bool {
+        use crate::vm::Collection;
+        if min > max {
+            warn!(
+                "Invalid heap size bounds: min {} is larger than max {}",
+                min, max
+            );
+            return false;
+        }
+
+        let max_pages = crate::util::conversions::bytes_to_pages_up(max);
+        if max_pages < self.get_plan().get_reserved_pages()
+            && self.get_plan().constraints().collects_garbage
+            && self.state.is_initialized()
+            && VM::VMCollection::is_collection_enabled()
+        {
+            // Try to bring the heap usage below the new max heap size.
+            info!(
+                "New max heap size {} is below the current usage. Triggering collection",
+                max
+            );
+            self.handle_user_collection_request(tls, true, UserCollectionKind::Full);
+        }
+
+        let reserved_pages = self.get_plan().get_reserved_pages();
+        if max_pages < reserved_pages {
+            warn!(
+                "New max heap size {} is below the reserved pages {} ({} bytes)",
+                max,
+                reserved_pages,
+                crate::util::conversions::pages_to_bytes(reserved_pages)
+            );
+            return false;
+        }
+
+        self.gc_trigger.set_heap_size_bounds(min, max)
+    }
+
+    /// Find the paths from roots that keep `object` alive. This stops the world, and blocks the
+    /// current mutator until the query is done. Return an error without blocking if another
+    /// query is in progress.
+    /// See [`crate::memory_manager::find_retention_paths`].
+    ///
+    /// # Arguments
+    /// * `tls`: The mutator thread that makes the query
+    /// * `object`: The object to find the paths to
+    /// * `max_paths`: The maximum number of paths to return
+    pub fn find_retention_paths(
+        &self,
+        tls: VMMutatorThread,
+        object: ObjectReference,
+        max_paths: usize,
+    ) -> Result<Vec<RetentionPath<VM::VMEdge>>, QueryInProgress> {
+        use crate::vm::Collection;
+        {
+            let mut query = self.retention_query.lock().unwrap();
+            if query.is_some() {
+                return Err(QueryInProgress);
             }
+            *query = Some(RetentionQuery::new(object, max_paths));
+        }
 
-            self.state
-                .user_triggered_collection
-                .store(true, Ordering::Relaxed);
-            self.gc_requester.request();
+        loop {
+            {
+                let mut query = self.retention_query.lock().unwrap();
+                // The paths are taken after the pause of the walk has finished.
+                if !self.retention_walking.load(Ordering::SeqCst) {
+                    if let Some(paths) = query.as_mut().unwrap().take_paths() {
+                        *query = None;
+                        return Ok(paths);
+                    }
+                    // If a GC was already scheduled when we made the request, we were stopped by
+                    // that GC instead, and we need to request again.  We request while holding the
+                    // lock so that we never request another pause once the walk is scheduled.
+                    self.gc_requester.request_retention_walk();
+                }
+            }
             VM::VMCollection::block_for_gc(tls);
         }
     }
 
+    /// Report the occupancy and fragmentation of each space.
+    /// See [`crate::memory_manager::fragmentation_report`].
+    pub fn fragmentation_report(&self) -> Vec<SpaceReport> {
+        let mut reports = vec![];
+        self.get_plan()
+            .for_each_space(&mut |space| reports.push(space.fragmentation_report()));
+        reports
+    }
+
     /// MMTK has requested stop-the-world activity (e.g., stw within a concurrent gc).
     // This is not used, as we do not have a concurrent plan.
     #[allow(unused)]
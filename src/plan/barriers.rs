@@ -42,6 +42,10 @@ impl BarrierSelector {
 ///
 /// As a performance optimization, the binding may also choose to port the fast-path to the VM side,
 /// and call the slow-path (`object_reference_write_slow`) only if necessary.
+///
+/// Plans that need a load barrier (see [`crate::plan::PlanConstraints::needs_load_barrier`])
+/// require the binding to call `object_reference_read` for every object reference loaded from
+/// the heap, or to port the fast-path to the VM side and call `object_reference_read_slow`.
 pub trait Barrier<VM: VMBinding>: 'static + Send + Downcast {
     fn flush(&mut self) {}
 
@@ -114,6 +118,29 @@ pub trait Barrier<VM: VMBinding>: 'static + Send + Downcast {
     ///
     // TODO: Review any potential use cases for other VM bindings.
     fn object_probable_write(&mut self, _obj: ObjectReference) {}
+
+    /// Subsuming barrier for object reference read.
+    /// Load the object reference from `slot` of `src`, and return the object reference the
+    /// mutator should use, which may be different from the value held in the slot.
+    fn object_reference_read(
+        &mut self,
+        _src: ObjectReference,
+        slot: VM::VMEdge,
+    ) -> Option<ObjectReference> {
+        slot.load()
+    }
+
+    /// Object reference read slow-path call.
+    /// `target` is the object reference loaded from `slot`. Return the object reference the
+    /// mutator should use instead of `target`.
+    fn object_reference_read_slow(
+        &mut self,
+        _src: ObjectReference,
+        _slot: VM::VMEdge,
+        target: ObjectReference,
+    ) -> ObjectReference {
+        target
+    }
 }
 
 impl_downcast!(Barrier<VM> where VM: VMBinding);
@@ -250,3 +277,78 @@ impl<S: BarrierSemantics> Barrier<S::VM> for ObjectBarrier<S> {
         }
     }
 }
+
+/// The slow-path semantics of a load barrier.
+pub trait LoadBarrierSemantics: 'static + Send {
+    type VM: VMBinding;
+
+    /// The per-object metadata checked by the load barrier fast-path. The slow-path is taken if
+    /// the metadata of the loaded object is not zero. The metadata must not be larger than 8 bits.
+    const LOAD_BARRIER_SPEC: MetadataSpec;
+
+    /// Flush thread-local buffers, if any.
+    fn flush(&mut self) {}
+
+    /// Slow-path call for object field read operations. Return the object reference the mutator
+    /// should use instead of `target`.
+    fn object_reference_read_slow(
+        &mut self,
+        src: ObjectReference,
+        slot: <Self::VM as VMBinding>::VMEdge,
+        target: ObjectReference,
+    ) -> ObjectReference;
+}
+
+/// Generic load barrier with a type argument defining its slow-path behaviour.
+/// The fast-path checks the [`LoadBarrierSemantics::LOAD_BARRIER_SPEC`] metadata of the loaded
+/// object, and takes the slow-path if it is not zero.
+pub struct LoadBarrier<S: LoadBarrierSemantics> {
+    semantics: S,
+}
+
+impl<S: LoadBarrierSemantics> LoadBarrier<S> {
+    /// The metadata checked by the fast-path. A binding that inlines the fast-path in JIT-compiled
+    /// code should load this metadata for the loaded object, and call `object_reference_read_slow`
+    /// if the value is not zero.
+    pub const METADATA_SPEC: MetadataSpec = S::LOAD_BARRIER_SPEC;
+    /// The number of bits of the metadata checked by the fast-path.
+    pub const METADATA_NUM_OF_BITS: usize = S::LOAD_BARRIER_SPEC.num_of_bits();
+
+    pub fn new(semantics: S) -> Self {
+        debug_assert!(Self::METADATA_NUM_OF_BITS <= 8);
+        Self { semantics }
+    }
+
+    /// Does the loaded object need the slow-path?
+    fn needs_slow_path(&self, target: ObjectReference) -> bool {
+        S::LOAD_BARRIER_SPEC.load_atomic::<S::VM, u8>(target, None, Ordering::SeqCst) != 0
+    }
+}
+
+impl<S: LoadBarrierSemantics> Barrier<S::VM> for LoadBarrier<S> {
+    fn flush(&mut self) {
+        self.semantics.flush();
+    }
+
+    fn object_reference_read(
+        &mut self,
+        src: ObjectReference,
+        slot: <S::VM as VMBinding>::VMEdge,
+    ) -> Option<ObjectReference> {
+        let target = slot.load()?;
+        if self.needs_slow_path(target) {
+            Some(self.object_reference_read_slow(src, slot, target))
+        } else {
+            Some(target)
+        }
+    }
+
+    fn object_reference_read_slow(
+        &mut self,
+        src: ObjectReference,
+        slot: <S::VM as VMBinding>::VMEdge,
+        target: ObjectReference,
+    ) -> ObjectReference {
+        self.semantics.object_reference_read_slow(src, slot, target)
+    }
+}
@@ -8,6 +8,9 @@ pub struct GCRequester<VM: VMBinding> {
     /// Set by mutators to trigger GC.  It is atomic so that mutators can check if GC has already
     /// been requested efficiently in `poll` without acquiring any mutex.
     request_flag: AtomicBool,
+    /// Set when a GC, rather than only a retention path query, is requested.  It is cleared when
+    /// a GC finishes, but not when a retention path query finishes.
+    gc_flag: AtomicBool,
     scheduler: Arc<GCWorkScheduler<VM>>,
 }
 
@@ -15,6 +18,7 @@ impl<VM: VMBinding> GCRequester<VM> {
     pub fn new(scheduler: Arc<GCWorkScheduler<VM>>) -> Self {
         GCRequester {
             request_flag: AtomicBool::new(false),
+            gc_flag: AtomicBool::new(false),
             scheduler,
         }
     }
@@ -22,6 +26,17 @@ impl<VM: VMBinding> GCRequester<VM> {
     /// Request a GC.  Called by mutators when polling (during allocation) and when handling user
     /// GC requests (e.g. `System.gc();` in Java).
     pub fn request(&self) {
+        self.gc_flag.store(true, Ordering::Relaxed);
+        self.request_stop();
+    }
+
+    /// Request the world to be stopped for a retention path query.  The query is done instead of
+    /// a GC only if no GC is requested.
+    pub(crate) fn request_retention_walk(&self) {
+        self.request_stop();
+    }
+
+    fn request_stop(&self) {
         if self.request_flag.load(Ordering::Relaxed) {
             return;
         }
@@ -34,6 +49,35 @@ impl<VM: VMBinding> GCRequester<VM> {
         }
     }
 
+    /// Request a GC from the last parked GC worker, which will schedule the GC itself.  Unlike
+    /// `request`, this does not notify the scheduler.  Return true if the GC is newly requested,
+    /// or false if a GC has already been requested (e.g. by a mutator).
+    pub(crate) fn request_by_worker(&self) -> bool {
+        let newly_requested = !self.request_flag.swap(true, Ordering::Relaxed);
+        if newly_requested {
+            self.gc_flag.store(true, Ordering::Relaxed);
+        }
+        newly_requested
+    }
+
+    /// Request a GC from the last parked GC worker if a GC was requested but not done, because
+    /// the world was stopped for a retention path query instead, or because the user requested it
+    /// during the last GC.  Return true if the GC is newly requested.
+    pub(crate) fn request_pending_gc_by_worker(&self) -> bool {
+        self.gc_flag.load(Ordering::Relaxed) && !self.request_flag.swap(true, Ordering::Relaxed)
+    }
+
+    /// Return true if a GC, rather than only a retention path query, is requested.
+    pub(crate) fn is_gc_requested(&self) -> bool {
+        self.gc_flag.load(Ordering::Relaxed)
+    }
+
+    /// Forget the GC requests made before mutators were stopped, because the GC has served them.
+    /// Called by a GC worker when a GC has finished, before resuming mutators.
+    pub(crate) fn clear_gc_request(&self) {
+        self.gc_flag.store(false, Ordering::Relaxed);
+    }
+
     /// Clear the "GC requested" flag so that mutators can trigger the next GC.
     /// Called by a GC worker when all mutators have come to a stop.
     pub fn clear_request(&self) {
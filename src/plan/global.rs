@@ -21,6 +21,7 @@ use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
 use crate::util::metadata::side_metadata::SideMetadataSanity;
 use crate::util::metadata::side_metadata::SideMetadataSpec;
+use crate::util::numa::Numa;
 use crate::util::options::Options;
 use crate::util::options::PlanSelector;
 use crate::util::statistics::stats::Stats;
@@ -95,8 +96,7 @@ pub fn create_plan<VM: VMBinding>(
 
     // We have created Plan in the heap, and we won't explicitly move it.
     // Each space now has a fixed address for its lifetime. It is safe now to initialize SFT.
-    let sft_map: &mut dyn crate::policy::sft_map::SFTMap =
-        unsafe { crate::mmtk::SFT_MAP.get_mut() }.as_mut();
+    let sft_map: &dyn crate::policy::sft_map::SFTMap = crate::mmtk::SFT_MAP.as_ref();
     plan.for_each_space(&mut |s| {
         sft_map.notify_space_creation(s.as_sft());
         s.initialize_sft(sft_map);
@@ -365,6 +365,7 @@ pub struct CreateGeneralPlanArgs<'a, VM: VMBinding> {
     pub state: Arc<GlobalState>,
     pub gc_trigger: Arc<crate::util::heap::gc_trigger::GCTrigger<VM>>,
     pub scheduler: Arc<GCWorkScheduler<VM>>,
+    pub numa: Option<Arc<Numa>>,
     pub stats: &'a Stats,
     pub heap: &'a mut HeapMeta,
 }
@@ -398,6 +399,7 @@ impl<'a, VM: VMBinding> CreateSpecificPlanArgs<'a, VM> {
             constraints: self.constraints,
             gc_trigger: self.global_args.gc_trigger.clone(),
             scheduler: self.global_args.scheduler.clone(),
+            numa: self.global_args.numa.clone(),
             options: &self.global_args.options,
             global_state: self.global_args.state.clone(),
         }
@@ -742,3 +744,38 @@ pub enum AllocationSemantics {
     /// Non moving objects will not be moved by GC.
     NonMoving = 6,
 }
+
+/// The kind of a GC requested by the user with [`crate::memory_manager::handle_user_collection_request`].
+/// Plans that cannot do what a kind asks for do the closest thing they can.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserCollectionKind {
+    /// A nursery GC for generational plans, or a full heap GC for other plans.  A generational
+    /// plan may still do a full heap GC if it decides so, or if the option `full_heap_system_gc`
+    /// is set.
+    Nursery,
+    /// A full heap GC.
+    Full,
+    /// A full heap GC that compacts the heap as much as the plan can.  Immix plans defragment
+    /// the whole heap.  Copying plans and MarkCompact always compact in full heap GCs, and
+    /// MarkSweep does a full heap GC without compaction.
+    FullCompact,
+    /// A full heap GC that clears all soft references whose referents are not strongly reachable,
+    /// as in an emergency GC.
+    FullClearSoftRefs,
+}
+
+impl UserCollectionKind {
+    pub(crate) const FULL_HEAP: u8 = 1 << 0;
+    pub(crate) const COMPACT: u8 = 1 << 1;
+    pub(crate) const CLEAR_SOFT_REFS: u8 = 1 << 2;
+
+    /// The requirements of this kind, as a combination of the flags above.
+    pub(crate) fn flags(self) -> u8 {
+        match self {
+            Self::Nursery => 0,
+            Self::Full => Self::FULL_HEAP,
+            Self::FullCompact => Self::FULL_HEAP | Self::COMPACT,
+            Self::FullClearSoftRefs => Self::FULL_HEAP | Self::CLEAR_SOFT_REFS,
+        }
+    }
+}
@@ -173,6 +173,7 @@ impl<VM: VMBinding> Immix<VM> {
                 .load(Ordering::SeqCst),
             plan.base().global_state.is_user_triggered_collection(),
             *plan.base().options.full_heap_system_gc,
+            plan.base().global_state.is_compaction_requested(),
         );
 
         if in_defrag {
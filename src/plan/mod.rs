@@ -15,6 +15,7 @@
 
 mod barriers;
 pub use barriers::BarrierSelector;
+pub use barriers::{LoadBarrier, LoadBarrierSemantics};
 
 pub(crate) mod gc_requester;
 
@@ -27,6 +28,7 @@ pub(crate) use global::CreateGeneralPlanArgs;
 pub(crate) use global::HasSpaces;
 pub use global::Plan;
 pub(crate) use global::PlanTraceObject;
+pub use global::UserCollectionKind;
 
 mod mutator_context;
 pub use mutator_context::Mutator;
@@ -53,6 +55,7 @@ mod semispace;
 
 pub(crate) use generational::global::is_nursery_gc;
 pub(crate) use generational::global::GenerationalPlan;
+pub(crate) use nogc::NoGC;
 
 // Expose plan constraints as public. Though a binding can get them from plan.constraints(),
 // it is possible for performance reasons that they want the constraints as constants.
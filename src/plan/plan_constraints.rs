@@ -28,6 +28,10 @@ pub struct PlanConstraints {
     /// The barrier this plan uses. A binding may check this and know what kind of write barrier is in use
     /// if they would like to implement the barrier fast path in the binding side.
     pub barrier: BarrierSelector,
+    /// Does this plan need a load barrier? If so, the binding must call
+    /// `memory_manager::object_reference_read` for every object reference it loads from the heap,
+    /// or implement the load barrier fast path and call `memory_manager::object_reference_read_slow`.
+    pub needs_load_barrier: bool,
     // the following seems unused for now
     /// True if this plan requires linear scanning. This is unused and may be incorrect.
     pub needs_linear_scan: bool,
@@ -59,6 +63,7 @@ impl PlanConstraints {
             needs_forward_after_liveness: false,
             needs_log_bit: false,
             barrier: BarrierSelector::NoBarrier,
+            needs_load_barrier: false,
             needs_prepare_mutator: true,
         }
     }
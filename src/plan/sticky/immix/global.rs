@@ -347,6 +347,16 @@ impl<VM: VMBinding> StickyImmix<VM> {
         {
             // User triggered collection, and we force full heap for user triggered collection
             true
+        } else if self
+            .immix
+            .common
+            .base
+            .global_state
+            .is_full_heap_collection_requested()
+        {
+            trace!("full heap: requested by user");
+            // The user requested a full heap GC
+            true
         } else if self.next_gc_full_heap.load(Ordering::SeqCst)
             || self
                 .immix
@@ -4,6 +4,10 @@ pub(crate) type TraceKind = u8;
 
 pub const DEFAULT_TRACE: u8 = u8::MAX;
 pub const TRACE_KIND_TRANSITIVE_PIN: u8 = DEFAULT_TRACE - 1;
+/// The trace kind in which the large object space may relocate objects by remapping their pages
+/// (if the `los_page_remapping` option is enabled).  A plan allows it by using this kind for a
+/// trace that moves objects.
+pub const TRACE_KIND_RELOCATE_LOS: u8 = DEFAULT_TRACE - 2;
 
 use crate::plan::ObjectQueue;
 use crate::scheduler::GCWorker;
@@ -8,9 +8,11 @@ use crate::util::linear_scan::{Region, RegionIterator};
 use crate::util::metadata::side_metadata::{MetadataByteArrayRef, SideMetadataSpec};
 #[cfg(feature = "vo_bit")]
 use crate::util::metadata::vo_bit;
+use crate::util::numa::Numa;
 use crate::util::Address;
 use crate::vm::*;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 /// The block allocation state.
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -156,6 +158,26 @@ impl Block {
         byte as usize
     }
 
+    /// Count the marked lines and the holes in the block, like [`Block::sweep`] does.  Unlike
+    /// [`Block::get_holes`], this does not depend on whether the block has been swept.
+    pub fn count_marked_lines_and_holes(&self, line_mark_state: u8) -> (usize, usize) {
+        let mut marked_lines = 0;
+        let mut holes = 0;
+        let mut prev_line_is_marked = true;
+        for line in self.lines() {
+            if line.is_marked(line_mark_state) {
+                marked_lines += 1;
+                prev_line_is_marked = true;
+            } else {
+                if prev_line_is_marked {
+                    holes += 1;
+                }
+                prev_line_is_marked = false;
+            }
+        }
+        (marked_lines, holes)
+    }
+
     /// Initialize a clean block after acquired from page-resource.
     pub fn init(&self, copy: bool) {
         self.set_state(if copy {
@@ -302,47 +324,78 @@ impl Block {
 }
 
 /// A non-block single-linked list to store blocks.
+/// With NUMA, there is one list for each NUMA node.
 pub struct ReusableBlockPool {
-    queue: BlockPool<Block>,
+    queues: Vec<BlockPool<Block>>,
     num_workers: usize,
+    numa: Option<Arc<Numa>>,
 }
 
 impl ReusableBlockPool {
     /// Create empty block list
-    pub fn new(num_workers: usize) -> Self {
+    pub fn new(num_workers: usize, numa: Option<Arc<Numa>>) -> Self {
+        let num_nodes = numa.as_ref().map_or(1, |numa| numa.num_nodes());
         Self {
-            queue: BlockPool::new(num_workers),
+            queues: (0..num_nodes)
+                .map(|_| BlockPool::new(num_workers))
+                .collect(),
             num_workers,
+            numa,
         }
     }
 
     /// Get number of blocks in this list.
     pub fn len(&self) -> usize {
-        self.queue.len()
+        self.queues.iter().map(|queue| queue.len()).sum()
     }
 
-    /// Add a block to the list.
+    /// Add a block to the list of the NUMA node that the block is bound to.
     pub fn push(&self, block: Block) {
-        self.queue.push(block)
+        let node = self
+            .numa
+            .as_ref()
+            .and_then(|numa| numa.node_of_address(block.start()))
+            .unwrap_or(0);
+        self.queues[node].push(block)
     }
 
-    /// Pop a block out of the list.
+    /// Pop a block out of the list.  With NUMA, prefer blocks on the node of the current thread.
     pub fn pop(&self) -> Option<Block> {
-        self.queue.pop()
+        let node = self.numa.as_ref().map_or(0, |numa| numa.current_node());
+        self.pop_from_node(node)
+    }
+
+    /// Pop a block out of the list of the given node, or the lists of the following nodes if it is empty.
+    fn pop_from_node(&self, node: usize) -> Option<Block> {
+        let num_nodes = self.queues.len();
+        (0..num_nodes).find_map(|i| self.queues[(node + i) % num_nodes].pop())
     }
 
     /// Clear the list.
     pub fn reset(&mut self) {
-        self.queue = BlockPool::new(self.num_workers);
+        for queue in self.queues.iter_mut() {
+            *queue = BlockPool::new(self.num_workers);
+        }
     }
 
     /// Iterate all the blocks in the queue. Call the visitor for each reported block.
     pub fn iterate_blocks(&self, mut f: impl FnMut(Block)) {
-        self.queue.iterate_blocks(&mut f);
+        for queue in self.queues.iter() {
+            queue.iterate_blocks(&mut f);
+        }
+    }
+
+    /// Flush the thread-local queues of the current GC worker
+    pub fn flush_current_worker(&self) {
+        for queue in self.queues.iter() {
+            queue.flush_current_worker();
+        }
     }
 
     /// Flush the block queue
     pub fn flush_all(&self) {
-        self.queue.flush_all();
+        for queue in self.queues.iter() {
+            queue.flush_all();
+        }
     }
 }
@@ -63,6 +63,7 @@ impl Defrag {
     }
 
     /// Determine whether the current GC should do defragmentation.
+    #[allow(clippy::too_many_arguments)]
     pub fn decide_whether_to_defrag(
         &self,
         emergency_collection: bool,
@@ -71,13 +72,15 @@ impl Defrag {
         user_triggered: bool,
         exhausted_reusable_space: bool,
         full_heap_system_gc: bool,
+        compaction_requested: bool,
     ) {
         let in_defrag = super::DEFRAG
             && (emergency_collection
                 || (collection_attempts > 1)
                 || !exhausted_reusable_space
                 || super::STRESS_DEFRAG
-                || (collect_whole_heap && user_triggered && full_heap_system_gc));
+                || (collect_whole_heap && user_triggered && full_heap_system_gc)
+                || (collect_whole_heap && compaction_requested));
         // println!("Defrag: {}", in_defrag);
         self.in_defrag_collection
             .store(in_defrag, Ordering::Release)
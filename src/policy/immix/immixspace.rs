@@ -10,10 +10,13 @@ use crate::policy::space::{CommonSpace, Space};
 use crate::util::alloc::allocator::AllocatorContext;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
 use crate::util::copy::*;
+use crate::util::fragmentation::{ImmixReport, PolicyReport, SpaceReport};
 use crate::util::heap::chunk_map::*;
+use crate::util::heap::unswept_chunks::UnsweptChunks;
 use crate::util::heap::BlockPageResource;
 use crate::util::heap::PageResource;
 use crate::util::linear_scan::{Region, RegionIterator};
+use crate::util::memory::MmapStrategy;
 use crate::util::metadata::side_metadata::SideMetadataSpec;
 #[cfg(feature = "vo_bit")]
 use crate::util::metadata::vo_bit;
@@ -31,7 +34,8 @@ use atomic::Ordering;
 use std::sync::{atomic::AtomicU8, atomic::AtomicUsize, Arc};
 
 pub(crate) const TRACE_KIND_FAST: TraceKind = 0;
-pub(crate) const TRACE_KIND_DEFRAG: TraceKind = 1;
+/// The defrag trace.  Large objects may be relocated in this trace, too.
+pub(crate) const TRACE_KIND_DEFRAG: TraceKind = crate::policy::gc_work::TRACE_KIND_RELOCATE_LOS;
 
 pub struct ImmixSpace<VM: VMBinding> {
     common: CommonSpace<VM>,
@@ -54,6 +58,8 @@ pub struct ImmixSpace<VM: VMBinding> {
     scheduler: Arc<GCWorkScheduler<VM>>,
     /// Some settings for this space
     space_args: ImmixSpaceArgs,
+    /// Chunks to sweep after mutators are resumed. `None` if background sweeping is disabled.
+    unswept_chunks: Option<UnsweptChunks>,
 }
 
 /// Some arguments for Immix Space.
@@ -134,6 +140,10 @@ impl<VM: VMBinding> SFT for ImmixSpace<VM> {
     fn is_sane(&self) -> bool {
         true
     }
+    #[cfg(feature = "sanity")]
+    fn sanity_check_object(&self, object: ObjectReference) -> bool {
+        self.is_marked(object)
+    }
     fn initialize_object_metadata(&self, _object: ObjectReference, _alloc: bool) {
         #[cfg(feature = "vo_bit")]
         crate::util::metadata::vo_bit::set_vo_bit::<VM>(_object);
@@ -142,6 +152,19 @@ impl<VM: VMBinding> SFT for ImmixSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        // Objects do not span blocks. Do not search beyond the start of the block.
+        let block_start = Block::align(ptr);
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes.min(ptr - block_start),
+        )
+    }
     fn sft_trace_object(
         &self,
         _queue: &mut VectorObjectQueue,
@@ -165,12 +188,48 @@ impl<VM: VMBinding> Space<VM> for ImmixSpace<VM> {
     fn common(&self) -> &CommonSpace<VM> {
         &self.common
     }
-    fn initialize_sft(&self, sft_map: &mut dyn SFTMap) {
+    fn finish_background_sweeping(&self) -> bool {
+        self.unswept_chunks.as_ref().is_some_and(|unswept| {
+            unswept.sweep_all(|chunk| self.sweep_chunk(Chunk::from_aligned_address(chunk)))
+        })
+    }
+    fn initialize_sft(&self, sft_map: &dyn SFTMap) {
         self.common().initialize_sft(self.as_sft(), sft_map)
     }
     fn release_multiple_pages(&mut self, _start: Address) {
         panic!("immixspace only releases pages enmasse")
     }
+    fn fragmentation_report(&self) -> SpaceReport {
+        let mut immix = ImmixReport::default();
+        let line_mark_state = self.line_mark_state.load(Ordering::Acquire);
+        for chunk in self
+            .chunk_map
+            .all_chunks()
+            .filter(|chunk| self.chunk_map.get(*chunk) == ChunkState::Allocated)
+        {
+            for block in chunk
+                .iter_region::<Block>()
+                .filter(|block| block.get_state() != BlockState::Unallocated)
+            {
+                if super::BLOCK_ONLY {
+                    immix.add_block(0, 0, 0);
+                } else {
+                    let (marked_lines, holes) = block.count_marked_lines_and_holes(line_mark_state);
+                    immix.add_block(Block::LINES, marked_lines, holes);
+                }
+            }
+        }
+        let live_bytes = if super::BLOCK_ONLY {
+            immix.blocks * Block::BYTES
+        } else {
+            immix.marked_lines * Line::BYTES
+        };
+        SpaceReport {
+            live_bytes: Some(live_bytes),
+            policy: PolicyReport::Immix(immix),
+            ..SpaceReport::new(self.get_name(), self.reserved_pages())
+        }
+    }
     fn set_copy_for_sft_trace(&mut self, _semantics: Option<CopySemantics>) {
         panic!("We do not use SFT to trace objects for Immix. set_copy_context() cannot be used.")
     }
@@ -285,38 +344,47 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         vo_bit::helper::validate_config::<VM>();
         let vm_map = args.vm_map;
         let scheduler = args.scheduler.clone();
+        let unswept_chunks = UnsweptChunks::new(args.options);
+        let huge_page_aware = MmapStrategy::from_options(args.options).uses_huge_pages();
         let common =
             CommonSpace::new(args.into_policy_args(true, false, Self::side_metadata_specs()));
+        let numa = common.numa.clone();
+        let mut pr = if common.vmrequest.is_discontiguous() {
+            BlockPageResource::new_discontiguous(Block::LOG_PAGES, vm_map, scheduler.num_workers())
+        } else {
+            BlockPageResource::new_contiguous(
+                Block::LOG_PAGES,
+                common.start,
+                common.extent,
+                vm_map,
+                scheduler.num_workers(),
+            )
+        };
+        pr.huge_page_aware = huge_page_aware;
         ImmixSpace {
-            pr: if common.vmrequest.is_discontiguous() {
-                BlockPageResource::new_discontiguous(
-                    Block::LOG_PAGES,
-                    vm_map,
-                    scheduler.num_workers(),
-                )
-            } else {
-                BlockPageResource::new_contiguous(
-                    Block::LOG_PAGES,
-                    common.start,
-                    common.extent,
-                    vm_map,
-                    scheduler.num_workers(),
-                )
-            },
+            pr,
             common,
             chunk_map: ChunkMap::new(),
             line_mark_state: AtomicU8::new(Line::RESET_MARK_STATE),
             line_unavail_state: AtomicU8::new(Line::RESET_MARK_STATE),
             lines_consumed: AtomicUsize::new(0),
-            reusable_blocks: ReusableBlockPool::new(scheduler.num_workers()),
+            reusable_blocks: ReusableBlockPool::new(scheduler.num_workers(), numa),
             defrag: Defrag::default(),
             // Set to the correct mark state when inititialized. We cannot rely on prepare to set it (prepare may get skipped in nursery GCs).
             mark_state: Self::MARKED_STATE,
             scheduler: scheduler.clone(),
             space_args,
+            unswept_chunks,
         }
     }
 
+    /// Flush the thread-local queues of the current GC worker in BlockPageResource
+    fn flush_current_worker_page_resource(&self) {
+        self.reusable_blocks.flush_current_worker();
+        #[cfg(target_pointer_width = "64")]
+        self.pr.flush_current_worker()
+    }
+
     /// Flush the thread-local queues in BlockPageResource
     pub fn flush_page_resource(&self) {
         self.reusable_blocks.flush_all();
@@ -342,6 +410,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         collection_attempts: usize,
         user_triggered_collection: bool,
         full_heap_system_gc: bool,
+        compaction_requested: bool,
     ) -> bool {
         self.defrag.decide_whether_to_defrag(
             emergency_collection,
@@ -350,6 +419,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             user_triggered_collection,
             self.reusable_blocks.len() == 0,
             full_heap_system_gc,
+            compaction_requested,
         );
         self.defrag.in_defrag()
     }
@@ -360,6 +430,10 @@ impl<VM: VMBinding> ImmixSpace<VM> {
     }
 
     pub fn prepare(&mut self, major_gc: bool, plan_stats: StatsForDefrag) {
+        debug_assert!(self
+            .unswept_chunks
+            .as_ref()
+            .map_or(true, |unswept| unswept.all_swept()));
         if major_gc {
             // Update mark_state
             if VM::VMObjectModel::LOCAL_MARK_BIT_SPEC.is_on_side() {
@@ -456,7 +530,18 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         }
         // Sweep chunks and blocks
         let work_packets = self.generate_sweep_tasks();
-        self.scheduler().work_buckets[WorkBucketStage::Release].bulk_add(work_packets);
+        if let Some(unswept) = self.unswept_chunks.as_ref() {
+            // Sweep after mutators are resumed.
+            unswept.set(
+                self.chunk_map
+                    .all_chunks()
+                    .filter(|chunk| self.chunk_map.get(*chunk) == ChunkState::Allocated)
+                    .map(|chunk| chunk.start()),
+            );
+            self.scheduler().add_background_work(work_packets);
+        } else {
+            self.scheduler().work_buckets[WorkBucketStage::Release].bulk_add(work_packets);
+        }
         if super::DEFRAG {
             self.defrag.release(self);
         }
@@ -486,6 +571,35 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         tasks
     }
 
+    /// Sweep the blocks in a chunk, and set the chunk as free if it has no live blocks.
+    fn sweep_chunk(&self, chunk: Chunk) {
+        let mut histogram = self.defrag.new_histogram();
+        if self.chunk_map.get(chunk) == ChunkState::Allocated {
+            let line_mark_state = if super::BLOCK_ONLY {
+                None
+            } else {
+                Some(self.line_mark_state.load(Ordering::Acquire))
+            };
+            // number of allocated blocks.
+            let mut allocated_blocks = 0;
+            // Iterate over all allocated blocks in this chunk.
+            for block in chunk
+                .iter_region::<Block>()
+                .filter(|block| block.get_state() != BlockState::Unallocated)
+            {
+                if !block.sweep(self, &mut histogram, line_mark_state) {
+                    // Block is live. Increment the allocated block count.
+                    allocated_blocks += 1;
+                }
+            }
+            // Set this chunk as free if there is not live blocks.
+            if allocated_blocks == 0 {
+                self.chunk_map.set(chunk, ChunkState::Free)
+            }
+        }
+        self.defrag.add_completed_mark_histogram(histogram);
+    }
+
     /// Release a block.
     pub fn release_block(&self, block: Block) {
         block.deinit();
@@ -500,6 +614,11 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         }
         self.defrag.notify_new_clean_block(copy);
         let block = Block::from_aligned_address(block_address);
+        if let Some(unswept) = self.unswept_chunks.as_ref() {
+            // The sweeper must not see this block as an unmarked block of the last GC.
+            let chunk = block.chunk();
+            unswept.ensure_swept(chunk.start(), || self.sweep_chunk(chunk));
+        }
         block.init(copy);
         self.chunk_map.set(block.chunk(), ChunkState::Allocated);
         self.lines_consumed
@@ -531,6 +650,11 @@ impl<VM: VMBinding> ImmixSpace<VM> {
 
                 block.init(copy);
                 return Some(block);
+            } else if self.unswept_chunks.as_ref().is_some_and(|unswept| {
+                unswept.sweep_any(|chunk| self.sweep_chunk(Chunk::from_aligned_address(chunk)))
+            }) {
+                // Sweep on demand, and try again with the reusable blocks found by the sweeping.
+                continue;
             } else {
                 return None;
             }
@@ -886,32 +1010,17 @@ struct SweepChunk<VM: VMBinding> {
 
 impl<VM: VMBinding> GCWork<VM> for SweepChunk<VM> {
     fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
-        let mut histogram = self.space.defrag.new_histogram();
-        if self.space.chunk_map.get(self.chunk) == ChunkState::Allocated {
-            let line_mark_state = if super::BLOCK_ONLY {
-                None
-            } else {
-                Some(self.space.line_mark_state.load(Ordering::Acquire))
-            };
-            // number of allocated blocks.
-            let mut allocated_blocks = 0;
-            // Iterate over all allocated blocks in this chunk.
-            for block in self
-                .chunk
-                .iter_region::<Block>()
-                .filter(|block| block.get_state() != BlockState::Unallocated)
-            {
-                if !block.sweep(self.space, &mut histogram, line_mark_state) {
-                    // Block is live. Increment the allocated block count.
-                    allocated_blocks += 1;
-                }
-            }
-            // Set this chunk as free if there is not live blocks.
-            if allocated_blocks == 0 {
-                self.space.chunk_map.set(self.chunk, ChunkState::Free)
+        let space = self.space;
+        let chunk = self.chunk;
+        if let Some(unswept) = space.unswept_chunks.as_ref() {
+            // Background sweeping.  The chunk may have been swept on demand by a mutator.
+            if unswept.try_sweep(chunk.start(), || space.sweep_chunk(chunk)) {
+                // Make the released blocks available to mutators now.
+                space.flush_current_worker_page_resource();
             }
+        } else {
+            space.sweep_chunk(chunk);
         }
-        self.space.defrag.add_completed_mark_histogram(histogram);
         self.epilogue.finish_one_work_packet();
     }
 }
@@ -67,6 +67,17 @@ impl<VM: VMBinding> SFT for ImmortalSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes,
+        )
+    }
     fn sft_trace_object(
         &self,
         queue: &mut VectorObjectQueue,
@@ -91,7 +102,7 @@ impl<VM: VMBinding> Space<VM> for ImmortalSpace<VM> {
         &self.common
     }
 
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap) {
         self.common().initialize_sft(self.as_sft(), sft_map)
     }
 
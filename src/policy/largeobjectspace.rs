@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+
 use atomic::Ordering;
 
 use crate::plan::ObjectQueue;
@@ -6,7 +9,10 @@ use crate::policy::sft::GCWorkerMutRef;
 use crate::policy::sft::SFT;
 use crate::policy::space::{CommonSpace, Space};
 use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::conversions;
+use crate::util::fragmentation::{LargeObjectReport, PolicyReport, SpaceReport};
 use crate::util::heap::{FreeListPageResource, PageResource};
+use crate::util::memory::{self, MmapStrategy};
 use crate::util::metadata;
 use crate::util::opaque_pointer::*;
 use crate::util::treadmill::TreadMill;
@@ -28,34 +34,66 @@ pub struct LargeObjectSpace<VM: VMBinding> {
     mark_state: u8,
     in_nursery_gc: bool,
     treadmill: TreadMill,
+    /// Relocate objects by remapping their pages in defrag traces (the `los_page_remapping` option).
+    page_remapping: bool,
+    /// The strategy for mapping the pages left behind by relocated objects.
+    mmap_strategy: MmapStrategy,
+    /// The objects relocated (or being relocated) in the current GC, by their old references.
+    forwarded: Mutex<HashMap<ObjectReference, Relocation>>,
+    /// Notified when a worker finishes relocating an object in `forwarded`.
+    relocated: Condvar,
+}
+
+/// The entry of an object in the forwarding table of the large object space.
+#[derive(Clone, Copy)]
+enum Relocation {
+    /// A GC worker is moving the pages of the object.
+    InProgress,
+    /// The object has been moved to the new reference.
+    Done(ObjectReference),
 }
 
 impl<VM: VMBinding> SFT for LargeObjectSpace<VM> {
     fn name(&self) -> &str {
         self.get_name()
     }
+    fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        if !self.page_remapping {
+            return None;
+        }
+        match self.forwarded.lock().unwrap().get(&object) {
+            Some(Relocation::Done(new_object)) => Some(*new_object),
+            _ => None,
+        }
+    }
     fn is_live(&self, object: ObjectReference) -> bool {
-        self.test_mark_bit(object, self.mark_state)
+        self.test_mark_bit(object, self.mark_state) || self.get_forwarded_object(object).is_some()
     }
+    // Objects are only moved with page remapping.  Otherwise, they are always pinned.
     #[cfg(feature = "object_pinning")]
-    fn pin_object(&self, _object: ObjectReference) -> bool {
-        false
+    fn pin_object(&self, object: ObjectReference) -> bool {
+        self.page_remapping && VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.pin_object::<VM>(object)
     }
     #[cfg(feature = "object_pinning")]
-    fn unpin_object(&self, _object: ObjectReference) -> bool {
-        false
+    fn unpin_object(&self, object: ObjectReference) -> bool {
+        self.page_remapping && VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.unpin_object::<VM>(object)
     }
     #[cfg(feature = "object_pinning")]
-    fn is_object_pinned(&self, _object: ObjectReference) -> bool {
-        true
+    fn is_object_pinned(&self, object: ObjectReference) -> bool {
+        !self.page_remapping
+            || VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(object)
     }
     fn is_movable(&self) -> bool {
-        false
+        self.page_remapping
     }
     #[cfg(feature = "sanity")]
     fn is_sane(&self) -> bool {
         true
     }
+    #[cfg(feature = "sanity")]
+    fn sanity_check_object(&self, object: ObjectReference) -> bool {
+        self.is_live(object)
+    }
     fn initialize_object_metadata(&self, object: ObjectReference, alloc: bool) {
         let old_value = VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC.load_atomic::<VM, u8>(
             object,
@@ -86,6 +124,17 @@ impl<VM: VMBinding> SFT for LargeObjectSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes,
+        )
+    }
     fn sft_trace_object(
         &self,
         queue: &mut VectorObjectQueue,
@@ -107,7 +156,7 @@ impl<VM: VMBinding> Space<VM> for LargeObjectSpace<VM> {
         &self.pr
     }
 
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap) {
         self.common().initialize_sft(self.as_sft(), sft_map)
     }
 
@@ -115,11 +164,29 @@ impl<VM: VMBinding> Space<VM> for LargeObjectSpace<VM> {
         &self.common
     }
 
+    fn fragmentation_report(&self) -> SpaceReport {
+        let mut los = LargeObjectReport::default();
+        self.treadmill.for_each_object(|object| {
+            los.objects += 1;
+            los.object_bytes += VM::VMObjectModel::get_current_size(object);
+            los.pages += self
+                .pr
+                .pages_at(get_super_page(object.to_object_start::<VM>()));
+        });
+        SpaceReport {
+            live_bytes: Some(los.object_bytes),
+            free_list: Some(self.pr.free_list_report()),
+            policy: PolicyReport::LargeObject(los),
+            ..SpaceReport::new(self.get_name(), self.reserved_pages())
+        }
+    }
+
     fn release_multiple_pages(&mut self, start: Address) {
         self.pr.release_pages(start);
     }
 }
 
+use crate::policy::gc_work::TRACE_KIND_RELOCATE_LOS;
 use crate::scheduler::GCWorker;
 use crate::util::copy::CopySemantics;
 
@@ -131,10 +198,17 @@ impl<VM: VMBinding> crate::policy::gc_work::PolicyTraceObject<VM> for LargeObjec
         _copy: Option<CopySemantics>,
         _worker: &mut GCWorker<VM>,
     ) -> ObjectReference {
-        self.trace_object(queue, object)
+        // Large objects are only relocated in the traces that allow it, if page remapping is enabled.
+        if KIND == TRACE_KIND_RELOCATE_LOS && self.page_remapping {
+            self.trace_object_and_relocate(queue, object)
+        } else {
+            self.trace_object(queue, object)
+        }
     }
+    // This does not know whether page remapping is enabled.  But a plan only uses the kind for a
+    // trace that moves objects in other spaces, so this does not make the plan update more edges.
     fn may_move_objects<const KIND: crate::policy::gc_work::TraceKind>() -> bool {
-        false
+        KIND == TRACE_KIND_RELOCATE_LOS
     }
 }
 
@@ -145,10 +219,16 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
     ) -> Self {
         let is_discontiguous = args.vmrequest.is_discontiguous();
         let vm_map = args.vm_map;
+        let page_remapping = *args.options.los_page_remapping && !protect_memory_on_release;
+        let mmap_strategy = MmapStrategy::from_options(args.options);
         let common = CommonSpace::new(args.into_policy_args(
+            page_remapping,
             false,
-            false,
-            metadata::extract_side_metadata(&[*VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC]),
+            metadata::extract_side_metadata(&[
+                *VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC,
+                #[cfg(feature = "object_pinning")]
+                *VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC,
+            ]),
         ));
         let mut pr = if is_discontiguous {
             FreeListPageResource::new_discontiguous(vm_map)
@@ -162,6 +242,10 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
             mark_state: 0,
             in_nursery_gc: false,
             treadmill: TreadMill::new(),
+            page_remapping,
+            mmap_strategy,
+            forwarded: Mutex::new(HashMap::new()),
+            relocated: Condvar::new(),
         }
     }
 
@@ -180,6 +264,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         if full_heap {
             self.sweep_large_pages(false);
         }
+        self.forwarded.get_mut().unwrap().clear();
     }
     // Allow nested-if for this function to make it clear that test_and_mark() is only executed
     // for the outer condition is met.
@@ -223,6 +308,136 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         object
     }
 
+    /// Trace an object, and relocate it to free pages at a lower address if it is marked for the
+    /// first time.  Return the new reference if the object is relocated.
+    fn trace_object_and_relocate<Q: ObjectQueue>(
+        &self,
+        queue: &mut Q,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        let mut forwarded = self.forwarded.lock().unwrap();
+        // If another worker is relocating the object, wait for it.
+        while let Some(Relocation::InProgress) = forwarded.get(&object) {
+            forwarded = self.relocated.wait(forwarded).unwrap();
+        }
+        if let Some(Relocation::Done(new_object)) = forwarded.get(&object) {
+            return *new_object;
+        }
+        let nursery_object = self.is_in_nursery(object);
+        if (self.in_nursery_gc && !nursery_object) || !self.test_and_mark(object, self.mark_state) {
+            // Not traced in this GC, or marked (and relocated if possible) before.
+            return object;
+        }
+        let new_object = if self.is_pinned(object) {
+            drop(forwarded);
+            object
+        } else {
+            // Other workers that trace the object will wait for the relocation.  We do not hold
+            // the lock while remapping the pages, so other large objects can be traced meanwhile.
+            forwarded.insert(object, Relocation::InProgress);
+            drop(forwarded);
+            let relocated = self.relocate(object);
+            let mut forwarded = self.forwarded.lock().unwrap();
+            let new_object = match relocated {
+                Some(new_object) => {
+                    trace!("LOS object {} is relocated to {}", object, new_object);
+                    forwarded.insert(object, Relocation::Done(new_object));
+                    new_object
+                }
+                None => {
+                    forwarded.remove(&object);
+                    object
+                }
+            };
+            drop(forwarded);
+            self.relocated.notify_all();
+            new_object
+        };
+        self.treadmill.copy_to(object, new_object, nursery_object);
+        if nursery_object && self.common.needs_log_bit {
+            VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
+                .mark_as_unlogged::<VM>(new_object, Ordering::SeqCst);
+        }
+        queue.enqueue(new_object);
+        new_object
+    }
+
+    /// Move a marked object to free pages at a lower address by remapping its pages, and release
+    /// its old pages.  Return the new reference, or `None` if the object is not moved.
+    fn relocate(&self, object: ObjectReference) -> Option<ObjectReference> {
+        let object_start = object.to_object_start::<VM>();
+        let old_start = get_super_page(object_start);
+        let pages = self.pr.pages_at(old_start);
+        let bytes = conversions::pages_to_bytes(pages);
+        let new_start = self.pr.alloc_pages_below(pages, old_start)?;
+        let mapped = self.common.mmapper.ensure_mapped(new_start, pages).and(
+            self.common
+                .metadata
+                .try_map_metadata_space(new_start, bytes),
+        );
+        if let Err(e) = mapped {
+            warn!(
+                "Failed to map {} for relocating {}: {}",
+                new_start, object, e
+            );
+            self.pr.release_pages(new_start);
+            return None;
+        }
+
+        // Read everything we need from the object and its side metadata before its pages move.
+        let new_object = VM::VMObjectModel::get_reference_when_copied_to(
+            object,
+            new_start + (object_start - old_start),
+        );
+        let los_bits = VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC.load_atomic::<VM, u8>(
+            object,
+            None,
+            Ordering::SeqCst,
+        );
+        let unlogged = self.common.needs_log_bit
+            && VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.is_unlogged::<VM>(object, Ordering::SeqCst);
+
+        if let Err(e) = memory::mremap_fixed(old_start, new_start, bytes, self.mmap_strategy) {
+            warn!("Failed to remap {} to {}: {}", old_start, new_start, e);
+            self.pr.release_pages(new_start);
+            return None;
+        }
+
+        // In-header metadata has moved with the pages.  Side metadata has to be copied.
+        if VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC.is_on_side() {
+            VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC.store_atomic::<VM, u8>(
+                new_object,
+                los_bits,
+                None,
+                Ordering::SeqCst,
+            );
+        }
+        if self.common.needs_log_bit && VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.is_on_side() {
+            VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.store_atomic::<VM, u8>(
+                new_object,
+                unlogged as u8,
+                None,
+                Ordering::SeqCst,
+            );
+        }
+        #[cfg(feature = "vo_bit")]
+        {
+            crate::util::metadata::vo_bit::unset_vo_bit::<VM>(object);
+            crate::util::metadata::vo_bit::set_vo_bit::<VM>(new_object);
+        }
+        VM::VMObjectModel::on_object_remapped(object, new_object);
+        self.pr.release_pages(old_start);
+        Some(new_object)
+    }
+
+    fn is_pinned(&self, _object: ObjectReference) -> bool {
+        #[cfg(feature = "object_pinning")]
+        return self.is_object_pinned(_object);
+
+        #[cfg(not(feature = "object_pinning"))]
+        false
+    }
+
     fn sweep_large_pages(&mut self, sweep_nursery: bool) {
         let sweep = |object: ObjectReference| {
             #[cfg(feature = "vo_bit")]
@@ -294,7 +509,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
     }
 
     /// Check if a given object is in nursery
-    fn is_in_nursery(&self, object: ObjectReference) -> bool {
+    pub(crate) fn is_in_nursery(&self, object: ObjectReference) -> bool {
         VM::VMObjectModel::LOCAL_LOS_MARK_NURSERY_SPEC.load_atomic::<VM, u8>(
             object,
             None,
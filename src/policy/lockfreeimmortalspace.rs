@@ -77,6 +77,17 @@ impl<VM: VMBinding> SFT for LockFreeImmortalSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes,
+        )
+    }
     fn sft_trace_object(
         &self,
         _queue: &mut VectorObjectQueue,
@@ -109,7 +120,7 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
         panic!("immortalspace only releases pages enmasse")
     }
 
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap) {
         unsafe { sft_map.eager_initialize(self.as_sft(), self.start, self.total_bytes) };
     }
 
@@ -138,6 +149,15 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
         start
     }
 
+    /// We have to override the default implementation because
+    /// LockFreeImmortalSpace doesn't have a common space
+    unsafe fn release_memory(&self) {
+        crate::policy::space::clear_sft_entries(self.as_sft(), self.start, self.total_bytes);
+        self.metadata
+            .unmap_metadata_space(self.start, self.total_bytes);
+        crate::util::memory::munmap(self.start, self.total_bytes).unwrap();
+    }
+
     /// Get the name of the space
     ///
     /// We have to override the default implementation because
@@ -218,11 +238,7 @@ impl<VM: VMBinding> LockFreeImmortalSpace<VM> {
         };
 
         // Eagerly memory map the entire heap (also zero all the memory)
-        let strategy = if *args.options.transparent_hugepages {
-            MmapStrategy::TransparentHugePages
-        } else {
-            MmapStrategy::Normal
-        };
+        let strategy = MmapStrategy::from_options(args.options);
         crate::util::memory::dzmmap_noreplace(start, aligned_total_bytes, strategy).unwrap();
         if space
             .metadata
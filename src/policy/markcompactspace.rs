@@ -78,6 +78,17 @@ impl<VM: VMBinding> SFT for MarkCompactSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes,
+        )
+    }
 
     fn sft_trace_object(
         &self,
@@ -108,7 +119,7 @@ impl<VM: VMBinding> Space<VM> for MarkCompactSpace<VM> {
         &self.common
     }
 
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap) {
         self.common().initialize_sft(self.as_sft(), sft_map)
     }
 
@@ -376,7 +387,10 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
     }
 
     pub fn compact(&self) {
-        let mut to = Address::ZERO;
+        // If no object is live, the cursor is reset to the start of the first region.
+        let Some((mut to, _)) = self.pr.iterate_allocated_regions().next() else {
+            return;
+        };
         for (from_start, size) in self.pr.iterate_allocated_regions() {
             let from_end = from_start + size;
             for obj in self.linear_scan_objects(from_start..from_end) {
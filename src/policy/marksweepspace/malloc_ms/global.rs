@@ -8,6 +8,7 @@ use crate::policy::sft::SFT;
 use crate::policy::space::CommonSpace;
 use crate::scheduler::GCWorkScheduler;
 use crate::util::heap::gc_trigger::GCTrigger;
+use crate::util::heap::unswept_chunks::UnsweptChunks;
 use crate::util::heap::PageResource;
 use crate::util::malloc::library::{BYTES_IN_MALLOC_PAGE, LOG_BYTES_IN_MALLOC_PAGE};
 use crate::util::malloc::malloc_ms_util::*;
@@ -47,6 +48,8 @@ pub struct MallocSpace<VM: VMBinding> {
     /// Work packet scheduler
     scheduler: Arc<GCWorkScheduler<VM>>,
     gc_trigger: Arc<GCTrigger<VM>>,
+    /// Chunks to sweep after mutators are resumed. `None` if background sweeping is disabled.
+    unswept_chunks: Option<UnsweptChunks>,
     // Mapping between allocated address and its size - this is used to check correctness.
     // Size will be set to zero when the memory is freed.
     #[cfg(debug_assertions)]
@@ -108,6 +111,17 @@ impl<VM: VMBinding> SFT for MallocSpace<VM> {
         debug_assert!(!addr.is_mapped());
         has_object_alloced_by_malloc::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes,
+        )
+    }
 
     fn initialize_object_metadata(&self, object: ObjectReference, _alloc: bool) {
         trace!("initialize_object_metadata for object {}", object);
@@ -145,10 +159,21 @@ impl<VM: VMBinding> Space<VM> for MallocSpace<VM> {
         self.gc_trigger.as_ref()
     }
 
-    fn initialize_sft(&self, _sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn finish_background_sweeping(&self) -> bool {
+        self.unswept_chunks
+            .as_ref()
+            .is_some_and(|unswept| unswept.sweep_all(|chunk| self.sweep_chunk(chunk)))
+    }
+
+    fn initialize_sft(&self, _sft_map: &dyn crate::policy::sft_map::SFTMap) {
         // Do nothing - we will set sft when we get new results from malloc
     }
 
+    unsafe fn release_memory(&self) {
+        // MMTK::destroy() refuses to destroy an instance with a malloc space.
+        unreachable!("The memory allocated by malloc cannot be released when destroying MMTk")
+    }
+
     fn release_multiple_pages(&mut self, _start: Address) {
         unreachable!()
     }
@@ -269,6 +294,7 @@ impl<VM: VMBinding> MallocSpace<VM> {
             },
             scheduler: args.scheduler.clone(),
             gc_trigger: args.gc_trigger,
+            unswept_chunks: UnsweptChunks::new(args.options),
             #[cfg(debug_assertions)]
             active_mem: Mutex::new(HashMap::new()),
             #[cfg(debug_assertions)]
@@ -339,6 +365,16 @@ impl<VM: VMBinding> MallocSpace<VM> {
         if !address.is_zero() {
             let actual_size = get_malloc_usable_size(address, is_offset_malloc);
 
+            // The memory may be freed by a background sweeper from a chunk that is still being
+            // swept.  Make sure the sweeper is done with the chunks before we set any metadata.
+            if let Some(unswept) = self.unswept_chunks.as_ref() {
+                let mut chunk = conversions::chunk_align_down(address);
+                while chunk < address + actual_size {
+                    unswept.ensure_swept(chunk, || self.sweep_chunk(chunk));
+                    chunk += BYTES_IN_CHUNK;
+                }
+            }
+
             // If the side metadata for the address has not yet been mapped, we will map all the side metadata for the range [address, address + actual_size).
             if !is_meta_space_mapped(address, actual_size) {
                 // Map the metadata space for the associated chunk
@@ -458,7 +494,12 @@ impl<VM: VMBinding> MallocSpace<VM> {
         }
     }
 
-    pub fn prepare(&mut self) {}
+    pub fn prepare(&mut self) {
+        debug_assert!(self
+            .unswept_chunks
+            .as_ref()
+            .map_or(true, |unswept| unswept.all_swept()));
+    }
 
     pub fn release(&mut self) {
         use crate::scheduler::WorkBucketStage;
@@ -470,9 +511,11 @@ impl<VM: VMBinding> MallocSpace<VM> {
         // we can assume that the chunk mark metadata is not being accessed by anything else and hence we use
         // non-atomic accesses
         let space = unsafe { &*(self as *const Self) };
+        let mut chunks = vec![];
         while chunk < end {
             if is_chunk_mapped(chunk) && unsafe { is_chunk_marked_unsafe(chunk) } {
                 work_packets.push(Box::new(MSSweepChunk { ms: space, chunk }));
+                chunks.push(chunk);
             }
 
             chunk += BYTES_IN_CHUNK;
@@ -487,7 +530,13 @@ impl<VM: VMBinding> MallocSpace<VM> {
             self.work_live_bytes.store(0, Ordering::SeqCst);
         }
 
-        self.scheduler.work_buckets[WorkBucketStage::Release].bulk_add(work_packets);
+        if let Some(unswept) = self.unswept_chunks.as_ref() {
+            // Sweep after mutators are resumed.
+            unswept.set(chunks);
+            self.scheduler.add_background_work(work_packets);
+        } else {
+            self.scheduler.work_buckets[WorkBucketStage::Release].bulk_add(work_packets);
+        }
     }
 
     pub fn end_of_gc(&mut self) {}
@@ -574,7 +623,10 @@ impl<VM: VMBinding> MallocSpace<VM> {
         self.work_live_bytes
             .fetch_add(live_bytes_in_the_chunk, Ordering::SeqCst);
 
-        if completed_packets == self.total_work_packets.load(Ordering::Relaxed) {
+        // With background sweeping, mutators may allocate and free memory while we sweep.
+        if completed_packets == self.total_work_packets.load(Ordering::Relaxed)
+            && self.unswept_chunks.is_none()
+        {
             trace!(
                 "work_live_bytes = {}, live_bytes = {}, active_bytes = {}",
                 self.work_live_bytes.load(Ordering::Relaxed),
@@ -812,6 +864,11 @@ pub struct MSSweepChunk<VM: VMBinding> {
 
 impl<VM: VMBinding> GCWork<VM> for MSSweepChunk<VM> {
     fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
-        self.ms.sweep_chunk(self.chunk);
+        if let Some(unswept) = self.ms.unswept_chunks.as_ref() {
+            // Background sweeping.  The chunk may have been swept on demand by a mutator.
+            unswept.try_sweep(self.chunk, || self.ms.sweep_chunk(self.chunk));
+        } else {
+            self.ms.sweep_chunk(self.chunk);
+        }
     }
 }
@@ -208,6 +208,42 @@ impl Block {
         !self.load_free_list().is_zero()
     }
 
+    /// The number of cells in the block.
+    pub fn cells(&self) -> usize {
+        Block::BYTES / self.load_block_cell_size()
+    }
+
+    /// Count the cells that have an object marked in the last GC.  This does not depend on the
+    /// free list, which is stale until the block is swept.  Like `naive_brute_force_sweep`, we look
+    /// for a mark bit at each possible object reference in a cell.
+    pub fn count_marked_cells<VM: VMBinding>(&self) -> usize {
+        use crate::util::constants::MIN_OBJECT_SIZE;
+
+        let cell_size = self.load_block_cell_size();
+        let mut marked_cells = 0;
+        let mut cell = self.start();
+        while cell + cell_size <= self.end() {
+            let mut cursor = cell;
+            while cursor < cell + cell_size {
+                // About unsafe: We know cursor plus an offset cannot be 0.
+                let potential_object_ref = unsafe {
+                    ObjectReference::from_raw_address_unchecked(
+                        cursor + VM::VMObjectModel::OBJECT_REF_OFFSET_LOWER_BOUND,
+                    )
+                };
+                if VM::VMObjectModel::LOCAL_MARK_BIT_SPEC
+                    .is_marked::<VM>(potential_object_ref, Ordering::SeqCst)
+                {
+                    marked_cells += 1;
+                    break;
+                }
+                cursor += MIN_OBJECT_SIZE;
+            }
+            cell += cell_size;
+        }
+        marked_cells
+    }
+
     /// Get block mark state.
     pub fn get_state(&self) -> BlockState {
         let byte = Self::MARK_TABLE.load_atomic::<u8>(self.start(), Ordering::SeqCst);
@@ -22,6 +22,7 @@ use crate::plan::VectorObjectQueue;
 use crate::policy::sft::SFT;
 use crate::policy::space::{CommonSpace, Space};
 use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::fragmentation::{MarkSweepReport, PolicyReport, SpaceReport};
 use crate::util::heap::chunk_map::*;
 use crate::util::linear_scan::Region;
 use crate::util::VMThread;
@@ -78,6 +79,8 @@ pub struct MarkSweepSpace<VM: VMBinding> {
     /// these block lists in the space. These lists are only filled in the release phase,
     /// and will be moved to the abandoned lists above at the end of a GC.
     abandoned_in_gc: Mutex<AbandonedBlockLists>,
+    /// Sweep the abandoned unswept blocks with GC workers after mutators are resumed.
+    background_sweeping: bool,
 }
 
 pub struct AbandonedBlockLists {
@@ -175,6 +178,10 @@ impl<VM: VMBinding> SFT for MarkSweepSpace<VM> {
     fn is_sane(&self) -> bool {
         true
     }
+    #[cfg(feature = "sanity")]
+    fn sanity_check_object(&self, object: ObjectReference) -> bool {
+        self.is_live(object)
+    }
 
     fn initialize_object_metadata(&self, _object: crate::util::ObjectReference, _alloc: bool) {
         #[cfg(feature = "vo_bit")]
@@ -185,6 +192,19 @@ impl<VM: VMBinding> SFT for MarkSweepSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        // Objects do not span blocks. Do not search beyond the start of the block.
+        let block_start = Block::align(ptr);
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes.min(ptr - block_start),
+        )
+    }
 
     fn sft_trace_object(
         &self,
@@ -209,7 +229,7 @@ impl<VM: VMBinding> Space<VM> for MarkSweepSpace<VM> {
         &self.pr
     }
 
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap) {
         self.common().initialize_sft(self.as_sft(), sft_map)
     }
 
@@ -220,6 +240,35 @@ impl<VM: VMBinding> Space<VM> for MarkSweepSpace<VM> {
     fn release_multiple_pages(&mut self, _start: crate::util::Address) {
         todo!()
     }
+
+    fn fragmentation_report(&self) -> SpaceReport {
+        let mut ms = MarkSweepReport::default();
+        for chunk in self
+            .chunk_map
+            .all_chunks()
+            .filter(|chunk| self.chunk_map.get(*chunk) == ChunkState::Allocated)
+        {
+            for block in chunk
+                .iter_region::<Block>()
+                .filter(|block| block.get_state() != BlockState::Unallocated)
+            {
+                if block.load_block_cell_size() != 0 {
+                    let cells = block.cells();
+                    ms.add_block(
+                        block.load_block_cell_size(),
+                        cells,
+                        cells - block.count_marked_cells::<VM>(),
+                    );
+                }
+            }
+        }
+        SpaceReport {
+            live_bytes: Some(ms.used_cell_bytes()),
+            free_list: Some(self.pr.free_list_report()),
+            policy: PolicyReport::MarkSweep(ms),
+            ..SpaceReport::new(self.get_name(), self.reserved_pages())
+        }
+    }
 }
 
 impl<VM: VMBinding> crate::policy::gc_work::PolicyTraceObject<VM> for MarkSweepSpace<VM> {
@@ -254,6 +303,7 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
         let scheduler = args.scheduler.clone();
         let vm_map = args.vm_map;
         let is_discontiguous = args.vmrequest.is_discontiguous();
+        let background_sweeping = *args.options.background_sweeping;
         let local_specs = {
             metadata::extract_side_metadata(&vec![
                 MetadataSpec::OnSide(Block::NEXT_BLOCK_TABLE),
@@ -283,6 +333,7 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
             scheduler,
             abandoned: Mutex::new(AbandonedBlockLists::new()),
             abandoned_in_gc: Mutex::new(AbandonedBlockLists::new()),
+            background_sweeping,
         }
     }
 
@@ -323,8 +374,14 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
             .bulk_add(work_packets);
     }
 
+    /// Return true if blocks are swept in the pause. Otherwise, blocks are swept lazily by
+    /// allocators, and also by GC workers after the pause if background sweeping is enabled.
+    pub fn eager_sweeping(&self) -> bool {
+        cfg!(feature = "eager_sweeping") && !self.background_sweeping
+    }
+
     pub fn release(&mut self) {
-        if cfg!(feature = "eager_sweeping") {
+        if self.eager_sweeping() {
             // For eager sweeping, we have to sweep the lists that are abandoned to these global lists.
             let mut abandoned = self.abandoned.lock().unwrap();
             abandoned.sweep(self);
@@ -334,6 +391,16 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
             let mut abandoned = self.abandoned.lock().unwrap();
             abandoned.sweep_later(self);
         }
+        if self.background_sweeping {
+            // Allocators abandon their blocks to the global lists in the release phase, and the
+            // lists are merged at the end of GC. We sweep them after mutators are resumed.
+            // # Safety: MarkSweepSpace reference is always valid within this collection cycle.
+            let space = unsafe { &*(self as *const Self) };
+            let work_packets: Vec<Box<dyn GCWork<VM>>> = (0..MI_BIN_FULL)
+                .map(|bin| Box::new(SweepAbandonedBlocks { space, bin }) as _)
+                .collect();
+            self.scheduler.add_background_work(work_packets);
+        }
     }
 
     pub fn end_of_gc(&mut self) {
@@ -403,6 +470,31 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
 use crate::scheduler::GCWork;
 use crate::MMTK;
 
+/// Sweep the unswept blocks of a size class in the abandoned block lists. Allocators may take
+/// unswept blocks from the lists and sweep them at the same time.
+struct SweepAbandonedBlocks<VM: VMBinding> {
+    space: &'static MarkSweepSpace<VM>,
+    bin: usize,
+}
+
+impl<VM: VMBinding> GCWork<VM> for SweepAbandonedBlocks<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        loop {
+            let Some(block) = self.space.abandoned.lock().unwrap().unswept[self.bin].pop() else {
+                break;
+            };
+            // We own the block now.  Sweep it without holding the lock.
+            block.sweep::<VM>();
+            let mut abandoned = self.space.abandoned.lock().unwrap();
+            if block.has_free_cells() {
+                abandoned.available[self.bin].push(block);
+            } else {
+                abandoned.consumed[self.bin].push(block);
+            }
+        }
+    }
+}
+
 struct PrepareChunkMap<VM: VMBinding> {
     space: &'static MarkSweepSpace<VM>,
     chunk: Chunk,
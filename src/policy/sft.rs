@@ -63,6 +63,15 @@ pub trait SFT {
     #[cfg(feature = "sanity")]
     fn is_sane(&self) -> bool;
 
+    /// Is the policy-specific metadata of an object, such as its mark bit, in the expected state
+    /// for an object that is reachable at the end of a full-heap GC? The sanity checker will fail
+    /// if this returns false for an object it reaches. Policies whose metadata cannot tell this
+    /// after a GC (e.g. the mark bits are already reset) should keep the default implementation.
+    #[cfg(feature = "sanity")]
+    fn sanity_check_object(&self, _object: ObjectReference) -> bool {
+        true
+    }
+
     /// Is the object managed by MMTk? For most cases, if we find the sft for an object, that means
     /// the object is in the space and managed by MMTk. However, for some spaces, like MallocSpace,
     /// we mark the entire chunk in the SFT table as a malloc space, but only some of the addresses
@@ -78,6 +87,14 @@ pub trait SFT {
     #[cfg(feature = "is_mmtk_object")]
     fn is_mmtk_object(&self, addr: Address) -> bool;
 
+    /// Find the object that `ptr` points into, searching backwards at most `max_search_bytes`.
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference>;
+
     /// Initialize object metadata (in the header, or in the side metadata).
     fn initialize_object_metadata(&self, object: ObjectReference, alloc: bool);
 
@@ -157,6 +174,14 @@ impl SFT for EmptySpaceSFT {
     fn is_mmtk_object(&self, _addr: Address) -> bool {
         false
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        _ptr: Address,
+        _max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        None
+    }
 
     fn initialize_object_metadata(&self, object: ObjectReference, _alloc: bool) {
         panic!(
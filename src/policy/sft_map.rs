@@ -39,7 +39,14 @@ pub trait SFTMap {
     unsafe fn update(&self, space: SFTRawPointer, start: Address, bytes: usize);
 
     /// Notify the SFT map for space creation. `DenseChunkMap` needs to create an entry for the space.
-    fn notify_space_creation(&mut self, _space: SFTRawPointer) {}
+    fn notify_space_creation(&self, _space: SFTRawPointer) {}
+
+    /// Notify the SFT map for space destruction. `DenseChunkMap` removes the entry for the space,
+    /// so the entry can be reused by a new space.
+    ///
+    /// # Safety
+    /// The SFT entries for the space must have been cleared, and the space must not be used any more.
+    unsafe fn notify_space_destruction(&self, _space: SFTRawPointer) {}
 
     /// Eagerly initialize the SFT table. For most implementations, it could be the same as update().
     /// However, we need this as a seprate method for SFTDenseChunkMap, as it needs to map side metadata first
@@ -49,7 +56,7 @@ pub trait SFTMap {
     /// The address must have a valid SFT entry in the map. Usually we know this if the address is from an object reference, or from our space address range.
     /// Otherwise, the caller should check with `has_sft_entry()` before calling this method.
     unsafe fn eager_initialize(
-        &mut self,
+        &self,
         space: *const (dyn SFT + Sync + 'static),
         start: Address,
         bytes: usize,
@@ -306,6 +313,7 @@ mod dense_chunk_map {
     use crate::util::metadata::side_metadata::*;
     use std::collections::HashMap;
     use std::sync::atomic::Ordering;
+    use std::sync::Mutex;
 
     /// SFTDenseChunkMap is a small table. It has one entry for each space in the table, and use
     /// side metadata to record the index for each chunk. This works for both 32 bits and 64 bits.
@@ -317,24 +325,25 @@ mod dense_chunk_map {
     /// will be costly in terms of memory. In this case, the dense chunk map is a good solution.
     pub struct SFTDenseChunkMap {
         /// The dense table, one entry per space. We use side metadata to store the space index for each chunk.
-        /// 0 is EMPTY_SPACE_SFT.
+        /// 0 is EMPTY_SPACE_SFT. The table has an entry for every possible index, and unused entries are
+        /// EMPTY_SPACE_SFT, so it is never resized while other MMTk instances are reading it.
         sft: Vec<SFTRefStorage>,
-        /// A map from space name (assuming they are unique) to their index. We use this to know whether we have
-        /// pushed &dyn SFT for a space, and to know its index.
-        index_map: HashMap<String, usize>,
+        /// A map from the address of a space to its index. We use this to know whether we have
+        /// pushed &dyn SFT for a space, and to know its index. We cannot use space names, as
+        /// spaces of different MMTk instances may have the same name.
+        index_map: Mutex<HashMap<usize, usize>>,
+        /// Indices that are not used by any space. Destroyed spaces return their indices here.
+        /// When both locks are needed, `index_map` is locked first.
+        free_indices: Mutex<Vec<usize>>,
     }
 
     unsafe impl Sync for SFTDenseChunkMap {}
 
     impl SFTMap for SFTDenseChunkMap {
         fn has_sft_entry(&self, addr: Address) -> bool {
-            if SFT_DENSE_CHUNK_MAP_INDEX.is_mapped(addr) {
-                let index = Self::addr_to_index(addr);
-                index < self.sft.len() as u8
-            } else {
-                // We haven't mapped side metadata for the chunk, so we do not have an SFT entry for the address.
-                false
-            }
+            // Every index in the side metadata has an entry in the table. But if we haven't mapped side metadata
+            // for the chunk, we do not have an SFT entry for the address.
+            SFT_DENSE_CHUNK_MAP_INDEX.is_mapped(addr)
         }
 
         fn get_side_metadata(&self) -> Option<&SideMetadataSpec> {
@@ -356,20 +365,30 @@ mod dense_chunk_map {
             cell.load()
         }
 
-        fn notify_space_creation(&mut self, space: SFTRawPointer) {
+        fn notify_space_creation(&self, space: SFTRawPointer) {
             // Insert the space into the SFT table, and the SFT map.
 
-            let space_name = unsafe { &*space }.name().to_string();
+            let mut index_map = self.index_map.lock().unwrap();
             // We shouldn't have this space in our map yet. Otherwise, this method is called multiple times for the same space.
-            assert!(!self.index_map.contains_key(&space_name));
-            // Index for the space
-            let index = self.sft.len();
-            // Insert to hashmap and vec
-            self.sft.push(SFTRefStorage::new(space));
-            self.index_map.insert(space_name, index);
+            assert!(!index_map.contains_key(&Self::space_key(space)));
+            let index = self
+                .free_indices
+                .lock()
+                .unwrap()
+                .pop()
+                .expect("Too many spaces for SFTDenseChunkMap");
+            self.sft[index].store(space);
+            index_map.insert(Self::space_key(space), index);
+        }
+
+        unsafe fn notify_space_destruction(&self, space: SFTRawPointer) {
+            let mut index_map = self.index_map.lock().unwrap();
+            let index = index_map.remove(&Self::space_key(space)).unwrap();
+            self.sft[index].store(&EMPTY_SPACE_SFT as _);
+            self.free_indices.lock().unwrap().push(index);
         }
 
-        unsafe fn eager_initialize(&mut self, space: SFTRawPointer, start: Address, bytes: usize) {
+        unsafe fn eager_initialize(&self, space: SFTRawPointer, start: Address, bytes: usize) {
             let context = SideMetadataContext {
                 global: vec![SFT_DENSE_CHUNK_MAP_INDEX],
                 local: vec![],
@@ -387,7 +406,12 @@ mod dense_chunk_map {
             start: Address,
             bytes: usize,
         ) {
-            let index: u8 = *self.index_map.get((*space).name()).unwrap() as u8;
+            let index: u8 = *self
+                .index_map
+                .lock()
+                .unwrap()
+                .get(&Self::space_key(space))
+                .unwrap() as u8;
 
             // Iterate through the chunks and record the space index in the side metadata.
             let first_chunk = conversions::chunk_align_down(start);
@@ -422,13 +446,25 @@ mod dense_chunk_map {
         const EMPTY_SFT_INDEX: u8 = 0;
 
         pub fn new() -> Self {
+            let table_size = u8::MAX as usize + 1;
             Self {
-                // Empty space is at index 0
-                sft: vec![SFTRefStorage::default()],
-                index_map: HashMap::new(),
+                sft: std::iter::repeat_with(SFTRefStorage::default)
+                    .take(table_size)
+                    .collect(),
+                index_map: Mutex::new(HashMap::new()),
+                // Empty space is at index 0. Hand out the other indices from the lowest.
+                free_indices: Mutex::new(
+                    (Self::EMPTY_SFT_INDEX as usize + 1..table_size)
+                        .rev()
+                        .collect(),
+                ),
             }
         }
 
+        fn space_key(space: SFTRawPointer) -> usize {
+            space as *const () as usize
+        }
+
         pub fn addr_to_index(addr: Address) -> u8 {
             SFT_DENSE_CHUNK_MAP_INDEX.load_atomic::<u8>(addr, Ordering::Relaxed)
         }
@@ -22,6 +22,7 @@ use crate::mmtk::SFT_MAP;
 use crate::policy::sft::EMPTY_SFT_NAME;
 use crate::policy::sft::SFT;
 use crate::util::copy::*;
+use crate::util::fragmentation::SpaceReport;
 use crate::util::heap::gc_trigger::GCTrigger;
 use crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK;
 use crate::util::heap::layout::Mmapper;
@@ -29,6 +30,7 @@ use crate::util::heap::layout::VMMap;
 use crate::util::heap::space_descriptor::SpaceDescriptor;
 use crate::util::heap::HeapMeta;
 use crate::util::memory;
+use crate::util::numa::Numa;
 use crate::vm::VMBinding;
 
 use std::marker::PhantomData;
@@ -45,7 +47,7 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
     /// Initialize entires in SFT map for the space. This is called when the Space object
     /// has a non-moving address, as we will use the address to set sft.
     /// Currently after we create a boxed plan, spaces in the plan have a non-moving address.
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap);
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap);
 
     /// A check for the obvious out-of-memory case: if the requested size is larger than
     /// the heap size, it is definitely an OOM. We would like to identify that, and
@@ -169,6 +171,13 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
                         map_sidemetadata();
                     }
 
+                    // Bind new chunks to the NUMA node of the current thread before they are touched.
+                    if res.new_chunk {
+                        if let Some(numa) = self.common().numa.as_ref() {
+                            numa.bind_chunks(res.start, bytes);
+                        }
+                    }
+
                     // TODO: Concurrent zeroing
                     if self.common().zeroed {
                         memory::zero(res.start, bytes);
@@ -325,12 +334,60 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
 
     fn release_multiple_pages(&mut self, start: Address);
 
+    /// Sweep the memory that is left for GC workers to sweep after mutators are resumed (see the
+    /// option `background_sweeping`), and wait for the sweeping in progress.  Memory is not
+    /// accounted as free until it is swept.  Return true if anything was not swept yet.
+    fn finish_background_sweeping(&self) -> bool {
+        false
+    }
+
+    /// Report the occupancy and fragmentation of the space.  Policies that know more than the
+    /// reserved pages should override this.  See [`crate::memory_manager::fragmentation_report`].
+    fn fragmentation_report(&self) -> SpaceReport {
+        SpaceReport::new(self.get_name(), self.reserved_pages())
+    }
+
     /// What copy semantic we should use for this space if we copy objects from this space.
     /// This is only needed for plans that use SFTProcessEdges
     fn set_copy_for_sft_trace(&mut self, _semantics: Option<CopySemantics>) {
         panic!("A copying space should override this method")
     }
 
+    /// Release the memory of the space and its side metadata, and clear the SFT entries and the VM
+    /// map entries of the space, so that its address range can be used by new spaces.  This is
+    /// called when the MMTk instance is destroyed.
+    ///
+    /// # Safety
+    ///
+    /// The space must not be used after this call.
+    unsafe fn release_memory(&self) {
+        let common = self.common();
+        let mut ranges = vec![];
+        if common.contiguous {
+            ranges.push((common.start, common.extent));
+        } else {
+            let mut region = self
+                .get_page_resource()
+                .common()
+                .get_head_discontiguous_region();
+            while !region.is_zero() {
+                ranges.push((region, common.vm_map().get_contiguous_region_size(region)));
+                region = common.vm_map().get_next_contiguous_region(region);
+            }
+        }
+        for (start, extent) in ranges {
+            clear_sft_entries(self.as_sft(), start, extent);
+            common.metadata.unmap_metadata_space(start, extent);
+            common
+                .mmapper
+                .unmap(start, conversions::bytes_to_pages_up(extent));
+        }
+        // The chunks of discontiguous spaces are released when the VM map is reset.
+        if common.contiguous {
+            common.vm_map().release_space(common.start, common.extent);
+        }
+    }
+
     /// Ensure that the current space's metadata context does not have any issues.
     /// Panics with a suitable message if any issue is detected.
     /// It also initialises the sanity maps which will then be used if the `extreme_assertions` feature is active.
@@ -346,6 +403,28 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
     }
 }
 
+/// Clear the SFT entries that point to `sft` in the address range.
+///
+/// # Safety
+///
+/// The space of `sft` must not be used any more.
+pub(crate) unsafe fn clear_sft_entries(
+    sft: &(dyn SFT + Sync + 'static),
+    start: Address,
+    extent: usize,
+) {
+    let sft_ptr = sft as *const _ as *const ();
+    let mut chunk = start.align_down(BYTES_IN_CHUNK);
+    while chunk < start + extent {
+        if SFT_MAP.has_sft_entry(chunk)
+            && SFT_MAP.get_checked(chunk) as *const _ as *const () == sft_ptr
+        {
+            SFT_MAP.clear(chunk);
+        }
+        chunk += BYTES_IN_CHUNK;
+    }
+}
+
 /// Print the VM map for a space.
 /// Space needs to be object-safe, so it cannot have methods that use extra generic type paramters. So this method is placed outside the Space trait.
 /// This method can be invoked on a &dyn Space (space.as_space() will return &dyn Space).
@@ -439,6 +518,8 @@ pub struct CommonSpace<VM: VMBinding> {
 
     pub gc_trigger: Arc<GCTrigger<VM>>,
     pub global_state: Arc<GlobalState>,
+    /// NUMA-aware placement of memory. `None` if the `numa` option is disabled.
+    pub(crate) numa: Option<Arc<Numa>>,
 
     p: PhantomData<VM>,
 }
@@ -463,6 +544,7 @@ pub struct PlanCreateSpaceArgs<'a, VM: VMBinding> {
     pub constraints: &'a PlanConstraints,
     pub gc_trigger: Arc<GCTrigger<VM>>,
     pub scheduler: Arc<GCWorkScheduler<VM>>,
+    pub numa: Option<Arc<Numa>>,
     pub options: &'a Options,
     pub global_state: Arc<GlobalState>,
 }
@@ -507,6 +589,7 @@ impl<VM: VMBinding> CommonSpace<VM> {
             },
             acquire_lock: Mutex::new(()),
             global_state: args.plan_args.global_state,
+            numa: args.plan_args.numa,
             p: PhantomData,
         };
 
@@ -599,7 +682,7 @@ impl<VM: VMBinding> CommonSpace<VM> {
     pub fn initialize_sft(
         &self,
         sft: &(dyn SFT + Sync + 'static),
-        sft_map: &mut dyn crate::policy::sft_map::SFTMap,
+        sft_map: &dyn crate::policy::sft_map::SFTMap,
     ) {
         // We have to keep this for now: if a space is contiguous, our page resource will NOT consider newly allocated chunks
         // as new chunks (new_chunks = true). In that case, in grow_space(), we do not set SFT when new_chunks = false.
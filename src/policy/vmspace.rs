@@ -67,6 +67,17 @@ impl<VM: VMBinding> SFT for VMSpace<VM> {
     fn is_mmtk_object(&self, addr: Address) -> bool {
         crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
     }
+    #[cfg(feature = "is_mmtk_object")]
+    fn find_object_from_internal_pointer(
+        &self,
+        ptr: Address,
+        max_search_bytes: usize,
+    ) -> Option<ObjectReference> {
+        crate::util::metadata::vo_bit::find_object_from_internal_pointer::<VM>(
+            ptr,
+            max_search_bytes,
+        )
+    }
     fn sft_trace_object(
         &self,
         queue: &mut VectorObjectQueue,
@@ -91,7 +102,7 @@ impl<VM: VMBinding> Space<VM> for VMSpace<VM> {
         &self.common
     }
 
-    fn initialize_sft(&self, sft_map: &mut dyn crate::policy::sft_map::SFTMap) {
+    fn initialize_sft(&self, sft_map: &dyn crate::policy::sft_map::SFTMap) {
         // Initialize sft for current external pages. This method is called at the end of plan creation.
         // So we only set SFT for VM regions that are set by options (we skipped sft initialization for them earlier).
         let vm_regions = self.pr.get_external_pages();
@@ -123,6 +134,16 @@ impl<VM: VMBinding> Space<VM> for VMSpace<VM> {
         unreachable!()
     }
 
+    unsafe fn release_memory(&self) {
+        // The VM space is mapped by the runtime, so we only release its side metadata.
+        for external_pages in self.pr.get_external_pages().iter() {
+            let start = external_pages.start.align_down(BYTES_IN_CHUNK);
+            let size = external_pages.end.align_up(BYTES_IN_CHUNK) - start;
+            crate::policy::space::clear_sft_entries(self.as_sft(), start, size);
+            self.common.metadata.unmap_metadata_space(start, size);
+        }
+    }
+
     fn address_in_space(&self, start: Address) -> bool {
         // The default implementation checks with vm map. But vm map has some assumptions about
         // the address range for spaces and the VM space may break those assumptions (as the space is
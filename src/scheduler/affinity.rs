@@ -59,3 +59,23 @@ fn bind_current_thread_to_core(cpu: CoreId) {
 fn bind_current_thread_to_core(_cpu: CoreId) {
     unimplemented!()
 }
+
+#[cfg(target_os = "linux")]
+/// Bind the current thread to the specified set of cores.
+pub(crate) fn bind_current_thread_to_cores(cpus: &[CoreId]) {
+    use std::mem::MaybeUninit;
+    unsafe {
+        let mut cs = MaybeUninit::zeroed().assume_init();
+        CPU_ZERO(&mut cs);
+        for cpu in cpus {
+            CPU_SET(*cpu as usize, &mut cs);
+        }
+        sched_setaffinity(0, std::mem::size_of::<cpu_set_t>(), &cs);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+/// Bind the current thread to the specified set of cores.
+pub(crate) fn bind_current_thread_to_cores(_cpus: &[CoreId]) {
+    unimplemented!()
+}
@@ -14,6 +14,14 @@ pub struct ScheduleCollection;
 
 impl<VM: VMBinding> GCWork<VM> for ScheduleCollection {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        // A retention path query stops the world like a GC, but does not collect.  If a GC is
+        // also requested, the query waits for the next pause.
+        if !mmtk.gc_requester.is_gc_requested()
+            && crate::util::retention_path::schedule_retention_walk(mmtk)
+        {
+            return;
+        }
+
         // Tell GC trigger that GC started.
         mmtk.gc_trigger.policy.on_gc_start(mmtk);
 
@@ -408,7 +416,9 @@ impl<C: GCWorkContext> GCWork<C::VM> for ScanMutatorRoots<C> {
             unsafe { &mut *(self.0 as *mut _) },
             factory,
         );
-        self.0.flush();
+        if C::FLUSH_MUTATORS {
+            self.0.flush();
+        }
 
         if mmtk.state.inform_stack_scanned(mutators) {
             <C::VM as VMBinding>::VMScanning::notify_initial_thread_scan_complete(
@@ -569,6 +579,17 @@ pub trait ProcessEdgesWork:
             .add_root_edges(self.edges.clone());
     }
 
+    /// Store the root nodes traced by `ProcessRootNode` for sanity GC, like
+    /// `cache_roots_for_sanity_gc` does for root edges.
+    #[cfg(feature = "sanity")]
+    fn cache_root_nodes_for_sanity_gc(&self, nodes: Vec<ObjectReference>) {
+        self.mmtk()
+            .sanity_checker
+            .lock()
+            .unwrap()
+            .add_root_nodes(nodes);
+    }
+
     /// Start the a scan work packet. If SCAN_OBJECTS_IMMEDIATELY, the work packet will be executed immediately, in this method.
     /// Otherwise, the work packet will be added the Closure work bucket and will be dispatched later by the scheduler.
     fn start_or_dispatch_scan_work(&mut self, mut work_packet: impl GCWork<Self::VM>) {
@@ -1049,16 +1070,6 @@ impl<VM: VMBinding, R2OPE: ProcessEdgesWork<VM = VM>, O2OPE: ProcessEdgesWork<VM
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
         trace!("ProcessRootNode");
 
-        #[cfg(feature = "sanity")]
-        {
-            if !mmtk.is_in_sanity() {
-                mmtk.sanity_checker
-                    .lock()
-                    .unwrap()
-                    .add_root_nodes(self.roots.clone());
-            }
-        }
-
         // Because this is a root packet, the objects in this packet will have not been traced, yet.
         //
         // This step conceptually traces the edges from root slots to the objects they point to.
@@ -1074,6 +1085,11 @@ impl<VM: VMBinding, R2OPE: ProcessEdgesWork<VM = VM>, O2OPE: ProcessEdgesWork<VM
                 R2OPE::new(vec![], true, mmtk, WorkBucketStage::PinningRootsTrace);
             process_edges_work.set_worker(worker);
 
+            #[cfg(feature = "sanity")]
+            if !mmtk.is_in_sanity() {
+                process_edges_work.cache_root_nodes_for_sanity_gc(self.roots.clone());
+            }
+
             for object in self.roots.iter().copied() {
                 let new_object = process_edges_work.trace_object(object);
                 debug_assert_eq!(
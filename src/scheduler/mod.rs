@@ -14,13 +14,13 @@ pub use work::GCWork;
 pub(crate) use work::GCWorkContext;
 
 mod work_bucket;
-pub use work_bucket::WorkBucketStage;
+pub use work_bucket::{CustomStage, WorkBucketStage, MAX_CUSTOM_STAGES};
 
 mod worker;
 mod worker_goals;
 mod worker_monitor;
-pub(crate) use worker::current_worker_ordinal;
 pub use worker::GCWorker;
+pub(crate) use worker::{current_worker_ordinal, try_current_worker_ordinal};
 
 pub(crate) mod gc_work;
 pub use gc_work::ProcessEdgesWork;
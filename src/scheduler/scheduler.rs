@@ -9,16 +9,18 @@ use super::worker_monitor::{LastParkedResult, WorkerMonitor};
 use super::*;
 use crate::global_state::GcStatus;
 use crate::mmtk::MMTK;
+use crate::util::numa::Numa;
 use crate::util::opaque_pointer::*;
-use crate::util::options::AffinityKind;
+use crate::util::options::{ActiveGCWorkers, AffinityKind};
 use crate::util::rust_util::array_from_fn;
+use crate::util::timeline::Timeline;
 use crate::vm::Collection;
 use crate::vm::VMBinding;
 use crate::Plan;
 use crossbeam::deque::Steal;
 use enum_map::{Enum, EnumMap};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 pub struct GCWorkScheduler<VM: VMBinding> {
@@ -30,6 +32,14 @@ pub struct GCWorkScheduler<VM: VMBinding> {
     pub(crate) worker_monitor: Arc<WorkerMonitor>,
     /// How to assign the affinity of each GC thread. Specified by the user.
     affinity: AffinityKind,
+    /// NUMA-aware placement of GC workers. `None` if the `numa` option is disabled.
+    numa: Option<Arc<Numa>>,
+    /// The GC timeline. `None` if the `timeline_output` option is not set.
+    pub(crate) timeline: Option<Timeline>,
+    /// The stop-the-world stages, including registered custom stages, in the order they are opened.
+    stage_order: Vec<WorkBucketStage>,
+    /// Work packets to execute after mutators are resumed.  See `add_background_work`.
+    background_work: Mutex<Vec<Box<dyn GCWork<VM>>>>,
 }
 
 // FIXME: GCWorkScheduler should be naturally Sync, but we cannot remove this `impl` yet.
@@ -38,7 +48,13 @@ pub struct GCWorkScheduler<VM: VMBinding> {
 unsafe impl<VM: VMBinding> Sync for GCWorkScheduler<VM> {}
 
 impl<VM: VMBinding> GCWorkScheduler<VM> {
-    pub fn new(num_workers: usize, affinity: AffinityKind) -> Arc<Self> {
+    pub fn new(
+        num_workers: usize,
+        affinity: AffinityKind,
+        numa: Option<Arc<Numa>>,
+        timeline: Option<Timeline>,
+        custom_stages: &[WorkBucketStage],
+    ) -> Arc<Self> {
         let worker_monitor: Arc<WorkerMonitor> = Arc::new(WorkerMonitor::new(num_workers));
         let worker_group = WorkerGroup::new(num_workers);
 
@@ -51,15 +67,17 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         }));
 
         // Set the open condition of each bucket.
+        let stage_order = WorkBucketStage::stw_stage_order(custom_stages);
         {
             let first_stw_stage = WorkBucketStage::first_stw_stage();
+            debug_assert_eq!(stage_order[0], first_stw_stage);
             let mut open_stages: Vec<WorkBucketStage> = vec![first_stw_stage];
-            let stages = (0..WorkBucketStage::LENGTH).map(WorkBucketStage::from_usize);
-            for stage in stages {
-                // Unconstrained is always open.
+            for &stage in stage_order.iter() {
+                // Unconstrained is always open, and it is not in `stage_order`.
                 // The first STW stage (Prepare) will be opened when the world stopped
                 // (i.e. when all mutators are suspended).
-                if stage != WorkBucketStage::Unconstrained && stage != first_stw_stage {
+                // Custom stages that are not registered are never opened.
+                if stage != first_stw_stage {
                     // Other work packets will be opened after previous stages are done
                     // (i.e their buckets are drained and all workers parked).
                     let cur_stages = open_stages.clone();
@@ -78,9 +96,25 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             worker_group,
             worker_monitor,
             affinity,
+            numa,
+            timeline,
+            stage_order,
+            background_work: Mutex::new(vec![]),
         })
     }
 
+    /// Return true if the stage is a built-in stage or a registered custom stage.
+    pub(crate) fn is_stage_registered(&self, stage: WorkBucketStage) -> bool {
+        stage == WorkBucketStage::Unconstrained || self.stage_order.contains(&stage)
+    }
+
+    /// Add work packets that GC workers execute after the current GC, concurrently with mutators.
+    /// They are added to the `Unconstrained` bucket after mutators are resumed.  The next GC will
+    /// not start until they are all done, because a GC only starts when all workers have parked.
+    pub(crate) fn add_background_work(&self, packets: Vec<Box<dyn GCWork<VM>>>) {
+        self.background_work.lock().unwrap().extend(packets);
+    }
+
     pub fn num_workers(&self) -> usize {
         self.worker_group.as_ref().worker_count()
     }
@@ -101,6 +135,21 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         self.worker_monitor.make_request(WorkerGoal::StopForFork);
     }
 
+    /// Ask all GC workers to exit before the MMTk instance is destroyed.  This uses the same
+    /// worker goal as stopping GC workers for forking.
+    pub fn stop_gc_threads_for_destroying(self: &Arc<Self>) {
+        self.worker_group.prepare_surrender_buffer();
+
+        debug!("A mutator is requesting GC threads to stop for destroying MMTk...");
+        self.worker_monitor.make_request(WorkerGoal::StopForFork);
+    }
+
+    /// Drop the `GCWorker` instances of the exited GC workers.  They hold references to the MMTk
+    /// instance, and we drop them before destroying the MMTk instance.
+    pub fn release_gc_workers(&self) {
+        self.worker_group.drop_surrendered_workers();
+    }
+
     /// Surrender the `GCWorker` struct of a GC worker when it exits.
     pub fn surrender_gc_worker(&self, worker: Box<GCWorker<VM>>) {
         let all_surrendered = self.worker_group.surrender_gc_worker(worker);
@@ -124,6 +173,10 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
     /// Resolve the affinity of a thread.
     pub fn resolve_affinity(&self, thread: ThreadId) {
         self.affinity.resolve_affinity(thread);
+        // The `thread_affinity` option takes precedence.  Otherwise pin the worker to its NUMA node.
+        if let (AffinityKind::OsDefault, Some(numa)) = (&self.affinity, &self.numa) {
+            numa.bind_current_worker(thread);
+        }
     }
 
     /// Request a GC to be scheduled.  Called by mutator via `GCRequester`.
@@ -261,16 +314,19 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
     pub(crate) fn update_buckets(&self) -> bool {
         let mut buckets_updated = false;
         let mut new_packets = false;
-        for i in 0..WorkBucketStage::LENGTH {
-            let id = WorkBucketStage::from_usize(i);
-            if id == WorkBucketStage::Unconstrained {
-                continue;
-            }
+        for &id in self.stage_order.iter() {
             let bucket = &self.work_buckets[id];
             let bucket_opened = bucket.update(self);
             buckets_updated = buckets_updated || bucket_opened;
             if bucket_opened {
-                probe!(mmtk, bucket_opened, id);
+                probe!(mmtk, bucket_opened, id.into_usize());
+                if let Some(timeline) = self.timeline.as_ref() {
+                    timeline.record_instant(
+                        crate::scheduler::current_worker_ordinal(),
+                        "BUCKET_OPEN",
+                        Some(("stage", format!("{:?}", id))),
+                    );
+                }
                 new_packets = new_packets || !bucket.is_drained();
                 if new_packets {
                     // Quit the loop. There are already new packets in the newly opened buckets.
@@ -348,15 +404,32 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                 _ => {}
             }
         }
-        // Try steal some packets from any worker
-        for (id, worker_shared) in self.worker_group.workers_shared.iter().enumerate() {
-            if id == worker.ordinal {
-                continue;
-            }
+        // Try steal some packets from any worker.  With NUMA, try workers on the same node first.
+        let mut steal_from = |id: usize| {
+            let worker_shared = &self.worker_group.workers_shared[id];
             match worker_shared.stealer.as_ref().unwrap().steal() {
-                Steal::Success(w) => return Steal::Success(w),
-                Steal::Retry => should_retry = true,
-                _ => {}
+                Steal::Success(w) => Some(w),
+                Steal::Retry => {
+                    should_retry = true;
+                    None
+                }
+                _ => None,
+            }
+        };
+        if let Some(numa) = &self.numa {
+            for &id in numa.steal_order(worker.ordinal) {
+                if let Some(w) = steal_from(id) {
+                    return Steal::Success(w);
+                }
+            }
+        } else {
+            for id in 0..self.worker_group.workers_shared.len() {
+                if id == worker.ordinal {
+                    continue;
+                }
+                if let Some(w) = steal_from(id) {
+                    return Steal::Success(w);
+                }
             }
         }
         if should_retry {
@@ -402,8 +475,11 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             }
 
             let ordinal = worker.ordinal;
-            self.worker_monitor
-                .park_and_wait(ordinal, |goals| self.on_last_parked(worker, goals))?;
+            self.worker_monitor.park_and_wait(
+                ordinal,
+                |goals| self.on_last_parked(worker, goals),
+                || !worker.shared.designated_work.is_empty(),
+            )?;
         }
     }
 
@@ -436,14 +512,29 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                 let found_more_work = self.find_more_work_for_workers();
 
                 if found_more_work {
-                    LastParkedResult::WakeAll
+                    // Surplus workers only wake up for their designated work.
+                    if self.worker_group.has_designated_work() {
+                        LastParkedResult::WakeAllWithDesignatedWork
+                    } else {
+                        LastParkedResult::WakeAll
+                    }
                 } else {
                     // GC finished.
-                    self.on_gc_finished(worker);
+                    let has_background_work = self.on_gc_finished(worker);
 
                     // Clear the current goal
                     goals.on_current_goal_completed();
-                    self.respond_to_requests(worker, goals)
+                    if let Some(timeline) = self.timeline.as_ref() {
+                        timeline.record_instant(worker.ordinal, "GOAL_COMPLETE", None);
+                    }
+                    if has_background_work {
+                        // Let all workers do the background work.  Pending requests will be
+                        // responded to when all workers park again, i.e. after the background work
+                        // is done.
+                        LastParkedResult::WakeAll
+                    } else {
+                        self.respond_to_requests(worker, goals)
+                    }
                 }
             }
             WorkerGoal::StopForFork => {
@@ -464,9 +555,30 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         assert!(goals.current().is_none());
 
         let Some(goal) = goals.poll_next_goal() else {
-            // No requests.  Park this worker, too.
-            return LastParkedResult::ParkSelf;
+            // A GC requested while the world was being stopped for a retention path query, or a
+            // user GC requested during the last GC, has not been done yet.  Do it now.
+            if worker.mmtk.gc_requester.request_pending_gc_by_worker() {
+                goals.set_request(WorkerGoal::Gc);
+                return self.respond_to_requests(worker, goals);
+            }
+
+            // No requests.  Park this worker, too, unless a periodic GC is due.
+            return match self.poll_periodic_gc(worker) {
+                Ok(()) => {
+                    goals.set_request(WorkerGoal::Gc);
+                    self.respond_to_requests(worker, goals)
+                }
+                Err(Some(deadline)) => LastParkedResult::ParkSelfUntil(deadline),
+                Err(None) => LastParkedResult::ParkSelf,
+            };
         };
+        if let Some(timeline) = self.timeline.as_ref() {
+            timeline.record_instant(
+                worker.ordinal,
+                "GOAL_SET",
+                Some(("goal", format!("{:?}", goal))),
+            );
+        }
 
         match goal {
             WorkerGoal::Gc => {
@@ -482,6 +594,14 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                     *gc_start_time = Some(Instant::now());
                 }
 
+                let active_workers = self.compute_active_workers(worker.mmtk);
+                debug!(
+                    "{} of {} GC workers are active for this GC",
+                    active_workers,
+                    self.num_workers()
+                );
+                self.worker_monitor.set_active_workers(active_workers);
+
                 self.add_schedule_collection_packet();
                 LastParkedResult::WakeSelf
             }
@@ -492,6 +612,73 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         }
     }
 
+    /// Decide how many workers should work for the GC that is about to start (see the option
+    /// `active_gc_workers`).  The estimated work volume is the bytes allocated since the last GC
+    /// for generational plans (which mostly do nursery GCs), or the reserved bytes of the heap for
+    /// other plans.
+    fn compute_active_workers(&self, mmtk: &MMTK<VM>) -> usize {
+        let num_workers = self.num_workers();
+        match *mmtk.options.active_gc_workers {
+            ActiveGCWorkers::All => num_workers,
+            ActiveGCWorkers::Adaptive { bytes_per_worker } => {
+                let reserved_pages = mmtk.get_plan().get_reserved_pages();
+                let work_pages = if mmtk.get_plan().generational().is_some() {
+                    reserved_pages.saturating_sub(
+                        mmtk.state
+                            .reserved_pages_at_last_gc
+                            .load(std::sync::atomic::Ordering::SeqCst),
+                    )
+                } else {
+                    reserved_pages
+                };
+                let work_bytes = crate::util::conversions::pages_to_bytes(work_pages);
+                ((work_bytes + bytes_per_worker - 1) / bytes_per_worker).clamp(1, num_workers)
+            }
+        }
+    }
+
+    /// Check if a periodic GC is due (see the option `periodic_gc_interval`).  This is called by
+    /// the last parked worker when there is no request.
+    ///
+    /// Return `Ok(())` if a full heap GC has been requested, and the caller should schedule it.
+    /// Otherwise, return the time when this should be checked again, or `None` if periodic GC is
+    /// disabled.
+    fn poll_periodic_gc(&self, worker: &GCWorker<VM>) -> Result<(), Option<Instant>> {
+        let mmtk = worker.mmtk;
+        let interval = *mmtk.options.periodic_gc_interval;
+        if interval == 0
+            || !mmtk.state.is_initialized()
+            || !mmtk.get_plan().constraints().collects_garbage
+        {
+            return Err(None);
+        }
+
+        let interval = std::time::Duration::from_millis(interval as u64);
+        let since_last_gc = mmtk.state.time_since_last_gc();
+        if since_last_gc < interval {
+            return Err(Some(Instant::now() + (interval - since_last_gc)));
+        }
+
+        // Only collect if there may be something to reclaim, and if the binding allows GC now.
+        // Otherwise, check again after another interval.
+        if !mmtk
+            .state
+            .may_have_garbage_since_last_gc(mmtk.get_plan().get_reserved_pages())
+            || !<VM as VMBinding>::VMCollection::is_collection_enabled()
+            || !mmtk.gc_requester.request_by_worker()
+        {
+            return Err(Some(Instant::now() + interval));
+        }
+
+        info!(
+            "Triggering a periodic GC ({} ms since the last GC)",
+            since_last_gc.as_millis()
+        );
+        mmtk.state
+            .request_user_collection(crate::plan::UserCollectionKind::Full);
+        Ok(())
+    }
+
     /// Find more work for workers to do.  Return true if more work is available.
     fn find_more_work_for_workers(&self) -> bool {
         if self.worker_group.has_designated_work() {
@@ -516,7 +703,9 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
     }
 
     /// Called when GC has finished, i.e. when all work packets have been executed.
-    fn on_gc_finished(&self, worker: &GCWorker<VM>) {
+    ///
+    /// Return true if background work has been added to the `Unconstrained` bucket.
+    fn on_gc_finished(&self, worker: &GCWorker<VM>) -> bool {
         // All GC workers must have parked by now.
         debug_assert!(!self.worker_group.has_designated_work());
         debug_assert!(self.all_buckets_empty());
@@ -527,8 +716,14 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
 
         let mmtk = worker.mmtk;
 
+        // A retention path query did not collect anything, so we do not inform the GC trigger
+        // or the plan.
+        let is_retention_walk = crate::util::retention_path::on_pause_finished(mmtk);
+
         // Tell GC trigger that GC ended - this happens before we resume mutators.
-        mmtk.gc_trigger.policy.on_gc_end(mmtk);
+        if !is_retention_walk {
+            mmtk.gc_trigger.policy.on_gc_end(mmtk);
+        }
 
         // Compute the elapsed time of the GC.
         let start_time = {
@@ -536,6 +731,19 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             gc_start_time.take().expect("GC not started yet?")
         };
         let elapsed = start_time.elapsed();
+        if let Some(timeline) = self.timeline.as_ref() {
+            timeline.record_gc(start_time);
+        }
+
+        if !is_retention_walk {
+            mmtk.state.record_gc_end(
+                elapsed,
+                mmtk.get_plan().get_reserved_pages(),
+                mmtk.get_plan()
+                    .generational()
+                    .map_or(true, |gen| gen.last_collection_full_heap()),
+            );
+        }
 
         info!(
             "End of GC ({}/{} pages, took {} ms)",
@@ -566,8 +774,18 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         }
 
         // All other workers are parked, so it is safe to access the Plan instance mutably.
-        let plan_mut: &mut dyn Plan<VM = VM> = unsafe { mmtk.get_plan_mut() };
-        plan_mut.end_of_gc(worker.tls);
+        if !is_retention_walk {
+            let plan_mut: &mut dyn Plan<VM = VM> = unsafe { mmtk.get_plan_mut() };
+            plan_mut.end_of_gc(worker.tls);
+        }
+
+        if *mmtk.options.fragmentation_report && !is_retention_walk {
+            let reports = mmtk.fragmentation_report();
+            for report in reports.iter() {
+                info!("Fragmentation: {:?}", report);
+            }
+            *mmtk.last_fragmentation_report.lock().unwrap() = reports;
+        }
 
         #[cfg(feature = "extreme_assertions")]
         if crate::util::edge_logger::should_check_duplicate_edges(mmtk.get_plan()) {
@@ -575,12 +793,28 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             mmtk.edge_logger.reset();
         }
 
-        // Reset the triggering information.
-        mmtk.state.reset_collection_trigger();
+        // Reset the triggering information.  A retention path query did not serve the GC
+        // requests, so they are kept for the next GC.  Neither did this GC serve a user request
+        // made after the GC had set its kind.  We keep the GC requested, and the last parked
+        // worker will start the next GC for it.
+        if !is_retention_walk {
+            mmtk.state.reset_collection_trigger();
+            if !mmtk.state.is_user_collection_requested() {
+                mmtk.gc_requester.clear_gc_request();
+            }
+        }
 
         // Set to NotInGC after everything, and right before resuming mutators.
         mmtk.set_gc_status(GcStatus::NotInGC);
         <VM as VMBinding>::VMCollection::resume_mutators(worker.tls);
+
+        // Start the background work after mutators are resumed.
+        let background_work = std::mem::take(&mut *self.background_work.lock().unwrap());
+        let has_background_work = !background_work.is_empty();
+        for packet in background_work {
+            self.work_buckets[WorkBucketStage::Unconstrained].add_boxed_no_notify(packet);
+        }
+        has_background_work
     }
 
     pub fn enable_stat(&self) {
@@ -98,4 +98,9 @@ pub trait GCWorkContext: Send + 'static {
     /// If a plan does not support object pinning, it should use `UnsupportedProcessEdges` for this
     /// type member.
     type PinningProcessEdges: ProcessEdgesWork<VM = Self::VM>;
+
+    /// Whether `ScanMutatorRoots` flushes each mutator after scanning its roots.  Work that does
+    /// not collect garbage, such as a retention path query, must not flush mutators, because the
+    /// flushed remembered sets would be processed as if in a GC.
+    const FLUSH_MUTATORS: bool = true;
 }
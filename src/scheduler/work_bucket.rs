@@ -2,7 +2,7 @@ use super::worker_monitor::WorkerMonitor;
 use super::*;
 use crate::vm::VMBinding;
 use crossbeam::deque::{Injector, Steal, Worker};
-use enum_map::Enum;
+use enum_map::{Enum, EnumArray};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
@@ -294,6 +294,8 @@ pub enum WorkBucketStage {
     Release,
     /// Resume mutators and end GC.
     Final,
+    /// A stage registered by the VM binding with [`crate::MMTKBuilder::add_custom_stage`].
+    Custom(CustomStage),
 }
 
 impl WorkBucketStage {
@@ -301,4 +303,100 @@ impl WorkBucketStage {
     pub fn first_stw_stage() -> Self {
         WorkBucketStage::from_usize(1)
     }
+
+    /// Compute the order of the stop-the-world stages, given the stage that each custom stage is
+    /// placed after.  `custom_stages[i]` is the predecessor of `CustomStage(i)`.  Custom stages
+    /// placed after the same stage are ordered by their indices.
+    pub(crate) fn stw_stage_order(custom_stages: &[WorkBucketStage]) -> Vec<WorkBucketStage> {
+        fn push_with_successors(
+            stage: WorkBucketStage,
+            custom_stages: &[WorkBucketStage],
+            order: &mut Vec<WorkBucketStage>,
+        ) {
+            order.push(stage);
+            for (i, predecessor) in custom_stages.iter().enumerate() {
+                if *predecessor == stage {
+                    push_with_successors(
+                        WorkBucketStage::Custom(CustomStage(i as u8)),
+                        custom_stages,
+                        order,
+                    );
+                }
+            }
+        }
+
+        let mut order = vec![];
+        for i in 1..WorkBucketStage::LENGTH {
+            let stage = WorkBucketStage::from_usize(i);
+            if !matches!(stage, WorkBucketStage::Custom(_)) {
+                push_with_successors(stage, custom_stages, &mut order);
+            }
+        }
+        order
+    }
+}
+
+/// The maximum number of custom work bucket stages that a VM binding can register.
+pub const MAX_CUSTOM_STAGES: usize = 8;
+
+/// The ID of a work bucket stage registered by the VM binding.  See [`WorkBucketStage::Custom`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CustomStage(pub(crate) u8);
+
+impl CustomStage {
+    /// The index of this custom stage, in the order of registration.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Enum for CustomStage {
+    const LENGTH: usize = MAX_CUSTOM_STAGES;
+
+    fn from_usize(value: usize) -> Self {
+        assert!(value < MAX_CUSTOM_STAGES);
+        CustomStage(value as u8)
+    }
+
+    fn into_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<V> EnumArray<V> for CustomStage {
+    type Array = [V; MAX_CUSTOM_STAGES];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stw_stage_order() {
+        let order = WorkBucketStage::stw_stage_order(&[]);
+        assert_eq!(order[0], WorkBucketStage::first_stw_stage());
+        assert_eq!(order.last(), Some(&WorkBucketStage::Final));
+        assert!(!order.contains(&WorkBucketStage::Unconstrained));
+
+        let custom = |i| WorkBucketStage::Custom(CustomStage(i));
+        let order = WorkBucketStage::stw_stage_order(&[
+            WorkBucketStage::WeakRefClosure,
+            WorkBucketStage::Prepare,
+            WorkBucketStage::WeakRefClosure,
+            custom(0),
+        ]);
+        let position = |stage| order.iter().position(|s| *s == stage).unwrap();
+        assert_eq!(position(custom(1)), position(WorkBucketStage::Prepare) + 1);
+        // Custom stage 3 is placed after custom stage 0, before custom stage 2.
+        assert_eq!(
+            position(custom(0)),
+            position(WorkBucketStage::WeakRefClosure) + 1
+        );
+        assert_eq!(position(custom(3)), position(custom(0)) + 1);
+        assert_eq!(position(custom(2)), position(custom(3)) + 1);
+        assert_eq!(
+            position(WorkBucketStage::FinalRefClosure),
+            position(custom(2)) + 1
+        );
+    }
 }
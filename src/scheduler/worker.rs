@@ -13,6 +13,7 @@ use crossbeam::queue::ArrayQueue;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// Represents the ID of a GC worker thread.
 pub type ThreadId = usize;
@@ -33,6 +34,12 @@ pub fn current_worker_ordinal() -> ThreadId {
     ordinal
 }
 
+/// Get current worker ordinal, or `None` if the current thread is not a GC worker.
+pub fn try_current_worker_ordinal() -> Option<ThreadId> {
+    let ordinal = WORKER_ORDINAL.with(|x| x.load(Ordering::Relaxed));
+    (ordinal != ThreadId::MAX).then_some(ordinal)
+}
+
 /// The struct has one instance per worker, but is shared between workers via the scheduler
 /// instance.  This structure is used for communication between workers, e.g. adding designated
 /// work packets, stealing work packets from other workers, and collecting per-worker statistics.
@@ -200,7 +207,7 @@ impl<VM: VMBinding> GCWorker<VM> {
     ///
     /// Each worker will keep polling and executing work packets in a loop.  It runs until the
     /// worker is requested to exit.  Currently a worker may exit after
-    /// [`crate::mmtk::MMTK::prepare_to_fork`] is called.
+    /// [`crate::mmtk::MMTK::prepare_to_fork`] or [`crate::mmtk::MMTK::prepare_to_destroy`] is called.
     ///
     /// Arguments:
     /// * `tls`: The VM-specific thread-local storage for this GC worker thread.
@@ -241,7 +248,13 @@ impl<VM: VMBinding> GCWorker<VM> {
             std::hint::black_box(unsafe { *(typename.as_ptr()) });
 
             probe!(mmtk, work, typename.as_ptr(), typename.len());
+            let start_time = self.scheduler.timeline.as_ref().map(|_| Instant::now());
             work.do_work_with_stat(&mut self, mmtk);
+            if let (Some(timeline), Some(start_time)) =
+                (self.scheduler.timeline.as_ref(), start_time)
+            {
+                timeline.record_work_packet(self.ordinal, typename, start_time);
+            }
         }
         debug!(
             "Worker exiting. ordinal: {}, {}",
@@ -399,6 +412,27 @@ impl<VM: VMBinding> WorkerGroup<VM> {
         })
     }
 
+    /// Drop the `GCWorker` structs surrendered by the workers.  This is called when the MMTk
+    /// instance is destroyed, and all the workers must have exited.
+    pub fn drop_surrendered_workers(&self) {
+        let mut state = self.state.lock().unwrap();
+        match state.as_mut().unwrap() {
+            // GC workers have never been spawned.
+            WorkerCreationState::Initial { .. } => {}
+            WorkerCreationState::Spawned => {
+                panic!("GC workers are still running (was prepare_to_destroy() called before?)")
+            }
+            WorkerCreationState::Surrendered { workers } => {
+                assert_eq!(
+                    workers.len(),
+                    self.worker_count(),
+                    "Not all GC workers have exited."
+                );
+                workers.clear();
+            }
+        }
+    }
+
     /// Return the `GCWorker` struct to the worker group.
     /// This function returns `true` if all workers returned their `GCWorker` structs.
     pub fn surrender_gc_worker(&self, worker: Box<GCWorker<VM>>) -> bool {
@@ -31,7 +31,8 @@ pub(crate) struct WorkerGoals {
 pub(crate) enum WorkerGoal {
     /// Do a garbage collection.
     Gc,
-    /// Stop all GC threads so that the VM can call `fork()`.
+    /// Stop all GC threads so that the VM can call `fork()`.  This is also used for stopping GC
+    /// threads before destroying the MMTk instance.
     StopForFork,
 }
 
@@ -4,7 +4,9 @@
 //! -   letting the last parked worker take action, and
 //! -   letting workers and mutators notify workers when workers are given things to do.
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Condvar, Mutex};
+use std::time::Instant;
 
 use super::{
     worker::WorkerShouldExit,
@@ -16,10 +18,17 @@ use super::{
 pub(crate) enum LastParkedResult {
     /// The last parked worker should wait, too, until more work packets are added.
     ParkSelf,
+    /// The last parked worker should wait, too, until more work packets are added or until the
+    /// given time.  If it times out, it will unpark, look for work packets, and park again.
+    ParkSelfUntil(Instant),
     /// The last parked worker should unpark and find work packet to do.
     WakeSelf,
-    /// Wake up all parked GC workers.
+    /// Wake up all parked GC workers except surplus workers (see
+    /// `WorkerMonitor::set_active_workers`).
     WakeAll,
+    /// Wake up all parked GC workers, including surplus workers, because workers have been given
+    /// designated work.
+    WakeAllWithDesignatedWork,
 }
 
 /// A data structure for synchronizing workers with each other and with mutators.
@@ -35,6 +44,13 @@ pub(crate) struct WorkerMonitor {
     /// -   any work packets available, and
     /// -   any field in `sync.goals.requests` set to true.
     workers_have_anything_to_do: Condvar,
+    /// Surplus workers wait on this during GC, so that they are not woken up whenever work
+    /// packets are available.  Notified when the GC finishes, or when workers are given
+    /// designated work.
+    surplus_workers_have_anything_to_do: Condvar,
+    /// The number of workers that work for the current GC.  Workers with `ordinal >= active_workers`
+    /// stay parked during GC unless they have designated work.
+    active_workers: AtomicUsize,
 }
 
 /// The synchronized part of `WorkerMonitor`.
@@ -92,9 +108,23 @@ impl WorkerMonitor {
                 goals: Default::default(),
             }),
             workers_have_anything_to_do: Default::default(),
+            surplus_workers_have_anything_to_do: Default::default(),
+            active_workers: AtomicUsize::new(worker_count),
         }
     }
 
+    /// Set the number of workers that work for the next GC.  This is called by the last parked
+    /// worker before a GC starts.  Surplus workers will stay parked during that GC.
+    pub fn set_active_workers(&self, active_workers: usize) {
+        debug_assert!(active_workers > 0);
+        self.active_workers.store(active_workers, Ordering::SeqCst);
+    }
+
+    /// Get the number of workers that work for the current GC.
+    pub fn active_workers(&self) -> usize {
+        self.active_workers.load(Ordering::SeqCst)
+    }
+
     /// Make a request.  Can be called by a mutator to request the workers to work towards the
     /// given `goal`.
     pub fn make_request(&self, goal: WorkerGoal) {
@@ -115,22 +145,47 @@ impl WorkerMonitor {
         }
     }
 
-    /// Park a worker and wait on the CondVar `workers_have_anything_to_do`.
+    /// Wake up surplus workers.  They check whether they are still surplus workers, and park
+    /// again if they are.
+    fn notify_surplus_workers(&self) {
+        self.surplus_workers_have_anything_to_do.notify_all();
+    }
+
+    /// Return true if the worker is a surplus worker of the current GC, and has no designated
+    /// work.
+    fn is_surplus(
+        &self,
+        sync: &WorkerMonitorSync,
+        ordinal: usize,
+        has_designated_work: &impl Fn() -> bool,
+    ) -> bool {
+        matches!(sync.goals.current(), Some(WorkerGoal::Gc))
+            && ordinal >= self.active_workers()
+            && !has_designated_work()
+    }
+
+    /// Park a worker and wait on the CondVar `workers_have_anything_to_do`, or
+    /// `surplus_workers_have_anything_to_do` if it is a surplus worker.
     ///
     /// If it is the last worker parked, `on_last_parked` will be called.
     /// The argument of `on_last_parked` is true if `sync.gc_requested` is `true`.
     /// The return value of `on_last_parked` will determine whether this worker and other workers
     /// will wake up or block waiting.
     ///
+    /// During GC, a surplus worker (see `set_active_workers`) keeps waiting until the GC finishes,
+    /// unless `has_designated_work` returns true.
+    ///
     /// This function returns `Ok(())` if the current worker should continue working,
     /// or `Err(WorkerShouldExit)` if the current worker should exit now.
-    pub fn park_and_wait<F>(
+    pub fn park_and_wait<F, G>(
         &self,
         ordinal: usize,
         on_last_parked: F,
+        has_designated_work: G,
     ) -> Result<(), WorkerShouldExit>
     where
         F: FnOnce(&mut WorkerGoals) -> LastParkedResult,
+        G: Fn() -> bool,
     {
         let mut sync = self.sync.lock().unwrap();
 
@@ -145,6 +200,7 @@ impl WorkerMonitor {
         );
 
         let mut should_wait = false;
+        let mut wait_until = None;
 
         if all_parked {
             trace!("Worker {} is the last worker parked.", ordinal);
@@ -153,12 +209,24 @@ impl WorkerMonitor {
                 LastParkedResult::ParkSelf => {
                     should_wait = true;
                 }
+                LastParkedResult::ParkSelfUntil(deadline) => {
+                    should_wait = true;
+                    wait_until = Some(deadline);
+                }
                 LastParkedResult::WakeSelf => {
                     // Continue without waiting.
                 }
                 LastParkedResult::WakeAll => {
                     self.notify_work_available(true);
                 }
+                LastParkedResult::WakeAllWithDesignatedWork => {
+                    self.notify_work_available(true);
+                    self.notify_surplus_workers();
+                }
+            }
+            // Surplus workers are no longer surplus if the GC has finished.
+            if !matches!(sync.goals.current(), Some(WorkerGoal::Gc)) {
+                self.notify_surplus_workers();
             }
         } else {
             should_wait = true;
@@ -218,7 +286,29 @@ impl WorkerMonitor {
             //     and park again if not available.  The last parked worker will ensure the two
             //     conditions listed above are both false before blocking.  If either condition is
             //     true, the last parked worker will take action.
-            sync = self.workers_have_anything_to_do.wait(sync).unwrap();
+            //
+            // 3.  A surplus worker stays parked for the current GC unless it has designated work.
+            //     It is still counted as parked, so the last active worker parked will be the last
+            //     parked worker.  It waits on `surplus_workers_have_anything_to_do`, which is only
+            //     notified by the last parked worker while holding `self.sync`, so it does not miss
+            //     the end of the GC or its designated work.
+            loop {
+                let condvar = if self.is_surplus(&sync, ordinal, &has_designated_work) {
+                    &self.surplus_workers_have_anything_to_do
+                } else {
+                    &self.workers_have_anything_to_do
+                };
+                sync = if let Some(deadline) = wait_until.take() {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    condvar.wait_timeout(sync, timeout).unwrap().0
+                } else {
+                    condvar.wait(sync).unwrap()
+                };
+
+                if !self.is_surplus(&sync, ordinal, &has_designated_work) {
+                    break;
+                }
+            }
         }
 
         // Unpark this worker.
@@ -254,6 +344,13 @@ mod tests {
 
     use super::WorkerMonitor;
 
+    /// Finish the current GC like the last parked worker does.
+    fn finish_gc(worker_monitor: &WorkerMonitor) {
+        let mut sync = worker_monitor.sync.lock().unwrap();
+        sync.goals.on_current_goal_completed();
+        worker_monitor.notify_surplus_workers();
+    }
+
     /// Test if the `WorkerMonitor::park_and_wait` method calls the `on_last_parked` callback
     /// properly.
     #[test]
@@ -274,12 +371,16 @@ mod tests {
                     while !should_unpark.load(Ordering::SeqCst) {
                         println!("Thread {} parking...", ordinal);
                         worker_monitor
-                            .park_and_wait(ordinal, |_goals| {
-                                println!("Thread {} is the last thread parked.", ordinal);
-                                on_last_parked_called.fetch_add(1, Ordering::SeqCst);
-                                should_unpark.store(true, Ordering::SeqCst);
-                                super::LastParkedResult::WakeAll
-                            })
+                            .park_and_wait(
+                                ordinal,
+                                |_goals| {
+                                    println!("Thread {} is the last thread parked.", ordinal);
+                                    on_last_parked_called.fetch_add(1, Ordering::SeqCst);
+                                    should_unpark.store(true, Ordering::SeqCst);
+                                    super::LastParkedResult::WakeAll
+                                },
+                                || false,
+                            )
                             .unwrap();
                         println!("Thread {} unparked.", ordinal);
                     }
@@ -314,13 +415,17 @@ mod tests {
                     while !should_unpark.load(Ordering::SeqCst) {
                         println!("Thread {} parking...", ordinal);
                         worker_monitor
-                            .park_and_wait(ordinal, |_goals| {
-                                println!("Thread {} is the last thread parked.", ordinal);
-                                on_last_parked_called.fetch_add(1, Ordering::SeqCst);
-                                should_unpark.store(true, Ordering::SeqCst);
-                                i_am_the_last_parked_worker = true;
-                                super::LastParkedResult::WakeSelf
-                            })
+                            .park_and_wait(
+                                ordinal,
+                                |_goals| {
+                                    println!("Thread {} is the last thread parked.", ordinal);
+                                    on_last_parked_called.fetch_add(1, Ordering::SeqCst);
+                                    should_unpark.store(true, Ordering::SeqCst);
+                                    i_am_the_last_parked_worker = true;
+                                    super::LastParkedResult::WakeSelf
+                                },
+                                || false,
+                            )
                             .unwrap();
                         println!("Thread {} unparked.", ordinal);
                     }
@@ -340,4 +445,188 @@ mod tests {
         // `on_last_parked` should only be called once.
         assert_eq!(on_last_parked_called.load(Ordering::SeqCst), 1);
     }
+
+    /// Test if the last parked worker wakes up by itself after the time given by
+    /// `ParkSelfUntil`, and calls `on_last_parked` again when it parks again.
+    #[test]
+    fn test_last_worker_park_until() {
+        let worker_monitor = WorkerMonitor::new(1);
+        let on_last_parked_called = AtomicUsize::new(0);
+        let timeout = std::time::Duration::from_millis(10);
+        for _ in 0..2 {
+            worker_monitor
+                .park_and_wait(
+                    0,
+                    |_goals| {
+                        if on_last_parked_called.fetch_add(1, Ordering::SeqCst) == 0 {
+                            super::LastParkedResult::ParkSelfUntil(
+                                std::time::Instant::now() + timeout,
+                            )
+                        } else {
+                            super::LastParkedResult::WakeSelf
+                        }
+                    },
+                    || false,
+                )
+                .unwrap();
+        }
+
+        assert_eq!(on_last_parked_called.load(Ordering::SeqCst), 2);
+    }
+
+    /// Test if surplus workers stay parked during GC unless they have designated work.
+    #[test]
+    fn test_surplus_workers_stay_parked() {
+        let number_threads = 4;
+        let active_workers = 2;
+        // The worker with this ordinal is a surplus worker, but it has designated work.
+        let ordinal_with_designated_work = 3;
+        let worker_monitor = Arc::new(WorkerMonitor::new(number_threads));
+        worker_monitor.set_active_workers(active_workers);
+        let last_parked_ordinal = AtomicUsize::new(usize::MAX);
+        let unparked_during_gc = AtomicUsize::new(0);
+        let gc_finished = AtomicBool::new(false);
+        // The active workers, the worker with designated work, and the last parked worker which
+        // wakes up all workers, whether it is active or not.
+        let expected = |last_parked: usize| {
+            if last_parked < active_workers || last_parked == ordinal_with_designated_work {
+                active_workers + 1
+            } else {
+                active_workers + 2
+            }
+        };
+
+        std::thread::scope(|scope| {
+            for ordinal in 0..number_threads {
+                let worker_monitor = worker_monitor.clone();
+                let last_parked_ordinal = &last_parked_ordinal;
+                let unparked_during_gc = &unparked_during_gc;
+                let gc_finished = &gc_finished;
+                scope.spawn(move || {
+                    worker_monitor
+                        .park_and_wait(
+                            ordinal,
+                            |goals| {
+                                last_parked_ordinal.store(ordinal, Ordering::SeqCst);
+                                goals.set_request(super::WorkerGoal::Gc);
+                                goals.poll_next_goal();
+                                super::LastParkedResult::WakeAll
+                            },
+                            || ordinal == ordinal_with_designated_work,
+                        )
+                        .unwrap();
+                    if !gc_finished.load(Ordering::SeqCst) {
+                        let is_last_parked = last_parked_ordinal.load(Ordering::SeqCst) == ordinal;
+                        assert!(
+                            ordinal < active_workers
+                                || ordinal == ordinal_with_designated_work
+                                || is_last_parked,
+                            "Surplus worker {} unparked during GC",
+                            ordinal
+                        );
+                        unparked_during_gc.fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+            }
+
+            // Wait until the workers that are expected to work for the GC have unparked.
+            while last_parked_ordinal.load(Ordering::SeqCst) == usize::MAX
+                || unparked_during_gc.load(Ordering::SeqCst)
+                    < expected(last_parked_ordinal.load(Ordering::SeqCst))
+            {
+                std::thread::yield_now();
+            }
+
+            // Finish the GC. The remaining surplus worker should unpark.
+            gc_finished.store(true, Ordering::SeqCst);
+            finish_gc(&worker_monitor);
+        });
+
+        assert_eq!(
+            unparked_during_gc.load(Ordering::SeqCst),
+            expected(last_parked_ordinal.load(Ordering::SeqCst))
+        );
+    }
+
+    /// Test if surplus workers are not woken up when work packets are made available during GC.
+    #[test]
+    fn test_surplus_workers_not_woken_up() {
+        let number_threads = 4;
+        let active_workers = 2;
+        let notifications = 100;
+        let worker_monitor = Arc::new(WorkerMonitor::new(number_threads));
+        worker_monitor.set_active_workers(active_workers);
+        // The number of times surplus workers check whether they are still surplus workers, i.e.
+        // once before waiting during GC, and once after each time they are woken up during GC.
+        let surplus_checks = AtomicUsize::new(0);
+        let parked_workers = || worker_monitor.sync.lock().unwrap().parker.parked_workers;
+
+        std::thread::scope(|scope| {
+            let park = |ordinal: usize| {
+                let worker_monitor = &worker_monitor;
+                let surplus_checks = &surplus_checks;
+                move || {
+                    worker_monitor
+                        .park_and_wait(
+                            ordinal,
+                            |goals| {
+                                goals.set_request(super::WorkerGoal::Gc);
+                                goals.poll_next_goal();
+                                super::LastParkedResult::WakeAll
+                            },
+                            || {
+                                surplus_checks.fetch_add(1, Ordering::SeqCst);
+                                false
+                            },
+                        )
+                        .unwrap();
+                }
+            };
+
+            // Park the surplus workers first so that an active worker is the last parked worker,
+            // which starts the GC.
+            for ordinal in active_workers..number_threads {
+                scope.spawn(park(ordinal));
+            }
+            while parked_workers() < number_threads - active_workers {
+                std::thread::yield_now();
+            }
+            for ordinal in 0..active_workers {
+                scope.spawn(park(ordinal));
+            }
+
+            // Wait until the GC has started, and the active workers have unparked.
+            while worker_monitor
+                .sync
+                .lock()
+                .unwrap()
+                .goals
+                .current()
+                .is_none()
+                || parked_workers() > number_threads - active_workers
+            {
+                std::thread::yield_now();
+            }
+
+            // Work packets are made available many times during the GC.  Give woken up workers
+            // time to park again.
+            for _ in 0..notifications {
+                worker_monitor.notify_work_available(true);
+                worker_monitor.notify_work_available(false);
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+
+            finish_gc(&worker_monitor);
+        });
+
+        // Each surplus worker is woken up when the GC starts, and checks again before it waits on
+        // the other CondVar.  It should not be woken up by the notifications, but spurious
+        // wake-ups are possible.
+        let checks = surplus_checks.load(Ordering::SeqCst);
+        assert!(
+            checks < notifications,
+            "Surplus workers were checked {} times",
+            checks
+        );
+    }
 }
@@ -257,7 +257,7 @@ impl<VM: VMBinding> FreeListAllocator<VM> {
         align: usize,
         _stress_test: bool,
     ) -> Option<Block> {
-        if cfg!(feature = "eager_sweeping") {
+        if self.space.eager_sweeping() {
             // We have swept blocks in the last GC. If we run out of available blocks, there is nothing we can do.
             None
         } else {
@@ -427,9 +427,16 @@ impl<VM: VMBinding> FreeListAllocator<VM> {
     /// for mark sweep.
     const ABANDON_BLOCKS_IN_RESET: bool = true;
 
-    /// Lazy sweeping. We just move all the blocks to the unswept block list.
-    #[cfg(not(feature = "eager_sweeping"))]
     fn reset(&mut self) {
+        if self.space.eager_sweeping() {
+            self.reset_and_sweep()
+        } else {
+            self.reset_for_lazy_sweeping()
+        }
+    }
+
+    /// Lazy sweeping. We just move all the blocks to the unswept block list.
+    fn reset_for_lazy_sweeping(&mut self) {
         trace!("reset");
         // consumed and available are now unswept
         for bin in 0..MI_BIN_FULL {
@@ -455,8 +462,7 @@ impl<VM: VMBinding> FreeListAllocator<VM> {
     }
 
     /// Eager sweeping. We sweep all the block lists, and move them to available block lists.
-    #[cfg(feature = "eager_sweeping")]
-    fn reset(&mut self) {
+    fn reset_and_sweep(&mut self) {
         debug!("reset");
         // sweep all blocks and push consumed onto available list
         for bin in 0..MI_BIN_FULL {
@@ -114,6 +114,11 @@ pub const LOG_BITS_IN_PAGE: usize = LOG_BITS_IN_BYTE as usize + LOG_BYTES_IN_PAG
 /// The number of bits in a page
 pub const BITS_IN_PAGE: usize = 1 << LOG_BITS_IN_PAGE;
 
+/// log2 of the number of bytes in a huge page (a transparent huge page or a `MAP_HUGETLB` page)
+pub const LOG_BYTES_IN_HUGE_PAGE: u8 = 21;
+/// The number of bytes in a huge page
+pub const BYTES_IN_HUGE_PAGE: usize = 1 << LOG_BYTES_IN_HUGE_PAGE;
+
 /// log2 of the number of bytes in the address space
 pub const LOG_BYTES_IN_ADDRESS_SPACE: u8 = BITS_IN_ADDRESS as u8;
 
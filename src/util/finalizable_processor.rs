@@ -147,6 +147,7 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for Finalization<E> {
             let rescan = Box::new(RescanReferences {
                 soft: true,
                 weak: true,
+                stage: WorkBucketStage::FinalRefClosure,
                 phantom_data: PhantomData,
             });
             worker.scheduler().work_buckets[WorkBucketStage::FinalRefClosure].set_sentinel(rescan);
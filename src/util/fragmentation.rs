@@ -0,0 +1,255 @@
+//! Per-space occupancy and fragmentation reports.
+//!
+//! A report tells, for each space, how many bytes the space reserves and how many of them are
+//! live, together with details specific to the policy of the space.  It helps to tell whether an
+//! out-of-memory error is caused by fragmentation.  See [`crate::memory_manager::fragmentation_report`]
+//! and the option `fragmentation_report`.
+//!
+//! The numbers are as of the last GC.  Memory allocated since then is counted as reserved, but not
+//! necessarily as live.
+
+use crate::util::constants::BYTES_IN_PAGE;
+
+/// The occupancy and fragmentation of a space.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpaceReport {
+    /// The name of the space.
+    pub name: &'static str,
+    /// The bytes reserved by the space, including its side metadata.
+    pub reserved_bytes: usize,
+    /// The bytes of live objects, or `None` if the policy cannot tell.  It is an upper bound for
+    /// policies that manage memory at a granularity coarser than objects, such as Immix lines.
+    pub live_bytes: Option<usize>,
+    /// The free pages in the free list of the page resource, if the space uses a free list.
+    pub free_list: Option<FreeListReport>,
+    /// The details specific to the policy of the space.
+    pub policy: PolicyReport,
+}
+
+impl SpaceReport {
+    /// A report with no live bytes or details.
+    pub(crate) fn new(name: &'static str, reserved_pages: usize) -> Self {
+        Self {
+            name,
+            reserved_bytes: reserved_pages * BYTES_IN_PAGE,
+            live_bytes: None,
+            free_list: None,
+            policy: PolicyReport::Other,
+        }
+    }
+}
+
+/// The details of a space specific to its policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyReport {
+    /// The policy does not report any details.
+    Other,
+    /// An Immix space.
+    Immix(ImmixReport),
+    /// A native mark-sweep space.
+    MarkSweep(MarkSweepReport),
+    /// A large object space.
+    LargeObject(LargeObjectReport),
+}
+
+/// The line occupancy of the blocks in an Immix space, according to the line marks of the last GC.
+/// In the block-only mode, lines are not marked, and all the line counts are zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImmixReport {
+    /// The number of allocated blocks.
+    pub blocks: usize,
+    /// The number of lines in the allocated blocks.
+    pub lines: usize,
+    /// The number of marked lines.
+    pub marked_lines: usize,
+    /// The number of holes, i.e. runs of unmarked lines, in all the blocks.
+    pub holes: usize,
+    /// `blocks_by_holes[n]` is the number of blocks with `n` holes.
+    pub blocks_by_holes: Vec<usize>,
+}
+
+impl ImmixReport {
+    /// Add a block with the given numbers of lines, marked lines and holes.
+    pub(crate) fn add_block(&mut self, lines: usize, marked_lines: usize, holes: usize) {
+        self.blocks += 1;
+        self.lines += lines;
+        self.marked_lines += marked_lines;
+        self.holes += holes;
+        if self.blocks_by_holes.len() <= holes {
+            self.blocks_by_holes.resize(holes + 1, 0);
+        }
+        self.blocks_by_holes[holes] += 1;
+    }
+}
+
+/// The free cells of a native mark-sweep space, for each size class, according to the mark bits of
+/// the last GC.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MarkSweepReport {
+    /// The size classes with at least one block, sorted by cell size.
+    pub size_classes: Vec<SizeClassReport>,
+}
+
+/// The blocks and cells of a size class in a native mark-sweep space.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeClassReport {
+    /// The size of each cell.
+    pub cell_size: usize,
+    /// The number of blocks of this size class.
+    pub blocks: usize,
+    /// The number of cells in the blocks.
+    pub cells: usize,
+    /// The number of cells without an object marked in the last GC.  This does not depend on
+    /// whether the blocks have been swept since the last GC.
+    pub free_cells: usize,
+}
+
+impl MarkSweepReport {
+    /// Add a block with the given cell size and numbers of cells.
+    pub(crate) fn add_block(&mut self, cell_size: usize, cells: usize, free_cells: usize) {
+        let index = match self
+            .size_classes
+            .binary_search_by_key(&cell_size, |class| class.cell_size)
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.size_classes.insert(
+                    index,
+                    SizeClassReport {
+                        cell_size,
+                        blocks: 0,
+                        cells: 0,
+                        free_cells: 0,
+                    },
+                );
+                index
+            }
+        };
+        let class = &mut self.size_classes[index];
+        class.blocks += 1;
+        class.cells += cells;
+        class.free_cells += free_cells;
+    }
+
+    /// The bytes in the cells that are not free.
+    pub fn used_cell_bytes(&self) -> usize {
+        self.size_classes
+            .iter()
+            .map(|class| (class.cells - class.free_cells) * class.cell_size)
+            .sum()
+    }
+}
+
+/// The objects and pages of a large object space.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LargeObjectReport {
+    /// The number of objects.
+    pub objects: usize,
+    /// The total size of the objects.
+    pub object_bytes: usize,
+    /// The pages allocated for the objects.  The bytes in the pages but not in the objects are
+    /// lost to internal fragmentation.
+    pub pages: usize,
+}
+
+/// The free pages in the free list of a page resource.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FreeListReport {
+    /// The number of free pages.
+    pub free_pages: usize,
+    /// The number of contiguous ranges of free pages.
+    pub free_extents: usize,
+    /// The number of pages in the largest contiguous range of free pages.
+    pub largest_free_extent_pages: usize,
+}
+
+impl FreeListReport {
+    /// Summarize the free lumps of a free list, given as `(first page, pages)`.  Adjacent lumps
+    /// that the free list does not coalesce, e.g. at chunk boundaries, are counted as one extent.
+    pub(crate) fn from_free_lumps(mut lumps: Vec<(usize, usize)>) -> Self {
+        lumps.sort_unstable();
+        let mut report = Self::default();
+        let mut extent: Option<(usize, usize)> = None;
+        for (first, pages) in lumps {
+            report.free_pages += pages;
+            extent = match extent {
+                Some((start, end)) if end == first => Some((start, first + pages)),
+                _ => {
+                    report.free_extents += 1;
+                    Some((first, first + pages))
+                }
+            };
+            let (start, end) = extent.unwrap();
+            report.largest_free_extent_pages = report.largest_free_extent_pages.max(end - start);
+        }
+        report
+    }
+
+    /// The fraction of free pages that are not in the largest free extent, from 0 (not
+    /// fragmented) to nearly 1 (highly fragmented).
+    pub fn fragmentation(&self) -> f64 {
+        if self.free_pages == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_extent_pages as f64 / self.free_pages as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_immix_report() {
+        let mut report = ImmixReport::default();
+        report.add_block(128, 128, 0);
+        report.add_block(128, 100, 2);
+        report.add_block(128, 0, 1);
+        assert_eq!(report.blocks, 3);
+        assert_eq!(report.lines, 384);
+        assert_eq!(report.marked_lines, 228);
+        assert_eq!(report.holes, 3);
+        assert_eq!(report.blocks_by_holes, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_mark_sweep_report() {
+        let mut report = MarkSweepReport::default();
+        report.add_block(64, 1024, 1000);
+        report.add_block(16, 4096, 4096);
+        report.add_block(64, 1024, 24);
+        assert_eq!(
+            report.size_classes,
+            vec![
+                SizeClassReport {
+                    cell_size: 16,
+                    blocks: 1,
+                    cells: 4096,
+                    free_cells: 4096
+                },
+                SizeClassReport {
+                    cell_size: 64,
+                    blocks: 2,
+                    cells: 2048,
+                    free_cells: 1024
+                },
+            ]
+        );
+        assert_eq!(report.used_cell_bytes(), 1024 * 64);
+    }
+
+    #[test]
+    fn test_free_list_report() {
+        // Pages 0-3 and 4-5 are adjacent.
+        let report = FreeListReport::from_free_lumps(vec![(10, 2), (4, 2), (0, 4), (20, 1)]);
+        assert_eq!(report.free_pages, 9);
+        assert_eq!(report.free_extents, 3);
+        assert_eq!(report.largest_free_extent_pages, 6);
+        assert!((report.fragmentation() - 1.0 / 3.0).abs() < 1e-9);
+
+        let empty = FreeListReport::from_free_lumps(vec![]);
+        assert_eq!(empty, FreeListReport::default());
+        assert_eq!(empty.fragmentation(), 0.0);
+    }
+}
@@ -12,6 +12,8 @@ use crate::vm::*;
 use atomic::Ordering;
 use spin::RwLock;
 use std::cell::UnsafeCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Mutex;
 
@@ -25,6 +27,9 @@ pub struct BlockPageResource<VM: VMBinding, B: Region + 'static> {
     block_queue: BlockPool<B>,
     /// Slow-path allocation synchronization
     sync: Mutex<()>,
+    /// Hand out free blocks in partially used huge pages first, so that fewer huge pages are
+    /// touched and free huge pages stay free.
+    pub(crate) huge_page_aware: bool,
 }
 
 impl<VM: VMBinding, B: Region> PageResource<VM> for BlockPageResource<VM, B> {
@@ -68,6 +73,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
             flpr: FreeListPageResource::new_contiguous(start, bytes, vm_map),
             block_queue: BlockPool::new(num_workers),
             sync: Mutex::new(()),
+            huge_page_aware: false,
         }
     }
 
@@ -81,6 +87,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
             flpr: FreeListPageResource::new_discontiguous(vm_map),
             block_queue: BlockPool::new(num_workers),
             sync: Mutex::new(()),
+            huge_page_aware: false,
         }
     }
 
@@ -167,8 +174,17 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         self.block_queue.push(block)
     }
 
+    /// Flush the thread-local queue of the current GC worker, so that the released blocks can be
+    /// reused before all the thread-local queues are flushed.
+    pub fn flush_current_worker(&self) {
+        self.block_queue.flush_current_worker()
+    }
+
     pub fn flush_all(&self) {
-        self.block_queue.flush_all()
+        self.block_queue.flush_all();
+        if self.huge_page_aware {
+            self.block_queue.sort_by_huge_pages();
+        }
         // TODO: For 32-bit space, we may want to free some contiguous chunks.
     }
 }
@@ -306,10 +322,14 @@ impl<B: Region> BlockPool<B> {
         self.global_freed_blocks.write().push(array);
     }
 
-    /// Push a block to the thread-local queue
+    /// Push a block to the thread-local queue, or to the global pool if the current thread is not a
+    /// GC worker (e.g. a mutator that sweeps on demand).
     pub fn push(&self, block: B) {
         self.count.fetch_add(1, Ordering::SeqCst);
-        let id = crate::scheduler::current_worker_ordinal();
+        let Some(id) = crate::scheduler::try_current_worker_ordinal() else {
+            self.push_global(block);
+            return;
+        };
         let failed = unsafe {
             self.worker_local_freed_blocks[id]
                 .push_relaxed(block)
@@ -325,6 +345,22 @@ impl<B: Region> BlockPool<B> {
         }
     }
 
+    /// Push a block to the last BlockArray of the global pool.
+    fn push_global(&self, block: B) {
+        let mut global_freed_blocks = self.global_freed_blocks.write();
+        // Blocks are only popped from the global pool after the whole BlockArray is taken out of
+        // `global_freed_blocks`, which requires the write lock.  So we can push without atomics.
+        if let Some(queue) = global_freed_blocks.last() {
+            if unsafe { queue.push_relaxed(block) }.is_ok() {
+                return;
+            }
+        }
+        let queue = BlockQueue::new();
+        let result = unsafe { queue.push_relaxed(block) };
+        debug_assert!(result.is_ok());
+        global_freed_blocks.push(queue);
+    }
+
     /// Pop a block from the global pool
     pub fn pop(&self) -> Option<B> {
         if self.len() == 0 {
@@ -367,6 +403,11 @@ impl<B: Region> BlockPool<B> {
         }
     }
 
+    /// Flush the thread-local queue of the current GC worker to the global pool
+    pub fn flush_current_worker(&self) {
+        self.flush(crate::scheduler::current_worker_ordinal())
+    }
+
     /// Flush all thread-local queues to the global pool
     pub fn flush_all(&self) {
         if self.len() == 0 {
@@ -377,6 +418,34 @@ impl<B: Region> BlockPool<B> {
         }
     }
 
+    /// Reorder the blocks in the global pool so that the blocks in the huge pages with the fewest
+    /// free blocks are popped first.  The blocks of a huge page are kept together, and the huge
+    /// pages with no used blocks are popped last.  The blocks in thread-local queues are not
+    /// affected.  This holds the locks of the global pool, so concurrent pops wait until it is done.
+    pub fn sort_by_huge_pages(&self) {
+        let mut head_global_freed_blocks = self.head_global_freed_blocks.write();
+        let mut global_freed_blocks = self.global_freed_blocks.write();
+        let mut blocks = vec![];
+        for array in head_global_freed_blocks
+            .take()
+            .iter()
+            .chain(global_freed_blocks.iter())
+        {
+            array.iterate_blocks(&mut |block| blocks.push(block));
+        }
+        global_freed_blocks.clear();
+        sort_by_huge_pages(&mut blocks);
+        // Blocks are popped from the end of the last array.
+        for chunk in blocks.chunks(BlockQueue::<B>::CAPACITY) {
+            let array = BlockQueue::new();
+            for block in chunk {
+                let result = unsafe { array.push_relaxed(*block) };
+                debug_assert!(result.is_ok());
+            }
+            global_freed_blocks.push(array);
+        }
+    }
+
     /// Get total number of blocks in the whole BlockQueue
     pub fn len(&self) -> usize {
         self.count.load(Ordering::SeqCst)
@@ -395,3 +464,58 @@ impl<B: Region> BlockPool<B> {
         }
     }
 }
+
+/// Sort free blocks so that the blocks to use first are at the end: the blocks in the huge pages
+/// with the fewest free blocks, i.e. the most used blocks.
+fn sort_by_huge_pages<B: Region>(blocks: &mut [B]) {
+    let huge_page = |block: &B| block.start().align_down(BYTES_IN_HUGE_PAGE);
+    let mut free_blocks: HashMap<Address, usize> = HashMap::new();
+    for block in blocks.iter() {
+        *free_blocks.entry(huge_page(block)).or_default() += 1;
+    }
+    blocks.sort_unstable_by_key(|block| {
+        let huge_page = huge_page(block);
+        (Reverse(free_blocks[&huge_page]), huge_page, block.start())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::immix::block::Block;
+
+    fn block(huge_page: usize, index: usize) -> Block {
+        Block::from_aligned_address(unsafe {
+            Address::from_usize((16 + huge_page) * BYTES_IN_HUGE_PAGE + index * Block::BYTES)
+        })
+    }
+
+    #[test]
+    fn test_sort_by_huge_pages() {
+        let per_huge_page = BYTES_IN_HUGE_PAGE / Block::BYTES;
+        // Huge page 0 is entirely free.  Huge page 1 has 3 free blocks, and huge page 2 has 1.
+        let mut blocks: Vec<Block> = (0..per_huge_page).map(|i| block(0, i)).collect();
+        blocks.extend([block(1, 5), block(2, 3), block(1, 0), block(1, 7)]);
+        sort_by_huge_pages(&mut blocks);
+        let mut order = blocks.iter().rev();
+        assert_eq!(order.next(), Some(&block(2, 3)));
+        let next: Vec<_> = order.by_ref().take(3).copied().collect();
+        assert_eq!(next, vec![block(1, 7), block(1, 5), block(1, 0)]);
+        assert!(order.all(|b| b.start().align_down(BYTES_IN_HUGE_PAGE) == block(0, 0).start()));
+    }
+
+    #[test]
+    fn test_pool_sort_by_huge_pages() {
+        let pool = BlockPool::new(1);
+        for b in [block(0, 0), block(1, 0), block(0, 1), block(0, 2)] {
+            pool.push_global(b);
+        }
+        pool.count.store(4, Ordering::SeqCst);
+        pool.sort_by_huge_pages();
+        let popped: Vec<_> = std::iter::from_fn(|| pool.pop()).collect();
+        assert_eq!(
+            popped,
+            vec![block(1, 0), block(0, 2), block(0, 1), block(0, 0)]
+        );
+    }
+}
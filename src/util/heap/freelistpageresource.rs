@@ -10,6 +10,7 @@ use crate::mmtk::MMAPPER;
 use crate::util::address::Address;
 use crate::util::alloc::embedded_meta_data::*;
 use crate::util::conversions;
+use crate::util::fragmentation::FreeListReport;
 use crate::util::freelist;
 use crate::util::freelist::FreeList;
 use crate::util::heap::layout::vm_layout::*;
@@ -362,6 +363,51 @@ impl<VM: VMBinding> FreeListPageResource<VM> {
         self.inner_mut().common.release_discontiguous_chunks(chunk);
     }
 
+    /// The number of pages allocated at `first`.
+    pub(crate) fn pages_at(&self, first: Address) -> usize {
+        debug_assert!(conversions::is_page_aligned(first));
+        let page_offset = conversions::bytes_to_pages_up(first - self.start);
+        // Other threads may allocate or release pages in the meantime.
+        let _sync = self.sync.lock().unwrap();
+        self.free_list.size(page_offset as _) as usize
+    }
+
+    /// Allocate `pages` pages from the free list at an address below `limit`, for relocating an
+    /// object that starts at `limit` to a lower address.  This does not grow the space.  Return
+    /// `None` if there are no such free pages.
+    pub(crate) fn alloc_pages_below(&self, pages: usize, limit: Address) -> Option<Address> {
+        debug_assert!(!self.protect_memory_on_release);
+        // FIXME: We need a safe implementation
+        let self_mut = unsafe { self.inner_mut() };
+        let mut sync = self.sync.lock().unwrap();
+        let page_offset = self_mut.free_list.alloc(pages as _);
+        if page_offset == freelist::FAILURE {
+            return None;
+        }
+        let rtn = self.start + conversions::pages_to_bytes(page_offset as _);
+        if rtn >= limit {
+            // Moving the object up does not help compaction.  Put the pages back.
+            self_mut.free_list.free(page_offset, true);
+            return None;
+        }
+        sync.pages_currently_on_freelist -= pages;
+        if page_offset > sync.highwater_mark {
+            sync.highwater_mark = page_offset;
+        }
+        self.common().accounting.reserve_and_commit(pages);
+        Some(rtn)
+    }
+
+    /// Report the free pages in the free list, and how fragmented they are.
+    pub(crate) fn free_list_report(&self) -> FreeListReport {
+        let _sync = self.sync.lock().unwrap();
+        let mut lumps = vec![];
+        self.free_list.for_each_free(&mut |unit, size| {
+            lumps.push((unit as usize, size as usize));
+        });
+        FreeListReport::from_free_lumps(lumps)
+    }
+
     pub fn release_pages(&self, first: Address) {
         debug_assert!(conversions::is_page_aligned(first));
         let page_offset = conversions::bytes_to_pages_up(first - self.start);
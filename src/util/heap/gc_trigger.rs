@@ -4,13 +4,15 @@ use crate::global_state::GlobalState;
 use crate::plan::gc_requester::GCRequester;
 use crate::plan::Plan;
 use crate::policy::space::Space;
-use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::constants::{BYTES_IN_HUGE_PAGE, BYTES_IN_PAGE};
 use crate::util::conversions;
+use crate::util::heap::memory_pressure::MemoryPressureMonitor;
+use crate::util::memory::MmapStrategy;
 use crate::util::options::{GCTriggerSelector, Options, DEFAULT_MAX_NURSERY, DEFAULT_MIN_NURSERY};
 use crate::vm::VMBinding;
 use crate::MMTK;
 use std::mem::MaybeUninit;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
 
 /// GCTrigger is responsible for triggering GCs based on the given policy.
@@ -26,6 +28,8 @@ pub struct GCTrigger<VM: VMBinding> {
     gc_requester: Arc<GCRequester<VM>>,
     options: Arc<Options>,
     state: Arc<GlobalState>,
+    /// Monitors the memory pressure of the host. `None` if it is disabled.
+    memory_pressure: Option<MemoryPressureMonitor>,
 }
 
 impl<VM: VMBinding> GCTrigger<VM> {
@@ -38,7 +42,7 @@ impl<VM: VMBinding> GCTrigger<VM> {
             plan: MaybeUninit::uninit(),
             policy: match *options.gc_trigger {
                 GCTriggerSelector::FixedHeapSize(size) => Box::new(FixedHeapSizeTrigger {
-                    total_pages: conversions::bytes_to_pages_up(size),
+                    total_pages: AtomicUsize::new(conversions::bytes_to_pages_up(size)),
                 }),
                 GCTriggerSelector::DynamicHeapSize(min, max) => Box::new(MemBalancerTrigger::new(
                     conversions::bytes_to_pages_up(min),
@@ -48,6 +52,10 @@ impl<VM: VMBinding> GCTrigger<VM> {
                     <VM::VMCollection as crate::vm::Collection<VM>>::create_gc_trigger()
                 }
             },
+            memory_pressure: MemoryPressureMonitor::new(
+                &options.memory_pressure,
+                *options.memory_pressure_interval,
+            ),
             options,
             gc_requester,
             state,
@@ -72,10 +80,7 @@ impl<VM: VMBinding> GCTrigger<VM> {
     /// * `space`: The space that triggered the poll. This could `None` if the poll is not triggered by a space.
     pub fn poll(&self, space_full: bool, space: Option<&dyn Space<VM>>) -> bool {
         let plan = unsafe { self.plan.assume_init() };
-        if self
-            .policy
-            .is_gc_required(space_full, space.map(|s| SpaceStats::new(s)), plan)
-        {
+        if self.is_gc_required(space_full, space, plan) {
             info!(
                 "[POLL] {}{} ({}/{} pages)",
                 if let Some(space) = space {
@@ -90,9 +95,55 @@ impl<VM: VMBinding> GCTrigger<VM> {
             self.gc_requester.request();
             return true;
         }
+        if self.is_memory_pressure_high(plan) {
+            info!(
+                "[POLL] Triggering collection due to memory pressure ({}/{} pages)",
+                plan.get_reserved_pages(),
+                plan.get_total_pages(),
+            );
+            if *self.options.memory_pressure_shrink_heap {
+                self.policy.on_memory_pressure();
+            }
+            self.gc_requester.request();
+            return true;
+        }
         false
     }
 
+    /// Ask the policy if a GC is required.  Memory that is left for GC workers to sweep after the
+    /// last GC (see the option `background_sweeping`) is still accounted as used, so we finish the
+    /// sweeping and ask again before we trigger a GC.
+    fn is_gc_required(
+        &self,
+        space_full: bool,
+        space: Option<&dyn Space<VM>>,
+        plan: &dyn Plan<VM = VM>,
+    ) -> bool {
+        let is_gc_required = || {
+            self.policy
+                .is_gc_required(space_full, space.map(|s| SpaceStats::new(s)), plan)
+        };
+        if !is_gc_required() {
+            return false;
+        }
+        if !*self.options.background_sweeping {
+            return true;
+        }
+        let mut swept = false;
+        plan.for_each_space(&mut |space| swept |= space.finish_background_sweeping());
+        !swept || is_gc_required()
+    }
+
+    /// Check the memory pressure of the host if the monitor is enabled. We only report memory
+    /// pressure if the plan can collect garbage and GC is initialized.
+    fn is_memory_pressure_high(&self, plan: &dyn Plan<VM = VM>) -> bool {
+        self.memory_pressure.as_ref().is_some_and(|monitor| {
+            plan.constraints().collects_garbage
+                && self.state.is_initialized()
+                && monitor.is_under_pressure()
+        })
+    }
+
     pub fn should_do_stress_gc(&self) -> bool {
         Self::should_do_stress_gc_inner(&self.state, &self.options)
     }
@@ -109,6 +160,16 @@ impl<VM: VMBinding> GCTrigger<VM> {
         self.policy.is_heap_full(self.plan())
     }
 
+    /// Change the bounds of the heap size (in bytes) of the current policy. Return `false` if the
+    /// policy does not support it.
+    pub fn set_heap_size_bounds(&self, min_bytes: usize, max_bytes: usize) -> bool {
+        debug_assert!(min_bytes <= max_bytes);
+        self.policy.set_heap_size_bounds(
+            conversions::bytes_to_pages_up(min_bytes),
+            conversions::bytes_to_pages_up(max_bytes),
+        )
+    }
+
     /// Return upper bound of the nursery size (in number of bytes)
     pub fn get_max_nursery_bytes(&self) -> usize {
         use crate::util::options::NurserySize;
@@ -156,7 +217,15 @@ impl<VM: VMBinding> GCTrigger<VM> {
 
     /// Return upper bound of the nursery size (in number of pages)
     pub fn get_max_nursery_pages(&self) -> usize {
-        crate::util::conversions::bytes_to_pages_up(self.get_max_nursery_bytes())
+        let max_bytes = self.get_max_nursery_bytes();
+        // A copying nursery is bump allocated from its start after each GC.  If the heap is backed
+        // by huge pages, let the nursery fill the last huge page it uses before it is collected.
+        let max_bytes = if MmapStrategy::from_options(&self.options).uses_huge_pages() {
+            conversions::raw_align_up(max_bytes, BYTES_IN_HUGE_PAGE)
+        } else {
+            max_bytes
+        };
+        crate::util::conversions::bytes_to_pages_up(max_bytes)
     }
 
     /// Return lower bound of the nursery size (in number of pages)
@@ -226,11 +295,22 @@ pub trait GCTriggerPolicy<VM: VMBinding>: Sync + Send {
     fn get_max_heap_size_in_pages(&self) -> usize;
     /// Can the heap size grow?
     fn can_heap_size_grow(&self) -> bool;
+    /// Change the lower and the upper bound of the heap size (in pages) at run time. Return `false`
+    /// if the policy does not support changing its bounds. The caller guarantees `min_pages <= max_pages`.
+    /// This may be called by a mutator while GC threads are running, so the implementation needs to
+    /// update its states atomically.
+    fn set_heap_size_bounds(&self, _min_pages: usize, _max_pages: usize) -> bool {
+        false
+    }
+    /// Inform the triggering policy that the memory pressure of the host is high, and a GC is triggered.
+    /// A policy with dynamic heap size may use a smaller heap size after the GC.
+    /// This is only called if the option `memory_pressure_shrink_heap` is set.
+    fn on_memory_pressure(&self) {}
 }
 
 /// A simple GC trigger that uses a fixed heap size.
 pub struct FixedHeapSizeTrigger {
-    total_pages: usize,
+    total_pages: AtomicUsize,
 }
 impl<VM: VMBinding> GCTriggerPolicy<VM> for FixedHeapSizeTrigger {
     fn is_gc_required(
@@ -245,20 +325,26 @@ impl<VM: VMBinding> GCTriggerPolicy<VM> for FixedHeapSizeTrigger {
 
     fn is_heap_full(&self, plan: &dyn Plan<VM = VM>) -> bool {
         // If reserved pages is larger than the total pages, the heap is full.
-        plan.get_reserved_pages() > self.total_pages
+        plan.get_reserved_pages() > self.total_pages.load(Ordering::Relaxed)
     }
 
     fn get_current_heap_size_in_pages(&self) -> usize {
-        self.total_pages
+        self.total_pages.load(Ordering::Relaxed)
     }
 
     fn get_max_heap_size_in_pages(&self) -> usize {
-        self.total_pages
+        self.total_pages.load(Ordering::Relaxed)
     }
 
     fn can_heap_size_grow(&self) -> bool {
         false
     }
+
+    fn set_heap_size_bounds(&self, _min_pages: usize, max_pages: usize) -> bool {
+        // The heap size is fixed, so we only use the upper bound.
+        self.total_pages.store(max_pages, Ordering::Relaxed);
+        true
+    }
 }
 
 use atomic_refcell::AtomicRefCell;
@@ -271,11 +357,13 @@ use std::time::Instant;
 // TODO: implement a complete mem balancer.
 pub struct MemBalancerTrigger {
     /// The min heap size
-    min_heap_pages: usize,
+    min_heap_pages: AtomicUsize,
     /// The max heap size
-    max_heap_pages: usize,
+    max_heap_pages: AtomicUsize,
     /// The current heap size
     current_heap_pages: AtomicUsize,
+    /// Set if the memory pressure of the host is high. The next heap limit will be computed with less headroom.
+    memory_pressure: AtomicBool,
     /// The number of pending allocation pages. The allocation requests for them have failed, and a GC is triggered.
     /// We will need to take them into consideration so that the new heap size can accomodate those allocations.
     pending_pages: AtomicUsize,
@@ -519,18 +607,36 @@ impl<VM: VMBinding> GCTriggerPolicy<VM> for MemBalancerTrigger {
     }
 
     fn get_max_heap_size_in_pages(&self) -> usize {
-        self.max_heap_pages
+        self.max_heap_pages.load(Ordering::Relaxed)
     }
 
     fn can_heap_size_grow(&self) -> bool {
-        self.current_heap_pages.load(Ordering::Relaxed) < self.max_heap_pages
+        self.current_heap_pages.load(Ordering::Relaxed)
+            < self.max_heap_pages.load(Ordering::Relaxed)
+    }
+
+    fn set_heap_size_bounds(&self, min_pages: usize, max_pages: usize) -> bool {
+        self.min_heap_pages.store(min_pages, Ordering::Relaxed);
+        self.max_heap_pages.store(max_pages, Ordering::Relaxed);
+        // Keep the current heap size within the new bounds. It will be recomputed at the end of the next GC.
+        let _ =
+            self.current_heap_pages
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                    Some(current.clamp(min_pages, max_pages))
+                });
+        true
+    }
+
+    fn on_memory_pressure(&self) {
+        self.memory_pressure.store(true, Ordering::Relaxed);
     }
 }
 impl MemBalancerTrigger {
     fn new(min_heap_pages: usize, max_heap_pages: usize) -> Self {
         Self {
-            min_heap_pages,
-            max_heap_pages,
+            min_heap_pages: AtomicUsize::new(min_heap_pages),
+            max_heap_pages: AtomicUsize::new(max_heap_pages),
+            memory_pressure: AtomicBool::new(false),
             pending_pages: AtomicUsize::new(0),
             // start with min heap
             current_heap_pages: AtomicUsize::new(min_heap_pages),
@@ -618,6 +724,17 @@ impl MemBalancerTrigger {
             (live as f64 * 4096f64).sqrt()
         };
 
+        // Under memory pressure, halve the headroom above the live memory.
+        let e = if self.memory_pressure.swap(false, Ordering::Relaxed) {
+            debug!(
+                "MemBalancer: memory pressure is high. Use half of the headroom {}",
+                e
+            );
+            e / 2f64
+        } else {
+            e
+        };
+
         // Get pending allocations
         let pending_pages = self.pending_pages.load(Ordering::SeqCst);
 
@@ -631,10 +748,12 @@ impl MemBalancerTrigger {
         );
 
         // The new heap size must be within min/max.
-        let new_heap = optimal_heap.clamp(self.min_heap_pages, self.max_heap_pages);
+        let min_heap_pages = self.min_heap_pages.load(Ordering::Relaxed);
+        let max_heap_pages = self.max_heap_pages.load(Ordering::Relaxed);
+        let new_heap = optimal_heap.clamp(min_heap_pages, max_heap_pages.max(min_heap_pages));
         debug!(
             "MemBalander: new heap limit = {} pages (optimal = {}, clamped to [{}, {}])",
-            new_heap, optimal_heap, self.min_heap_pages, self.max_heap_pages
+            new_heap, optimal_heap, min_heap_pages, max_heap_pages
         );
         self.current_heap_pages.store(new_heap, Ordering::Relaxed);
     }
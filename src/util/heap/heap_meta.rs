@@ -1,38 +1,146 @@
+use crate::util::heap::layout::heap_parameters::MAX_SPACES;
 use crate::util::heap::layout::vm_layout::vm_layout;
+use crate::util::memory::MmapStrategy;
 use crate::util::Address;
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+
+lazy_static! {
+    /// The part of the heap range that is not reserved by any MMTk instance.  It is shared by all
+    /// the MMTk instances, so that each instance reserves a distinct address range for its spaces.
+    static ref UNRESERVED_HEAP: Mutex<HeapMeta> = Mutex::new(HeapMeta::new());
+}
 
 pub struct HeapMeta {
     pub heap_cursor: Address,
     pub heap_limit: Address,
+    /// The number of MMTk instances that have reserved address ranges and are not destroyed.
+    instances: usize,
+    /// The mmap strategy of the MMTk instances that are not destroyed.  The mmapper is shared by
+    /// all the instances, so they must use the same strategy.
+    mmap_strategy: MmapStrategy,
+}
+
+/// The address ranges that an MMTk instance reserved from the heap range, from the bottom and
+/// from the top.
+#[derive(Clone, Debug)]
+pub(crate) struct HeapReservation {
+    bottom: Range<Address>,
+    top: Range<Address>,
 }
 
 impl HeapMeta {
     pub fn new() -> Self {
         HeapMeta {
             heap_cursor: vm_layout().heap_start,
-            heap_limit: vm_layout().heap_end,
+            heap_limit: Self::reservable_end(),
+            instances: 0,
+            mmap_strategy: MmapStrategy::Normal,
         }
     }
 
-    pub fn reserve(&mut self, extent: usize, top: bool) -> Address {
-        let ret = if top {
-            self.heap_limit -= extent;
-            self.heap_limit
+    /// The end of the heap range in which spaces can be reserved.  With contiguous spaces, the VM
+    /// map indexes spaces by the address bits above the space extent, and it only has room for
+    /// `MAX_SPACES` spaces, which may end below the heap range.
+    fn reservable_end() -> Address {
+        let layout = vm_layout();
+        if cfg!(target_pointer_width = "64") && layout.force_use_contiguous_spaces {
+            let map_end = unsafe { Address::from_usize(MAX_SPACES << layout.log_space_extent) };
+            layout.heap_end.min(map_end)
         } else {
-            let start = self.heap_cursor;
-            self.heap_cursor += extent;
-            start
-        };
+            layout.heap_end
+        }
+    }
 
+    /// Lock the part of the heap range that is not reserved by any MMTk instance.  An MMTk
+    /// instance holds the lock while creating its spaces, so MMTk instances are created one at a
+    /// time.
+    pub(crate) fn lock_unreserved() -> MutexGuard<'static, HeapMeta> {
+        UNRESERVED_HEAP.lock().unwrap()
+    }
+
+    /// Record the mmap strategy of a new MMTk instance.  The first instance decides the strategy,
+    /// and later instances must use the same one.  Return the strategy of the existing instances
+    /// as an error if it is different.
+    pub(crate) fn use_mmap_strategy(&mut self, strategy: MmapStrategy) -> Result<(), MmapStrategy> {
+        if self.instances == 0 {
+            self.mmap_strategy = strategy;
+        }
+        if self.mmap_strategy == strategy {
+            Ok(())
+        } else {
+            Err(self.mmap_strategy)
+        }
+    }
+
+    /// Start reserving address ranges for a new MMTk instance.
+    pub(crate) fn begin_reservation(&self) -> HeapReservation {
+        HeapReservation {
+            bottom: self.heap_cursor..self.heap_cursor,
+            top: self.heap_limit..self.heap_limit,
+        }
+    }
+
+    /// Finish reserving address ranges for a new MMTk instance, and record the address ranges
+    /// reserved since `begin_reservation`.
+    pub(crate) fn end_reservation(&mut self, reservation: &mut HeapReservation) {
+        reservation.bottom.end = self.heap_cursor;
+        reservation.top.start = self.heap_limit;
+        self.instances += 1;
+    }
+
+    /// Give back the address ranges reserved since `begin_reservation` if a new MMTk instance fails
+    /// to be created, and return the ranges.
+    pub(crate) fn cancel_reservation(
+        &mut self,
+        reservation: HeapReservation,
+    ) -> [Range<Address>; 2] {
+        let ranges = [
+            reservation.bottom.start..self.heap_cursor,
+            self.heap_limit..reservation.top.end,
+        ];
+        self.heap_cursor = reservation.bottom.start;
+        self.heap_limit = reservation.top.end;
+        ranges
+    }
+
+    /// Return the address ranges reserved by a destroyed MMTk instance.  The ranges can be reserved
+    /// again if no other instance has reserved address ranges after them.  Return `true` if no
+    /// MMTk instance is left, in which case the whole heap range is unreserved.
+    pub(crate) fn release_reservation(&mut self, reservation: HeapReservation) -> bool {
+        self.instances -= 1;
+        if self.instances == 0 {
+            *self = HeapMeta::new();
+            return true;
+        }
+        if self.heap_cursor == reservation.bottom.end {
+            self.heap_cursor = reservation.bottom.start;
+        }
+        if self.heap_limit == reservation.top.start {
+            self.heap_limit = reservation.top.end;
+        }
+        false
+    }
+
+    pub fn reserve(&mut self, extent: usize, top: bool) -> Address {
+        // Check before reserving, so the unreserved range is still valid if we fail.
         assert!(
-            self.heap_cursor <= self.heap_limit,
-            "Out of virtual address space at {} ({} > {})",
-            self.heap_cursor - extent,
+            extent <= self.heap_limit - self.heap_cursor,
+            "Out of virtual address space: cannot reserve {} bytes in {}..{}. The spaces of all the \
+             MMTk instances need to fit in the heap range.",
+            extent,
             self.heap_cursor,
             self.heap_limit
         );
 
-        ret
+        if top {
+            self.heap_limit -= extent;
+            self.heap_limit
+        } else {
+            let start = self.heap_cursor;
+            self.heap_cursor += extent;
+            start
+        }
     }
 
     pub fn get_discontig_start(&self) -> Address {
@@ -50,3 +158,83 @@ impl Default for HeapMeta {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_reservation() {
+        let mut heap = HeapMeta::new();
+        let (start, end) = (heap.heap_cursor, heap.heap_limit);
+        const EXTENT: usize = 1 << 22;
+
+        let mut first = heap.begin_reservation();
+        heap.reserve(EXTENT, false);
+        heap.reserve(EXTENT, true);
+        heap.end_reservation(&mut first);
+
+        let mut second = heap.begin_reservation();
+        let second_start = heap.reserve(EXTENT, false);
+        heap.end_reservation(&mut second);
+
+        // The ranges of the first instance cannot be reused while the second one is alive,
+        // except for the top one.
+        assert!(!heap.release_reservation(first));
+        assert_eq!(heap.heap_cursor, second_start + EXTENT);
+        assert_eq!(heap.heap_limit, end);
+
+        // The whole heap range is unreserved when the last instance is destroyed.
+        assert!(heap.release_reservation(second));
+        assert_eq!(heap.heap_cursor, start);
+        assert_eq!(heap.heap_limit, end);
+    }
+
+    #[test]
+    fn test_use_mmap_strategy() {
+        let mut heap = HeapMeta::new();
+        const EXTENT: usize = 1 << 22;
+
+        // The first instance decides the strategy.
+        assert!(heap
+            .use_mmap_strategy(MmapStrategy::TransparentHugePages)
+            .is_ok());
+        let mut first = heap.begin_reservation();
+        heap.reserve(EXTENT, false);
+        heap.end_reservation(&mut first);
+
+        assert_eq!(
+            heap.use_mmap_strategy(MmapStrategy::Normal),
+            Err(MmapStrategy::TransparentHugePages)
+        );
+        assert!(heap
+            .use_mmap_strategy(MmapStrategy::TransparentHugePages)
+            .is_ok());
+
+        // A new instance may use another strategy once no instance is left.
+        assert!(heap.release_reservation(first));
+        assert!(heap.use_mmap_strategy(MmapStrategy::Normal).is_ok());
+    }
+
+    #[test]
+    fn test_reserve_out_of_space() {
+        let mut heap = HeapMeta::new();
+        let (start, end) = (heap.heap_cursor, heap.heap_limit);
+        const EXTENT: usize = 1 << 22;
+
+        let reservation = heap.begin_reservation();
+        heap.reserve(EXTENT, false);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            heap.reserve(end - start, true)
+        }));
+        assert!(result.is_err());
+        // The failed reservation did not change the unreserved range.
+        assert_eq!(heap.heap_cursor, start + EXTENT);
+        assert_eq!(heap.heap_limit, end);
+
+        let ranges = heap.cancel_reservation(reservation);
+        assert_eq!(ranges, [start..start + EXTENT, end..end]);
+        assert_eq!(heap.heap_cursor, start);
+        assert_eq!(heap.heap_limit, end);
+    }
+}
@@ -130,6 +130,17 @@ impl Mmapper for ByteMapMmapper {
             MapState::transition_to_protected(&self.mapped[chunk], mmap_start).unwrap();
         }
     }
+
+    fn unmap(&self, start: Address, pages: usize) {
+        let start_chunk = Self::address_to_mmap_chunks_down(start);
+        let end_chunk = Self::address_to_mmap_chunks_up(start + pages_to_bytes(pages));
+        let _guard = self.lock.lock().unwrap();
+
+        for chunk in start_chunk..end_chunk {
+            let mmap_start = Self::mmap_chunks_to_address(chunk);
+            MapState::transition_to_unmapped(&self.mapped[chunk], mmap_start).unwrap();
+        }
+    }
 }
 
 impl ByteMapMmapper {
@@ -347,6 +358,49 @@ mod tests {
         })
     }
 
+    #[test]
+    fn unmap() {
+        serial_test(|| {
+            let test_memory_bytes = MMAP_CHUNK_BYTES * 2;
+            let test_memory_pages = test_memory_bytes >> LOG_BYTES_IN_PAGE;
+            let unmap_memory_pages = MMAP_CHUNK_BYTES >> LOG_BYTES_IN_PAGE;
+            with_cleanup(
+                || {
+                    // map 2 chunks
+                    let mmapper = ByteMapMmapper::new();
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, test_memory_pages)
+                        .unwrap();
+
+                    // unmap 1 chunk
+                    mmapper.unmap(FIXED_ADDRESS, unmap_memory_pages);
+
+                    let chunk = ByteMapMmapper::address_to_mmap_chunks_down(FIXED_ADDRESS);
+                    assert_eq!(
+                        mmapper.mapped[chunk].load(Ordering::Relaxed),
+                        MapState::Unmapped
+                    );
+                    assert_eq!(
+                        mmapper.mapped[chunk + 1].load(Ordering::Relaxed),
+                        MapState::Mapped
+                    );
+
+                    // We can map the chunk again
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, unmap_memory_pages)
+                        .unwrap();
+                    assert_eq!(
+                        mmapper.mapped[chunk].load(Ordering::Relaxed),
+                        MapState::Mapped
+                    );
+                },
+                || {
+                    memory::munmap(FIXED_ADDRESS, test_memory_bytes).unwrap();
+                },
+            )
+        })
+    }
+
     #[test]
     fn ensure_mapped_on_protected_chunks() {
         serial_test(|| {
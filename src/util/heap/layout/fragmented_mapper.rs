@@ -225,6 +225,31 @@ impl Mmapper for FragmentedMapper {
             start = high;
         }
     }
+
+    fn unmap(&self, mut start: Address, pages: usize) {
+        let end = start + conversions::pages_to_bytes(pages);
+        // Iterate over the slabs covered
+        while start < end {
+            let base = Self::slab_align_down(start);
+            let high = if end > Self::slab_limit(start) && !Self::slab_limit(start).is_zero() {
+                Self::slab_limit(start)
+            } else {
+                end
+            };
+
+            // We have never mapped anything in a slab that is not allocated.
+            if let Some(mapped) = self.slab_table(start) {
+                let _guard = self.lock.lock().unwrap();
+                let start_chunk = Self::chunk_index(base, start);
+                let end_chunk = Self::chunk_index(base, conversions::mmap_chunk_align_up(high));
+                for (chunk, entry) in mapped.iter().enumerate().take(end_chunk).skip(start_chunk) {
+                    let mmap_start = Self::chunk_index_to_address(base, chunk);
+                    MapState::transition_to_unmapped(entry, mmap_start).unwrap();
+                }
+            }
+            start = high;
+        }
+    }
 }
 
 impl FragmentedMapper {
@@ -538,6 +563,46 @@ mod tests {
         })
     }
 
+    #[test]
+    fn unmap() {
+        serial_test(|| {
+            with_cleanup(
+                || {
+                    // map 2 chunks
+                    let mmapper = FragmentedMapper::new();
+                    let pages_per_chunk = MMAP_CHUNK_BYTES >> LOG_BYTES_IN_PAGE as usize;
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, pages_per_chunk * 2)
+                        .unwrap();
+
+                    // unmap 1 chunk
+                    mmapper.unmap(FIXED_ADDRESS, pages_per_chunk);
+
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS),
+                        Some(MapState::Unmapped)
+                    );
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS + MMAP_CHUNK_BYTES),
+                        Some(MapState::Mapped)
+                    );
+
+                    // We can map the chunk again
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, pages_per_chunk)
+                        .unwrap();
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS),
+                        Some(MapState::Mapped)
+                    );
+                },
+                || {
+                    memory::munmap(FIXED_ADDRESS, MAX_BYTES).unwrap();
+                },
+            )
+        })
+    }
+
     #[test]
     fn ensure_mapped_on_protected_chunks() {
         serial_test(|| {
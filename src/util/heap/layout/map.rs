@@ -64,4 +64,23 @@ pub trait VMMap: Sync {
     fn get_descriptor_for_address(&self, address: Address) -> SpaceDescriptor;
 
     fn add_to_cumulative_committed_pages(&self, pages: usize);
+
+    /// Release the entries for the contiguous spaces in [`start`, `start + extent`), so that new
+    /// spaces can use the address range.  This is called when the MMTk instance that owns the
+    /// spaces is destroyed, or fails to be created.
+    ///
+    /// # Safety
+    ///
+    /// The spaces must not be used any more, and the caller must ensure that only one thread is
+    /// calling this method.
+    unsafe fn release_space(&self, start: Address, extent: usize);
+
+    /// Reset the map to its initial state.  This is called after all the MMTk instances are
+    /// destroyed, so that a new MMTk instance can be created.
+    ///
+    /// # Safety
+    ///
+    /// No space may use the map any more, and the caller must ensure that only one thread is
+    /// calling this method.
+    unsafe fn reset(&self);
 }
@@ -269,6 +269,26 @@ impl VMMap for Map32 {
         self.cumulative_committed_pages
             .fetch_add(pages, Ordering::Relaxed);
     }
+
+    unsafe fn release_space(&self, start: Address, extent: usize) {
+        let (_sync, self_mut) = self.mut_self_with_sync();
+        let mut e = 0;
+        while e < extent {
+            let index = (start + e).chunk_index();
+            self_mut.descriptor_map[index] = SpaceDescriptor::UNINITIALIZED;
+            e += BYTES_IN_CHUNK;
+        }
+    }
+
+    unsafe fn reset(&self) {
+        let (_sync, self_mut) = self.mut_self_with_sync();
+        let cumulative_committed_pages =
+            self_mut.cumulative_committed_pages.load(Ordering::Relaxed);
+        *self_mut = Map32::new().inner.into_inner();
+        self_mut
+            .cumulative_committed_pages
+            .store(cumulative_committed_pages, Ordering::Relaxed);
+    }
 }
 
 impl Map32 {
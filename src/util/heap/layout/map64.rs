@@ -23,6 +23,9 @@ struct Map64Inner {
     fl_page_resources: Vec<Option<NonNull<CommonFreeListPageResource>>>,
     fl_map: Vec<Option<NonNull<RawMemoryFreeList>>>,
     finalized: bool,
+    /// Have we finalized the free lists of each space?  Each MMTk instance boots and finalizes
+    /// the spaces it created, and must not touch the spaces of other instances.
+    finalized_spaces: Vec<bool>,
     descriptor_map: Vec<SpaceDescriptor>,
     base_address: Vec<Address>,
     high_water: Vec<Address>,
@@ -58,6 +61,7 @@ impl Map64 {
                 fl_page_resources: vec![None; MAX_SPACES],
                 fl_map: vec![None; MAX_SPACES],
                 finalized: false,
+                finalized_spaces: vec![false; MAX_SPACES],
                 cumulative_committed_pages: AtomicUsize::new(0),
             }),
         }
@@ -191,6 +195,9 @@ impl VMMap for Map64 {
         // It is fine to get a mutable reference.
         let self_mut: &mut Map64Inner = unsafe { self.mut_self() };
         for pr in 0..MAX_SPACES {
+            if self_mut.finalized_spaces[pr] {
+                continue;
+            }
             if let Some(mut fl) = self_mut.fl_map[pr] {
                 let fl_mut: &mut RawMemoryFreeList = unsafe { fl.as_mut() };
                 fl_mut.grow_freelist(0);
@@ -203,11 +210,15 @@ impl VMMap for Map64 {
         // It is fine to get a mutable reference.
         let self_mut: &mut Map64Inner = unsafe { self.mut_self() };
         for pr in 0..MAX_SPACES {
+            if self_mut.finalized_spaces[pr] {
+                continue;
+            }
             if let Some(mut fl) = self_mut.fl_page_resources[pr] {
                 let fl_mut = unsafe { fl.as_mut() };
                 fl_mut.resize_freelist(conversions::chunk_align_up(unsafe {
                     self.inner().fl_map[pr].unwrap().as_ref().get_limit()
                 }));
+                self_mut.finalized_spaces[pr] = true;
             }
         }
         self_mut.finalized = true;
@@ -227,6 +238,31 @@ impl VMMap for Map64 {
             .cumulative_committed_pages
             .fetch_add(pages, Ordering::Relaxed);
     }
+
+    unsafe fn release_space(&self, start: Address, extent: usize) {
+        let self_mut = self.mut_self();
+        let first = Self::space_index(start).unwrap();
+        let last = Self::space_index(start + extent - 1).unwrap();
+        for index in first..=last {
+            let base = Address::from_usize(index << vm_layout().log_space_extent);
+            self_mut.descriptor_map[index] = SpaceDescriptor::UNINITIALIZED;
+            self_mut.fl_page_resources[index] = None;
+            self_mut.fl_map[index] = None;
+            self_mut.finalized_spaces[index] = false;
+            self_mut.base_address[index] = base;
+            self_mut.high_water[index] = base;
+        }
+    }
+
+    unsafe fn reset(&self) {
+        let self_mut = self.mut_self();
+        let cumulative_committed_pages =
+            self_mut.cumulative_committed_pages.load(Ordering::Relaxed);
+        *self_mut = Map64::new().inner.into_inner();
+        self_mut
+            .cumulative_committed_pages
+            .store(cumulative_committed_pages, Ordering::Relaxed);
+    }
 }
 
 impl Map64 {
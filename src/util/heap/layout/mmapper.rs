@@ -62,6 +62,14 @@ pub trait Mmapper: Sync {
     /// * `start`: Address of the first page to be protected
     /// * `pages`: Number of pages to be protected
     fn protect(&self, start: Address, pages: usize);
+
+    /// Unmap a number of pages, and mark them as unmapped so that they can be mapped again.
+    /// Note that unmapping occurs at chunk granularity, not page granularity.
+    ///
+    /// Arguments:
+    /// * `start`: Address of the first page to be unmapped
+    /// * `pages`: Number of pages to be unmapped
+    fn unmap(&self, start: Address, pages: usize);
 }
 
 /// The mmap state of a mmap chunk.
@@ -200,6 +208,29 @@ impl MapState {
         Ok(())
     }
 
+    /// Check the current MapState of the chunk, and transition the chunk to MapState::Unmapped.
+    /// The caller should hold a lock before invoking this method.
+    pub(super) fn transition_to_unmapped(
+        state: &Atomic<MapState>,
+        mmap_start: Address,
+    ) -> Result<()> {
+        trace!(
+            "Trying to unmap {} - {}",
+            mmap_start,
+            mmap_start + MMAP_CHUNK_BYTES
+        );
+        let res = match state.load(Ordering::Relaxed) {
+            MapState::Unmapped => Ok(()),
+            MapState::Quarantined | MapState::Mapped | MapState::Protected => {
+                munmap(mmap_start, MMAP_CHUNK_BYTES)
+            }
+        };
+        if res.is_ok() {
+            state.store(MapState::Unmapped, Ordering::Relaxed);
+        }
+        res
+    }
+
     /// Check the current MapState of the chunk, and transition the chunk to MapState::Protected.
     /// The caller should hold a lock before invoking this method.
     pub(super) fn transition_to_protected(
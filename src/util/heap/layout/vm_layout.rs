@@ -150,6 +150,63 @@ impl VMLayout {
         layout64
     }
 
+    /// A layout for a heap of `heap_size` bytes whose references are compressed into 32 bits, for
+    /// example with [`crate::vm::edge_shape::CompressedEdge`].  `shift` is log2 of the object
+    /// alignment, i.e. the number of low bits dropped when compressing a reference.
+    ///
+    /// The heap is placed at a low address so that references can be compressed with a zero base
+    /// if the heap ends below `4G << shift`.  Otherwise, references are compressed relative to a
+    /// base below the heap start, and the heap must be smaller than `4G << shift`.  Use
+    /// [`VMLayout::compressed_heap_base`] to get the base.
+    #[cfg(target_pointer_width = "64")]
+    pub fn new_compressed(heap_size: usize, shift: usize) -> Self {
+        let start = if cfg!(target_os = "macos") {
+            // Impossible to map 0x4000_0000 on macOS. So choose a different address.
+            0x40_0000_0000
+        } else {
+            0x4000_0000
+        };
+        let reach = 1usize << (32 + shift);
+        let end = match start + heap_size {
+            // Unscaled: references are the addresses themselves.
+            end if end <= (1usize << 32) => 1usize << 32,
+            // Zero-based.
+            end if end <= reach => reach,
+            // Base-relative.  Leave room for the base below the heap start.
+            _ => {
+                assert!(
+                    heap_size <= reach - BYTES_IN_CHUNK,
+                    "The heap size {} is too large to compress references with shift {}",
+                    heap_size,
+                    shift
+                );
+                start + reach - BYTES_IN_CHUNK
+            }
+        };
+        let layout = Self {
+            log_address_space: (usize::BITS - (end - 1).leading_zeros()) as usize,
+            heap_start: chunk_align_down(unsafe { Address::from_usize(start) }),
+            heap_end: chunk_align_up(unsafe { Address::from_usize(end) }),
+            log_space_extent: 31,
+            force_use_contiguous_spaces: false,
+        };
+        layout.validate();
+        layout
+    }
+
+    /// The base for compressing references into 32 bits with the given `shift` (see
+    /// [`VMLayout::new_compressed`]).  It is zero if the heap ends below `4G << shift`.  Otherwise,
+    /// it is one alignment unit below the heap start, so that no object is compressed to 0, which
+    /// represents null.
+    #[cfg(target_pointer_width = "64")]
+    pub fn compressed_heap_base(&self, shift: usize) -> Address {
+        if self.heap_end.as_usize() <= (1usize << (32 + shift)) {
+            Address::ZERO
+        } else {
+            self.heap_start - (1usize << shift)
+        }
+    }
+
     /// Custom VM layout constants. VM bindings may use this function for compressed or 39-bit heap support.
     /// This function must be called before MMTk::new()
     pub(crate) fn set_custom_vm_layout(constants: VMLayout) {
@@ -195,3 +252,45 @@ pub fn vm_layout() -> &'static VMLayout {
     }
     unsafe { &*addr_of!(VM_LAYOUT) }
 }
+
+#[cfg(all(test, target_pointer_width = "64"))]
+mod tests {
+    use super::*;
+
+    const SHIFT: usize = 3;
+
+    #[test]
+    fn test_compressed_unscaled() {
+        let layout = VMLayout::new_compressed(1 << 20, SHIFT);
+        assert_eq!(layout.heap_end.as_usize(), 1 << 32);
+        assert_eq!(layout.log_address_space, 32);
+        if !cfg!(target_os = "macos") {
+            assert_eq!(layout.compressed_heap_base(SHIFT), Address::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_compressed_zero_based() {
+        let layout = VMLayout::new_compressed(8 << 30, SHIFT);
+        if !cfg!(target_os = "macos") {
+            assert_eq!(layout.heap_end.as_usize(), 32 << 30);
+            assert_eq!(layout.log_address_space, 35);
+            assert_eq!(layout.compressed_heap_base(SHIFT), Address::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_compressed_base_relative() {
+        let layout = VMLayout::new_compressed(31 << 30 | 1 << 29, SHIFT);
+        let base = layout.compressed_heap_base(SHIFT);
+        assert_eq!(base, layout.heap_start - (1usize << SHIFT));
+        // All the heap can be reached from the base with 32-bit compressed references.
+        assert!(layout.heap_end - base <= (u32::MAX as usize) << SHIFT);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compressed_heap_too_large() {
+        VMLayout::new_compressed(32 << 30, SHIFT);
+    }
+}
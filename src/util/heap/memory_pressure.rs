@@ -0,0 +1,192 @@
+use crate::util::options::MemoryPressureSelector;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Checks the memory pressure from a PSI file or a cgroup `memory.events` file. The file is read
+/// at most once in each interval, so it is cheap to call [`MemoryPressureMonitor::is_under_pressure`]
+/// frequently, e.g. each time we poll for GC.
+pub(crate) struct MemoryPressureMonitor {
+    selector: MemoryPressureSelector,
+    interval: Duration,
+    /// The time of the last check. `None` if we have not checked yet.
+    last_check: Mutex<Option<Instant>>,
+    /// The sum of the `high`, `max` and `oom` counters in `memory.events` at the last check.
+    last_events: AtomicU64,
+}
+
+impl MemoryPressureMonitor {
+    /// Create a monitor. Return `None` if memory pressure monitoring is disabled.
+    pub fn new(selector: &MemoryPressureSelector, interval_ms: usize) -> Option<Self> {
+        if *selector == MemoryPressureSelector::Disabled {
+            return None;
+        }
+        let monitor = Self {
+            selector: selector.clone(),
+            interval: Duration::from_millis(interval_ms as u64),
+            last_check: Mutex::new(None),
+            last_events: AtomicU64::new(0),
+        };
+        // Take the current event counters as the baseline. Events before MMTk starts do not count.
+        if let MemoryPressureSelector::CgroupEvents { path } = &monitor.selector {
+            if let Some(events) = read_memory_events(path) {
+                monitor.last_events.store(events, Ordering::Relaxed);
+            }
+        }
+        Some(monitor)
+    }
+
+    /// Return true if the memory pressure is high. This returns false without reading the file
+    /// if the last check is within the interval, or if another thread is checking.
+    pub fn is_under_pressure(&self) -> bool {
+        let Ok(mut last_check) = self.last_check.try_lock() else {
+            return false;
+        };
+        let now = Instant::now();
+        if matches!(*last_check, Some(last) if now.duration_since(last) < self.interval) {
+            return false;
+        }
+        *last_check = Some(now);
+        self.check()
+    }
+
+    /// Read the file and check the memory pressure.
+    fn check(&self) -> bool {
+        match &self.selector {
+            MemoryPressureSelector::Disabled => false,
+            MemoryPressureSelector::Psi { path, threshold } => match read_psi_some_avg10(path) {
+                Some(avg10) => {
+                    trace!("Memory pressure: some avg10 = {} ({})", avg10, path);
+                    avg10 > *threshold
+                }
+                None => false,
+            },
+            MemoryPressureSelector::CgroupEvents { path } => match read_memory_events(path) {
+                Some(events) => {
+                    let last = self.last_events.swap(events, Ordering::Relaxed);
+                    trace!("Memory events: {} (last: {}) ({})", events, last, path);
+                    events > last
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+fn read_psi_some_avg10(path: &str) -> Option<f64> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| warn!("Failed to read memory pressure from {}: {}", path, e))
+        .ok()?;
+    parse_psi_some_avg10(&content)
+}
+
+fn read_memory_events(path: &str) -> Option<u64> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| warn!("Failed to read memory events from {}: {}", path, e))
+        .ok()?;
+    parse_memory_events(&content)
+}
+
+/// Parse the `avg10` value of the `some` line in a PSI file, e.g.
+/// `some avg10=0.00 avg60=0.00 avg300=0.00 total=0`.
+fn parse_psi_some_avg10(content: &str) -> Option<f64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("some "))
+        .and_then(|line| {
+            line.split_ascii_whitespace()
+                .find_map(|field| field.strip_prefix("avg10="))
+        })
+        .and_then(|value| value.parse::<f64>().ok())
+}
+
+/// Parse a cgroup v2 `memory.events` file, and return the sum of the `high`, `max` and `oom`
+/// counters. They increase when the cgroup is throttled or reclaimed because of its memory limits.
+fn parse_memory_events(content: &str) -> Option<u64> {
+    let mut found = false;
+    let mut sum = 0u64;
+    for line in content.lines() {
+        if let Some((key, value)) = line.split_once(' ') {
+            if matches!(key, "high" | "max" | "oom") {
+                sum = sum.wrapping_add(value.trim().parse::<u64>().ok()?);
+                found = true;
+            }
+        }
+    }
+    found.then_some(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PSI: &str = "some avg10=12.50 avg60=3.00 avg300=1.00 total=12345\n\
+                       full avg10=1.00 avg60=0.50 avg300=0.10 total=678\n";
+    const EVENTS: &str = "low 0\nhigh 3\nmax 2\noom 0\noom_kill 0\noom_group_kill 0\n";
+
+    #[test]
+    fn test_parse_psi() {
+        assert_eq!(parse_psi_some_avg10(PSI), Some(12.5));
+        assert_eq!(parse_psi_some_avg10(""), None);
+        assert_eq!(parse_psi_some_avg10("full avg10=1.00\n"), None);
+    }
+
+    #[test]
+    fn test_parse_memory_events() {
+        assert_eq!(parse_memory_events(EVENTS), Some(5));
+        assert_eq!(parse_memory_events("low 1\n"), None);
+        assert_eq!(parse_memory_events("high abc\n"), None);
+    }
+
+    fn with_stand_in_file<F: FnOnce(&str)>(name: &str, content: &str, f: F) {
+        let path = std::env::temp_dir().join(format!(
+            "mmtk-memory-pressure-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, content).unwrap();
+        f(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_psi_monitor() {
+        with_stand_in_file("psi", PSI, |path| {
+            let high = MemoryPressureSelector::Psi {
+                path: path.to_string(),
+                threshold: 10f64,
+            };
+            let monitor = MemoryPressureMonitor::new(&high, 60_000).unwrap();
+            assert!(monitor.is_under_pressure());
+            // Within the interval.
+            assert!(!monitor.is_under_pressure());
+
+            let low = MemoryPressureSelector::Psi {
+                path: path.to_string(),
+                threshold: 20f64,
+            };
+            let monitor = MemoryPressureMonitor::new(&low, 60_000).unwrap();
+            assert!(!monitor.is_under_pressure());
+        })
+    }
+
+    #[test]
+    fn test_cgroup_events_monitor() {
+        with_stand_in_file("events", EVENTS, |path| {
+            let selector = MemoryPressureSelector::CgroupEvents {
+                path: path.to_string(),
+            };
+            let monitor = MemoryPressureMonitor::new(&selector, 60_000).unwrap();
+            // The events before the monitor is created do not count.
+            assert!(!monitor.check());
+            std::fs::write(path, "low 0\nhigh 4\nmax 2\noom 0\n").unwrap();
+            assert!(monitor.check());
+            assert!(!monitor.check());
+        })
+    }
+
+    #[test]
+    fn test_disabled_monitor() {
+        assert!(MemoryPressureMonitor::new(&MemoryPressureSelector::Disabled, 1000).is_none());
+    }
+}
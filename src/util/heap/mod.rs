@@ -7,9 +7,11 @@ pub(crate) mod externalpageresource;
 pub(crate) mod freelistpageresource;
 pub(crate) mod gc_trigger;
 mod heap_meta;
+pub(crate) mod memory_pressure;
 pub(crate) mod monotonepageresource;
 pub(crate) mod pageresource;
 pub(crate) mod space_descriptor;
+pub(crate) mod unswept_chunks;
 mod vmrequest;
 
 pub(crate) use self::accounting::PageAccounting;
@@ -17,7 +19,7 @@ pub(crate) use self::blockpageresource::BlockPageResource;
 pub(crate) use self::freelistpageresource::FreeListPageResource;
 pub use self::gc_trigger::GCTriggerPolicy;
 pub use self::gc_trigger::SpaceStats;
-pub(crate) use self::heap_meta::HeapMeta;
+pub(crate) use self::heap_meta::{HeapMeta, HeapReservation};
 pub use self::layout::vm_layout;
 pub(crate) use self::monotonepageresource::MonotonePageResource;
 pub(crate) use self::pageresource::PageResource;
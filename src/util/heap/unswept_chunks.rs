@@ -0,0 +1,217 @@
+use crate::util::options::Options;
+use crate::util::Address;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// The chunks of a space that are swept by GC workers after mutators are resumed (see the option
+/// `background_sweeping`).  Each chunk is swept by exactly one thread.  A GC worker or a mutator
+/// claims a chunk before sweeping it, and a mutator that is about to use memory in a chunk makes
+/// sure the chunk is swept first.
+pub(crate) struct UnsweptChunks {
+    /// The number of chunks that are not swept yet, including the chunks being swept.
+    remaining: AtomicUsize,
+    state: Mutex<UnsweptChunksState>,
+    /// Notified when a chunk is swept.
+    chunk_swept: Condvar,
+}
+
+#[derive(Default)]
+struct UnsweptChunksState {
+    /// The chunks that nobody has claimed.
+    unclaimed: HashSet<Address>,
+    /// The chunks that are being swept.
+    sweeping: HashSet<Address>,
+}
+
+impl UnsweptChunksState {
+    /// Claim the chunk for sweeping.  Return false if it is not unclaimed.
+    fn claim(&mut self, chunk: Address) -> bool {
+        if self.unclaimed.remove(&chunk) {
+            self.sweeping.insert(chunk);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl UnsweptChunks {
+    /// Create the tracker if the `background_sweeping` option is enabled.  Return `None` otherwise.
+    pub fn new(options: &Options) -> Option<Self> {
+        (*options.background_sweeping).then(Self::new_empty)
+    }
+
+    fn new_empty() -> Self {
+        Self {
+            remaining: AtomicUsize::new(0),
+            state: Mutex::new(UnsweptChunksState::default()),
+            chunk_swept: Condvar::new(),
+        }
+    }
+
+    /// Set the chunks to be swept after the current GC.  All the chunks of the last GC must have
+    /// been swept.
+    pub fn set(&self, chunks: impl IntoIterator<Item = Address>) {
+        let mut state = self.state.lock().unwrap();
+        debug_assert!(
+            state.unclaimed.is_empty() && state.sweeping.is_empty(),
+            "Chunks of the last GC are not swept"
+        );
+        state.unclaimed.extend(chunks);
+        self.remaining
+            .store(state.unclaimed.len(), Ordering::SeqCst);
+    }
+
+    /// Return true if all the chunks are swept.
+    pub fn all_swept(&self) -> bool {
+        self.remaining.load(Ordering::SeqCst) == 0
+    }
+
+    /// Sweep the chunk with `sweep` if nobody has claimed it.  Return false without waiting if the
+    /// chunk is swept, or is being swept by another thread.
+    pub fn try_sweep(&self, chunk: Address, sweep: impl FnOnce()) -> bool {
+        if self.all_swept() || !self.state.lock().unwrap().claim(chunk) {
+            return false;
+        }
+        sweep();
+        self.finish(chunk);
+        true
+    }
+
+    /// Make sure the chunk is swept before its memory is used.  Sweep it with `sweep` if nobody
+    /// has claimed it, or wait for the thread that is sweeping it.
+    pub fn ensure_swept(&self, chunk: Address, sweep: impl FnOnce()) {
+        if self.all_swept() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.claim(chunk) {
+            drop(state);
+            sweep();
+            self.finish(chunk);
+            return;
+        }
+        while state.sweeping.contains(&chunk) {
+            state = self.chunk_swept.wait(state).unwrap();
+        }
+    }
+
+    /// Claim any unclaimed chunk, and sweep it with `sweep`.  Return false if there is no
+    /// unclaimed chunk.
+    pub fn sweep_any(&self, sweep: impl FnOnce(Address)) -> bool {
+        if self.all_swept() {
+            return false;
+        }
+        let chunk = {
+            let mut state = self.state.lock().unwrap();
+            let Some(chunk) = state.unclaimed.iter().next().copied() else {
+                return false;
+            };
+            state.claim(chunk);
+            chunk
+        };
+        sweep(chunk);
+        self.finish(chunk);
+        true
+    }
+
+    /// Sweep all the unclaimed chunks with `sweep`, and wait for the chunks that other threads are
+    /// sweeping.  Return false if all the chunks were already swept.
+    pub fn sweep_all(&self, sweep: impl Fn(Address)) -> bool {
+        if self.all_swept() {
+            return false;
+        }
+        while self.sweep_any(&sweep) {}
+        let mut state = self.state.lock().unwrap();
+        while !state.sweeping.is_empty() {
+            state = self.chunk_swept.wait(state).unwrap();
+        }
+        true
+    }
+
+    fn finish(&self, chunk: Address) {
+        let mut state = self.state.lock().unwrap();
+        let removed = state.sweeping.remove(&chunk);
+        debug_assert!(removed);
+        self.remaining.fetch_sub(1, Ordering::SeqCst);
+        self.chunk_swept.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn chunk(i: usize) -> Address {
+        unsafe { Address::from_usize(BYTES_IN_CHUNK * (16 + i)) }
+    }
+
+    #[test]
+    fn test_sweep_each_chunk_once() {
+        let unswept = UnsweptChunks::new_empty();
+        assert!(unswept.all_swept());
+        unswept.set((0..3).map(chunk));
+        assert!(!unswept.all_swept());
+
+        let mut swept = vec![];
+        assert!(unswept.try_sweep(chunk(1), || swept.push(chunk(1))));
+        // Already swept.
+        assert!(!unswept.try_sweep(chunk(1), || unreachable!()));
+        unswept.ensure_swept(chunk(1), || unreachable!());
+        // Not a chunk to sweep.
+        assert!(!unswept.try_sweep(chunk(5), || unreachable!()));
+
+        while unswept.sweep_any(|c| swept.push(c)) {}
+        assert!(unswept.all_swept());
+        swept.sort();
+        assert_eq!(swept, (0..3).map(chunk).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_wait_for_sweeping() {
+        let unswept = Arc::new(UnsweptChunks::new_empty());
+        unswept.set([chunk(0)]);
+        let sweeping = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let sweeper = {
+            let unswept = unswept.clone();
+            let sweeping = sweeping.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                assert!(unswept.try_sweep(chunk(0), || {
+                    sweeping.store(true, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    done.store(true, Ordering::SeqCst);
+                }));
+            })
+        };
+        while !sweeping.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        // The chunk is claimed by the sweeper.  Wait until it is swept.
+        unswept.ensure_swept(chunk(0), || unreachable!());
+        assert!(done.load(Ordering::SeqCst));
+        sweeper.join().unwrap();
+        assert!(unswept.all_swept());
+    }
+
+    #[test]
+    fn test_sweep_all() {
+        let unswept = UnsweptChunks::new_empty();
+        unswept.set((0..3).map(chunk));
+        let swept = Mutex::new(vec![]);
+        assert!(unswept.try_sweep(chunk(1), || swept.lock().unwrap().push(chunk(1))));
+        assert!(unswept.sweep_all(|c| swept.lock().unwrap().push(c)));
+        assert!(unswept.all_swept());
+        let mut swept = swept.into_inner().unwrap();
+        swept.sort();
+        assert_eq!(swept, (0..3).map(chunk).collect::<Vec<_>>());
+        // Nothing left to sweep.
+        assert!(!unswept.sweep_all(|_| unreachable!()));
+    }
+}
@@ -354,6 +354,20 @@ mod tests {
         assert_eq!(res4, 4);
     }
 
+    #[test]
+    fn for_each_free() {
+        let mut l = IntArrayFreeList::new(LIST_SIZE, 2, 1);
+        let res1 = l.alloc(2);
+        assert_eq!(res1, 0);
+        let res2 = l.alloc(1);
+        assert_eq!(res2, 2);
+
+        let mut lumps = vec![];
+        l.for_each_free(&mut |unit, size| lumps.push((unit, size)));
+        lumps.sort_unstable();
+        assert_eq!(lumps, vec![(3, 1), (4, 1)]);
+    }
+
     #[test]
     fn multi_heads_alloc_free() {
         let parent = IntArrayFreeList::new(LIST_SIZE, 1, 2);
@@ -1,4 +1,84 @@
+use crate::mmtk::SFT_MAP;
+use crate::util::constants::BYTES_IN_ADDRESS;
+use crate::util::{Address, ObjectReference};
+use crate::vm::{RootsWorkFactory, VMBinding};
+
 /// The region size (in bytes) of the `VO_BIT` side metadata.
 /// The VM can use this to check if an object is properly aligned.
 pub const VO_BIT_REGION_SIZE: usize =
     1usize << crate::util::metadata::vo_bit::VO_BIT_SIDE_METADATA_SPEC.log_bytes_in_region;
+
+/// How far (in bytes) we search backwards from an ambiguous word for the object it may point
+/// into.  Words pointing further than this into an object are not recognized as interior pointers.
+pub const MAX_INTERIOR_POINTER_OFFSET: usize = 4096;
+
+/// The maximum number of objects we put into one pinning roots work packet.
+const ROOTS_PER_PACKET: usize = 4096;
+
+/// Statistics of a conservative scan of a memory range by
+/// [`crate::memory_manager::scan_conservative_range`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConservativeScanStats {
+    /// The number of words inspected in the range.
+    pub words_scanned: usize,
+    /// The number of words that point to, or into, an MMTk object, i.e. the ambiguous roots found.
+    /// A word that points into an object is counted even if the object has been seen before.
+    pub ambiguous_roots: usize,
+    /// The number of distinct objects reported to the roots work factory as pinning roots.
+    pub pinned_objects: usize,
+}
+
+/// Return the object that `addr` points to or into, if any.  This is the filter we apply to each
+/// word during conservative scanning.
+fn resolve_ambiguous_pointer<VM: VMBinding>(addr: Address) -> Option<ObjectReference> {
+    if addr.is_zero() {
+        return None;
+    }
+
+    // The common case: the word is a valid object reference.  `is_mmtk_object` is imprecise for
+    // misaligned addresses, so we recover the actual object from the VO bit region it hits.
+    if SFT_MAP.get_checked(addr).is_mmtk_object(addr) {
+        let raw = ObjectReference::from_raw_address(addr)?;
+        let region = raw.to_address::<VM>().align_down(VO_BIT_REGION_SIZE);
+        return Some(ObjectReference::from_address::<VM>(region));
+    }
+
+    // Otherwise, the word may be an interior pointer.
+    SFT_MAP
+        .get_checked(addr)
+        .find_object_from_internal_pointer(addr, MAX_INTERIOR_POINTER_OFFSET)
+}
+
+/// Scan the words in `[start, end)` conservatively, and report the objects they point to (or
+/// into) as pinning roots.  See [`crate::memory_manager::scan_conservative_range`].
+pub(crate) fn scan_conservative_range<VM: VMBinding, F: RootsWorkFactory<VM::VMEdge>>(
+    start: Address,
+    end: Address,
+    factory: &mut F,
+) -> ConservativeScanStats {
+    let mut stats = ConservativeScanStats::default();
+    let mut roots = vec![];
+
+    let mut cur = start.align_up(BYTES_IN_ADDRESS);
+    while cur + BYTES_IN_ADDRESS <= end {
+        let word = unsafe { cur.load::<Address>() };
+        stats.words_scanned += 1;
+        if let Some(object) = resolve_ambiguous_pointer::<VM>(word) {
+            stats.ambiguous_roots += 1;
+            roots.push(object);
+        }
+        cur += BYTES_IN_ADDRESS;
+    }
+
+    // The same object is often referenced from many stack slots.  Report each object only once.
+    roots.sort_unstable();
+    roots.dedup();
+    stats.pinned_objects = roots.len();
+
+    for chunk in roots.chunks(ROOTS_PER_PACKET) {
+        factory.create_process_pinning_roots_work(chunk.to_vec());
+    }
+
+    trace!("Conservatively scanned {} to {}: {:?}", start, end, stats);
+    stats
+}
@@ -1,5 +1,6 @@
 use crate::util::alloc::AllocationError;
 use crate::util::opaque_pointer::*;
+use crate::util::options::Options;
 use crate::util::Address;
 use crate::vm::{Collection, VMBinding};
 use bytemuck::NoUninit;
@@ -70,12 +71,34 @@ const MMAP_PROT: libc::c_int = PROT_READ | PROT_WRITE;
 /// methods. However, this can later be refactored to reduce other code
 /// repetition.
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, NoUninit)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, NoUninit)]
 pub enum MmapStrategy {
     /// The default mmap strategy.
     Normal,
     /// Enable transparent huge pages for the pages that are mapped. This option is only for linux.
     TransparentHugePages,
+    /// Map explicit huge pages from the huge page pool (`MAP_HUGETLB`). The mapped ranges must be
+    /// aligned to huge pages, and the pool must have enough pages. This option is only for linux.
+    ExplicitHugePages,
+}
+
+impl MmapStrategy {
+    /// The strategy for mapping the heap, selected by the `transparent_hugepages` and
+    /// `explicit_hugepages` options.
+    pub fn from_options(options: &Options) -> Self {
+        if *options.explicit_hugepages {
+            MmapStrategy::ExplicitHugePages
+        } else if *options.transparent_hugepages {
+            MmapStrategy::TransparentHugePages
+        } else {
+            MmapStrategy::Normal
+        }
+    }
+
+    /// Return true if the heap is backed by huge pages.
+    pub fn uses_huge_pages(&self) -> bool {
+        *self != MmapStrategy::Normal
+    }
 }
 
 /// Demand-zero mmap (no replace):
@@ -112,12 +135,21 @@ fn mmap_fixed(
     strategy: MmapStrategy,
 ) -> Result<()> {
     let ptr = start.to_mut_ptr();
+    #[cfg(target_os = "linux")]
+    let flags = if strategy == MmapStrategy::ExplicitHugePages {
+        flags | libc::MAP_HUGETLB
+    } else {
+        flags
+    };
     wrap_libc_call(
         &|| unsafe { libc::mmap(start.to_mut_ptr(), size, prot, flags, -1, 0) },
         ptr,
     )?;
     match strategy {
         MmapStrategy::Normal => Ok(()),
+        // `MAP_HUGETLB` is added to the flags above. Setting the explicit hugepage option to true
+        // will not pass the validation on non-Linux OSes.
+        MmapStrategy::ExplicitHugePages => Ok(()),
         MmapStrategy::TransparentHugePages => {
             #[cfg(target_os = "linux")]
             {
@@ -134,6 +166,46 @@ fn mmap_fixed(
     }
 }
 
+/// Move the pages in `[from, from + size)` to `to` without copying them (`mremap` with
+/// `MREMAP_FIXED`), and map fresh zeroed pages at `from` so that the source range stays mapped.
+/// Both ranges must be page-aligned, mapped by MMTk, and not overlapping.  The pages at `to` are
+/// replaced.
+#[cfg(target_os = "linux")]
+pub fn mremap_fixed(from: Address, to: Address, size: usize, strategy: MmapStrategy) -> Result<()> {
+    let ret = unsafe {
+        libc::mremap(
+            from.to_mut_ptr(),
+            size,
+            size,
+            libc::MREMAP_MAYMOVE | libc::MREMAP_FIXED,
+            to.to_mut_ptr::<libc::c_void>(),
+        )
+    };
+    if ret == libc::MAP_FAILED {
+        return Err(Error::last_os_error());
+    }
+    // `mremap` unmaps the source range.  The pages have been moved, so we cannot recover from an
+    // error here.
+    unsafe { dzmmap(from, size, strategy) }.unwrap_or_else(|e| {
+        panic!(
+            "Failed to map {} after moving its pages to {}: {}",
+            from, to, e
+        )
+    });
+    Ok(())
+}
+
+/// Move pages without copying them. This is not supported on this OS, and this always fails.
+#[cfg(not(target_os = "linux"))]
+pub fn mremap_fixed(
+    _from: Address,
+    _to: Address,
+    _size: usize,
+    _strategy: MmapStrategy,
+) -> Result<()> {
+    Err(Error::from(std::io::ErrorKind::Unsupported))
+}
+
 /// Unmap the given memory (in page granularity). This wraps the unsafe libc munmap call.
 pub fn munmap(start: Address, size: usize) -> Result<()> {
     wrap_libc_call(&|| unsafe { libc::munmap(start.to_mut_ptr(), size) }, 0)
@@ -222,6 +294,40 @@ pub fn mprotect(start: Address, size: usize) -> Result<()> {
     )
 }
 
+/// Set the memory policy of the given memory (in page granularity) so that its pages are preferably
+/// allocated on the given NUMA node (`mbind` with `MPOL_PREFERRED`). This only affects the pages
+/// that are not yet touched.
+#[cfg(target_os = "linux")]
+pub fn mbind_preferred(start: Address, size: usize, node: usize) -> Result<()> {
+    const MPOL_PREFERRED: libc::c_long = 1;
+    const BITS_IN_WORD: usize = libc::c_ulong::BITS as usize;
+    let mut nodemask = vec![0 as libc::c_ulong; node / BITS_IN_WORD + 1];
+    nodemask[node / BITS_IN_WORD] |= 1 << (node % BITS_IN_WORD);
+    // The kernel only reads `maxnode - 1` bits.
+    let maxnode = nodemask.len() * BITS_IN_WORD + 1;
+    wrap_libc_call(
+        &|| unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                start.to_mut_ptr::<libc::c_void>(),
+                size,
+                MPOL_PREFERRED,
+                nodemask.as_ptr(),
+                maxnode,
+                0,
+            )
+        },
+        0,
+    )
+}
+
+/// Set the memory policy of the given memory so that its pages are preferably allocated on the
+/// given NUMA node. NUMA is not supported on this OS, and this always fails.
+#[cfg(not(target_os = "linux"))]
+pub fn mbind_preferred(_start: Address, _size: usize, _node: usize) -> Result<()> {
+    Err(Error::from(std::io::ErrorKind::Unsupported))
+}
+
 fn wrap_libc_call<T: PartialEq>(f: &dyn Fn() -> T, expect: T) -> Result<()> {
     let ret = f();
     if ret == expect {
@@ -231,6 +337,85 @@ fn wrap_libc_call<T: PartialEq>(f: &dyn Fn() -> T, expect: T) -> Result<()> {
     }
 }
 
+/// How much of a range of memory is backed by huge pages, read from `/proc/self/smaps`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HugePageCoverage {
+    /// The resident bytes, including huge pages.
+    pub resident_bytes: usize,
+    /// The resident bytes in transparent huge pages or `MAP_HUGETLB` pages.
+    pub huge_page_bytes: usize,
+}
+
+impl HugePageCoverage {
+    /// The fraction of the resident bytes that are in huge pages.
+    pub fn ratio(&self) -> f64 {
+        if self.resident_bytes == 0 {
+            0f64
+        } else {
+            self.huge_page_bytes as f64 / self.resident_bytes as f64
+        }
+    }
+}
+
+/// Get the huge page coverage of the mappings that overlap with `[start, end)`. Return `None` if
+/// the memory maps cannot be read. This is only supported on Linux.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn get_huge_page_coverage(start: Address, end: Address) -> Option<HugePageCoverage> {
+    let smaps = std::fs::read_to_string("/proc/self/smaps")
+        .map_err(|e| warn!("Failed to read /proc/self/smaps: {}", e))
+        .ok()?;
+    Some(parse_huge_page_coverage(&smaps, start, end))
+}
+
+/// Get the huge page coverage of the mappings that overlap with `[start, end)`. This is not
+/// supported on this OS, and this always returns `None`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn get_huge_page_coverage(_start: Address, _end: Address) -> Option<HugePageCoverage> {
+    None
+}
+
+/// Sum the resident and huge page sizes of the mappings that overlap with `[start, end)` in the
+/// content of a `smaps` file. Transparent huge pages are counted in `Rss`, but `MAP_HUGETLB` pages
+/// are not.
+#[cfg(any(target_os = "linux", target_os = "android", test))]
+fn parse_huge_page_coverage(smaps: &str, start: Address, end: Address) -> HugePageCoverage {
+    let mut coverage = HugePageCoverage::default();
+    let mut in_range = false;
+    for line in smaps.lines() {
+        let mut fields = line.split_ascii_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+        if let Some((from, to)) = first.split_once('-') {
+            // A mapping header, e.g. `7f0000000000-7f0000200000 rw-p 00000000 00:00 0`.
+            if let (Ok(from), Ok(to)) = (
+                usize::from_str_radix(from, 16),
+                usize::from_str_radix(to, 16),
+            ) {
+                in_range = from < end.as_usize() && to > start.as_usize();
+                continue;
+            }
+        }
+        if !in_range {
+            continue;
+        }
+        let Some(kb) = fields.next().and_then(|v| v.parse::<usize>().ok()) else {
+            continue;
+        };
+        let bytes = kb << 10;
+        match first {
+            "Rss:" => coverage.resident_bytes += bytes,
+            "AnonHugePages:" => coverage.huge_page_bytes += bytes,
+            "Shared_Hugetlb:" | "Private_Hugetlb:" => {
+                coverage.resident_bytes += bytes;
+                coverage.huge_page_bytes += bytes;
+            }
+            _ => {}
+        }
+    }
+    coverage
+}
+
 /// Get the memory maps for the process. The returned string is a multi-line string.
 /// This is only meant to be used for debugging. For example, log process memory maps after detecting a clash.
 #[cfg(any(target_os = "linux", target_os = "android"))]
@@ -270,6 +455,92 @@ pub(crate) fn get_system_total_memory() -> u64 {
     sys.total_memory()
 }
 
+/// The default mount point of the cgroup file system.
+pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// The environment variable that overrides the mount point of the cgroup file system.
+/// This is mainly used for testing against fake cgroup files.
+pub const CGROUP_ROOT_ENV_VAR: &str = "MMTK_CGROUP_ROOT";
+
+/// Returns the memory available to this process in bytes. This is the total physical memory,
+/// or the cgroup memory limit of the process if it is smaller (e.g. when running in a container).
+pub(crate) fn get_available_memory() -> u64 {
+    let total = get_system_total_memory();
+    match get_cgroup_memory_limit() {
+        Some(limit) if limit < total => limit,
+        _ => total,
+    }
+}
+
+/// Returns the cgroup memory limit of the current process in bytes, or `None` if there is no limit
+/// or the cgroup files cannot be read. The cgroup root is [`DEFAULT_CGROUP_ROOT`] unless it is
+/// overridden by the environment variable [`CGROUP_ROOT_ENV_VAR`].
+pub(crate) fn get_cgroup_memory_limit() -> Option<u64> {
+    let root =
+        std::env::var(CGROUP_ROOT_ENV_VAR).unwrap_or_else(|_| DEFAULT_CGROUP_ROOT.to_string());
+    let self_cgroup = std::fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    cgroup_memory_limit(std::path::Path::new(&root), &self_cgroup)
+}
+
+/// Find the memory limit for the cgroups listed in `self_cgroup` (the content of `/proc/self/cgroup`)
+/// in the cgroup file system mounted at `root`. Both cgroup v2 (`memory.max`) and cgroup v1
+/// (`memory/memory.limit_in_bytes`) are checked. The limit of a cgroup is the smallest limit of
+/// itself and its ancestors. If the cgroup directory is not found (e.g. the cgroup namespace of a
+/// container), the files at `root` are used.
+fn cgroup_memory_limit(root: &std::path::Path, self_cgroup: &str) -> Option<u64> {
+    use std::path::Path;
+
+    // Each line is "hierarchy-ID:controller-list:cgroup-path".
+    let mut v2_path = "/";
+    let mut v1_path = "/";
+    for line in self_cgroup.lines() {
+        let mut fields = line.splitn(3, ':');
+        let (Some(id), Some(controllers), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if id == "0" && controllers.is_empty() {
+            v2_path = path;
+        } else if controllers.split(',').any(|c| c == "memory") {
+            v1_path = path;
+        }
+    }
+
+    // Read a limit file. "max" means no limit. cgroup v1 uses a very large number for no limit,
+    // which is filtered by the caller when it is compared with the total memory.
+    let read_limit = |file: &Path| -> Option<u64> {
+        let content = std::fs::read_to_string(file).ok()?;
+        let content = content.trim();
+        if content == "max" {
+            None
+        } else {
+            content.parse::<u64>().ok()
+        }
+    };
+
+    // The smallest limit of the cgroup and its ancestors under `dir`.
+    let limit_in = |dir: &Path, path: &str, file: &str| -> Option<u64> {
+        let cgroup_dir = dir.join(path.trim_start_matches('/'));
+        let start = if cgroup_dir.is_dir() {
+            cgroup_dir.as_path()
+        } else {
+            dir
+        };
+        start
+            .ancestors()
+            .take_while(|d| d.starts_with(dir))
+            .filter_map(|d| read_limit(&d.join(file)))
+            .min()
+    };
+
+    let v2 = limit_in(root, v2_path, "memory.max");
+    let v1 = limit_in(&root.join("memory"), v1_path, "memory.limit_in_bytes");
+    match (v2, v1) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,6 +624,32 @@ mod tests {
         })
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mremap_fixed() {
+        serial_test(|| {
+            with_cleanup(
+                || {
+                    let to = START + 2 * BYTES_IN_PAGE;
+                    let bytes = 2 * BYTES_IN_PAGE;
+                    assert!(
+                        dzmmap_noreplace(START, 4 * BYTES_IN_PAGE, MmapStrategy::Normal).is_ok()
+                    );
+                    set(START, 0xab, bytes);
+                    assert!(mremap_fixed(START, to, bytes, MmapStrategy::Normal).is_ok());
+                    // The pages are moved, and the source range is mapped with zeroed pages.
+                    let moved = unsafe { std::slice::from_raw_parts(to.to_ptr::<u8>(), bytes) };
+                    assert!(moved.iter().all(|b| *b == 0xab));
+                    let source = unsafe { std::slice::from_raw_parts(START.to_ptr::<u8>(), bytes) };
+                    assert!(source.iter().all(|b| *b == 0));
+                },
+                || {
+                    assert!(munmap(START, 4 * BYTES_IN_PAGE).is_ok());
+                },
+            )
+        })
+    }
+
     #[cfg(target_os = "linux")]
     #[test]
     #[should_panic]
@@ -432,4 +729,116 @@ mod tests {
         let total = get_system_total_memory();
         println!("Total memory: {:?}", total);
     }
+
+    /// Create a fake cgroup file system in a temporary directory. Each entry is a file path relative to the root and its content.
+    fn with_fake_cgroup<F: FnOnce(&std::path::Path) + std::panic::UnwindSafe>(
+        name: &str,
+        files: &[(&str, &str)],
+        f: F,
+    ) {
+        let root =
+            std::env::temp_dir().join(format!("mmtk-fake-cgroup-{}-{}", name, std::process::id()));
+        with_cleanup(
+            || {
+                for (path, content) in files {
+                    let file = root.join(path);
+                    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+                    std::fs::write(file, content).unwrap();
+                }
+                f(&root);
+            },
+            || {
+                let _ = std::fs::remove_dir_all(&root);
+            },
+        )
+    }
+
+    #[test]
+    fn test_cgroup_v2_memory_limit() {
+        with_fake_cgroup(
+            "v2",
+            &[
+                ("memory.max", "max\n"),
+                ("app/memory.max", "1073741824\n"),
+                ("app/worker/memory.max", "max\n"),
+            ],
+            |root| {
+                // The limit of the parent cgroup applies.
+                assert_eq!(cgroup_memory_limit(root, "0::/app/worker\n"), Some(1 << 30));
+                // No limit at the root.
+                assert_eq!(cgroup_memory_limit(root, "0::/\n"), None);
+                // The cgroup is not found (e.g. cgroup namespace). Use the root.
+                assert_eq!(cgroup_memory_limit(root, "0::/other\n"), None);
+            },
+        )
+    }
+
+    #[test]
+    fn test_cgroup_v1_memory_limit() {
+        with_fake_cgroup(
+            "v1",
+            &[
+                ("memory/memory.limit_in_bytes", "536870912\n"),
+                (
+                    "memory/docker/abc/memory.limit_in_bytes",
+                    "9223372036854771712\n",
+                ),
+            ],
+            |root| {
+                assert_eq!(
+                    cgroup_memory_limit(root, "4:memory:/docker/abc\n1:cpu:/\n"),
+                    Some(512 << 20)
+                );
+                assert_eq!(cgroup_memory_limit(root, ""), Some(512 << 20));
+            },
+        )
+    }
+
+    #[test]
+    fn test_cgroup_root_env_var() {
+        serial_test(|| {
+            with_fake_cgroup("env", &[("memory.max", "16777216\n")], |root| {
+                with_cleanup(
+                    || {
+                        std::env::set_var(CGROUP_ROOT_ENV_VAR, root);
+                        assert_eq!(get_cgroup_memory_limit(), Some(16 << 20));
+                        assert_eq!(get_available_memory(), 16 << 20);
+                    },
+                    || std::env::remove_var(CGROUP_ROOT_ENV_VAR),
+                )
+            })
+        })
+    }
+
+    #[test]
+    fn test_parse_huge_page_coverage() {
+        let smaps = "\
+200000-600000 rw-p 00000000 00:00 0
+Size:               4096 kB
+Rss:                3072 kB
+AnonHugePages:      2048 kB
+600000-800000 rw-p 00000000 00:00 0 /anon_hugepage (deleted)
+Rss:                   0 kB
+Private_Hugetlb:    2048 kB
+7f0000000000-7f0000001000 r--p 00000000 08:01 42 /usr/lib/libc.so
+Rss:                   4 kB
+AnonHugePages:         0 kB
+";
+        let start = unsafe { Address::from_usize(0x200000) };
+        let end = unsafe { Address::from_usize(0x800000) };
+        let coverage = parse_huge_page_coverage(smaps, start, end);
+        assert_eq!(
+            coverage,
+            HugePageCoverage {
+                resident_bytes: 5 << 20,
+                huge_page_bytes: 4 << 20,
+            }
+        );
+        assert_eq!(coverage.ratio(), 0.8);
+
+        // Only the mappings that overlap with the range are counted.
+        let coverage = parse_huge_page_coverage(smaps, start, start + 0x1000usize);
+        assert_eq!(coverage.huge_page_bytes, 2 << 20);
+        assert_eq!(coverage.ratio(), 2f64 / 3f64);
+    }
 }
@@ -39,6 +39,14 @@ impl MetadataSpec {
         }
     }
 
+    /// The number of bits of the metadata for each object or region.
+    pub const fn num_of_bits(&self) -> usize {
+        match self {
+            MetadataSpec::InHeader(spec) => spec.num_of_bits,
+            MetadataSpec::OnSide(spec) => 1 << spec.log_num_of_bits,
+        }
+    }
+
     /// A function to non-atomically load the specified metadata's content.
     /// Returns the metadata value.
     ///
@@ -946,6 +946,59 @@ impl SideMetadataSpec {
             },
         )
     }
+
+    /// Search backwards for a data address whose side metadata value is non-zero.  The search
+    /// starts from the region that contains `data_addr`, and checks all the regions that start in
+    /// `[data_addr - search_limit_bytes, data_addr]`.  Whole metadata bytes that are zero are
+    /// skipped at once, so sparse metadata (such as VO bits) is searched efficiently.
+    ///
+    /// The side metadata may not be mapped for the addresses we check.  The search stops and
+    /// returns `None` once it reaches a chunk whose side metadata is not mapped.
+    ///
+    /// Returns the start address of the nearest region whose metadata is non-zero, or `None` if
+    /// no such region is found.
+    pub fn find_prev_non_zero_value<T: MetadataValue>(
+        &self,
+        data_addr: Address,
+        search_limit_bytes: usize,
+    ) -> Option<Address> {
+        let region_bytes = 1usize << self.log_bytes_in_region;
+        // The number of data bytes covered by one byte of metadata.
+        let bytes_per_meta_byte = 1usize << addr_rshift(self);
+        let search_end = data_addr.saturating_sub(search_limit_bytes);
+
+        let mut cursor = data_addr.align_down(region_bytes);
+        let mut mapped_chunk = None;
+        loop {
+            let chunk = cursor.align_down(BYTES_IN_CHUNK);
+            if mapped_chunk != Some(chunk) {
+                if !self.is_mapped(cursor) {
+                    return None;
+                }
+                mapped_chunk = Some(chunk);
+            }
+
+            // The lowest region we have checked in this iteration.
+            let checked = if self.log_num_of_bits < 3
+                && unsafe {
+                    address_to_meta_address(self, cursor).atomic_load::<AtomicU8>(Ordering::SeqCst)
+                } == 0
+            {
+                // All the regions covered by this metadata byte are zero.
+                cursor.align_down(bytes_per_meta_byte)
+            } else {
+                if !self.load_atomic::<T>(cursor, Ordering::SeqCst).is_zero() {
+                    return Some(cursor);
+                }
+                cursor
+            };
+
+            if checked <= search_end || checked.as_usize() < region_bytes {
+                return None;
+            }
+            cursor = checked - region_bytes;
+        }
+    }
 }
 
 impl fmt::Debug for SideMetadataSpec {
@@ -1179,6 +1232,39 @@ impl SideMetadataContext {
         Ok(())
     }
 
+    /// Unmap the metadata space for the data address range, so that the memory is returned to the
+    /// OS and the address range can be mapped again.  This is used when an MMTk instance is
+    /// destroyed.  This should be called at chunk granularity.
+    pub fn unmap_metadata_space(&self, start: Address, size: usize) {
+        debug!("unmap_metadata_space({}, 0x{:x})", start, size);
+        // Chunk aligned
+        debug_assert!(start.is_aligned_to(BYTES_IN_CHUNK));
+        debug_assert!(size % BYTES_IN_CHUNK == 0);
+
+        for spec in self.global.iter() {
+            munmap_contiguous_metadata_space(start, size, spec);
+        }
+
+        #[cfg(target_pointer_width = "64")]
+        for spec in self.local.iter() {
+            munmap_contiguous_metadata_space(start, size, spec);
+        }
+
+        #[cfg(target_pointer_width = "32")]
+        {
+            let lsize: usize = self
+                .local
+                .iter()
+                .map(|spec| {
+                    metadata_bytes_per_chunk(spec.log_bytes_in_region, spec.log_num_of_bits)
+                })
+                .sum();
+            if lsize > 0 {
+                munmap_per_chunk_metadata_space(start, size, lsize);
+            }
+        }
+    }
+
     /// Unmap the corresponding metadata space or panic.
     ///
     /// Note-1: This function is only used for test and debug right now.
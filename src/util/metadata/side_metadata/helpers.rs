@@ -1,7 +1,7 @@
 use super::SideMetadataSpec;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
 use crate::util::constants::{BITS_IN_WORD, BYTES_IN_PAGE, LOG_BITS_IN_BYTE};
-use crate::util::heap::layout::vm_layout::VMLayout;
+use crate::util::heap::layout::vm_layout::{VMLayout, MMAP_CHUNK_BYTES};
 #[cfg(target_pointer_width = "32")]
 use crate::util::metadata::side_metadata::address_to_chunked_meta_address;
 use crate::util::Address;
@@ -54,6 +54,46 @@ pub(crate) fn ensure_munmap_contiguous_metadata_space(
     mmap_size
 }
 
+/// Unmaps the metadata space (`spec`) for the specified data address range (`start` and `size`),
+/// so that the memory is returned to the OS and the range can be mapped again.
+pub(super) fn munmap_contiguous_metadata_space(
+    start: Address,
+    size: usize,
+    spec: &SideMetadataSpec,
+) {
+    let metadata_start = address_to_meta_address(spec, start);
+    let metadata_size = (size + ((1 << addr_rshift(spec)) - 1)) >> addr_rshift(spec);
+    munmap_metadata_range(metadata_start, metadata_start + metadata_size);
+}
+
+/// Unmaps the metadata range [`start`, `end`).  The metadata of other data address ranges may share
+/// the mmap chunks at both ends of the range.  So we only unmap the mmap chunks that are entirely in
+/// the range, and zero the rest of the range if it is mapped.
+pub(super) fn munmap_metadata_range(start: Address, end: Address) {
+    let unmap_start = start.align_up(MMAP_CHUNK_BYTES);
+    let unmap_end = end.align_down(MMAP_CHUNK_BYTES);
+    if unmap_start < unmap_end {
+        trace!("munmap_metadata_range({}, {})", unmap_start, unmap_end);
+        MMAPPER.unmap(unmap_start, (unmap_end - unmap_start) >> LOG_BYTES_IN_PAGE);
+        zero_metadata_if_mapped(start, unmap_start);
+        zero_metadata_if_mapped(unmap_end, end);
+    } else {
+        zero_metadata_if_mapped(start, end);
+    }
+}
+
+/// Zeroes the metadata range [`start`, `end`) in the mmap chunks that are mapped.
+fn zero_metadata_if_mapped(start: Address, end: Address) {
+    let mut cursor = start;
+    while cursor < end {
+        let next = std::cmp::min(cursor.align_down(MMAP_CHUNK_BYTES) + MMAP_CHUNK_BYTES, end);
+        if MMAPPER.is_mapped_address(cursor) {
+            crate::util::memory::zero(cursor, next - cursor);
+        }
+        cursor = next;
+    }
+}
+
 /// Tries to mmap the metadata space (`spec`) for the specified data address range (`start` and `size`).
 /// Setting `no_reserve` to true means the function will only map address range, without reserving swap-space/physical memory.
 /// Returns the size in bytes that gets mmapped in the function if success.
@@ -169,6 +169,18 @@ pub(super) fn try_map_per_chunk_metadata_space(
     Ok(total_mapped)
 }
 
+/// Unmaps the local metadata for the chunks in the specified data address range (`start` and `size`).
+pub(super) fn munmap_per_chunk_metadata_space(start: Address, size: usize, local_per_chunk: usize) {
+    let mut aligned_start = start.align_down(BYTES_IN_CHUNK);
+    let aligned_end = (start + size).align_up(BYTES_IN_CHUNK);
+
+    while aligned_start < aligned_end {
+        let policy_meta_start = address_to_meta_chunk_addr(aligned_start);
+        super::munmap_metadata_range(policy_meta_start, policy_meta_start + local_per_chunk);
+        aligned_start += BYTES_IN_CHUNK;
+    }
+}
+
 // Try to map side metadata for the chunk starting at `start`
 pub(super) fn try_mmap_metadata_chunk(
     start: Address,
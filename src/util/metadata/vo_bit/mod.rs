@@ -160,3 +160,64 @@ pub fn bcopy_vo_bit_from_mark_bit<VM: VMBinding>(start: Address, size: usize) {
     let side_mark_bit_spec = mark_bit_spec.extract_side_spec();
     VO_BIT_SIDE_METADATA_SPEC.bcopy_metadata_contiguous(start, size, side_mark_bit_spec);
 }
+
+/// Find the object that `internal_ptr` points into by searching the VO bits backwards, for at most
+/// `search_limit_bytes`.  The object found is validated with [`ObjectModel::get_current_size`],
+/// and `None` is returned if `internal_ptr` is beyond the end of the nearest object.
+///
+/// The VO bit of an object is set at its `ref_to_address`, which may be after the start of the
+/// object.  To resolve pointers into the part of an object before that address, the search starts
+/// beyond `internal_ptr` by the smallest distance from the start of an object to its
+/// `ref_to_address`, which is derived from [`ObjectModel::OBJECT_REF_OFFSET_LOWER_BOUND`].  If the
+/// distance varies between objects, a pointer that is closer to the start of an object than that
+/// bound may not be resolved.
+///
+/// Note that the object found is not necessarily live if the VO bits of dead objects have not
+/// been cleared yet.  See the module-level documentation of `vo_bit::helper`.
+pub fn find_object_from_internal_pointer<VM: VMBinding>(
+    internal_ptr: Address,
+    search_limit_bytes: usize,
+) -> Option<ObjectReference> {
+    let header_bytes = min_bytes_before_address::<VM>(internal_ptr)?;
+    let mut search_start = internal_ptr + header_bytes;
+    let mut search_limit = search_limit_bytes + header_bytes;
+    loop {
+        let addr =
+            VO_BIT_SIDE_METADATA_SPEC.find_prev_non_zero_value::<u8>(search_start, search_limit)?;
+        let object = ObjectReference::from_address::<VM>(addr);
+        if is_internal_ptr::<VM>(object, internal_ptr) {
+            return Some(object);
+        }
+        // Objects do not overlap.  If an object whose VO bit is at or before `internal_ptr` does
+        // not contain it, no object before it does.
+        if addr <= internal_ptr {
+            return None;
+        }
+        // The object starts after `internal_ptr`.  Keep searching before its VO bit.
+        let next_start = addr - 1usize;
+        let skipped = search_start - next_start;
+        if skipped > search_limit {
+            return None;
+        }
+        search_start = next_start;
+        search_limit -= skipped;
+    }
+}
+
+/// The smallest distance from the start of an object to its `ref_to_address`, or 0 if the
+/// address may be before the start.  `ptr` is only used to forge an object reference, as
+/// `ref_to_address` is a constant offset from the object reference.  Return `None` if `ptr` is
+/// zero.
+fn min_bytes_before_address<VM: VMBinding>(ptr: Address) -> Option<usize> {
+    let forged = ObjectReference::from_raw_address(ptr)?;
+    let address_offset =
+        forged.to_address::<VM>().as_usize() as isize - forged.to_raw_address().as_usize() as isize;
+    Some((VM::VMObjectModel::OBJECT_REF_OFFSET_LOWER_BOUND + address_offset).max(0) as usize)
+}
+
+/// Return true if `internal_ptr` points into the storage of `object`.
+pub fn is_internal_ptr<VM: VMBinding>(object: ObjectReference, internal_ptr: Address) -> bool {
+    let object_start = object.to_object_start::<VM>();
+    let object_size = VM::VMObjectModel::get_current_size(object);
+    internal_ptr >= object_start && internal_ptr < object_start + object_size
+}
@@ -42,6 +42,8 @@ pub(crate) mod edge_logger;
 pub(crate) mod erase_vm;
 /// Finalization implementation.
 pub(crate) mod finalizable_processor;
+/// Per-space occupancy and fragmentation reports.
+pub mod fragmentation;
 /// Heap implementation, including page resource, mmapper, etc.
 pub mod heap;
 /// Checking if an address is an valid MMTk object.
@@ -53,10 +55,14 @@ pub(crate) mod logger;
 pub mod malloc;
 /// Metadata (OnSide or InHeader) implementation.
 pub mod metadata;
+/// NUMA topology, and NUMA-aware placement of memory and GC workers.
+pub mod numa;
 /// Forwarding word in object copying.
 pub(crate) mod object_forwarding;
 /// Reference processing implementation.
 pub(crate) mod reference_processor;
+/// Find the paths from roots that keep an object alive.
+pub mod retention_path;
 /// Utilities funcitons for Rust
 pub(crate) mod rust_util;
 /// Sanity checker for GC.
@@ -64,6 +70,8 @@ pub(crate) mod rust_util;
 pub(crate) mod sanity;
 /// Utils for collecting statistics.
 pub(crate) mod statistics;
+/// An in-process tracer that writes GC timelines in the Chrome Trace Event format.
+pub(crate) mod timeline;
 /// A treadmill implementation.
 pub(crate) mod treadmill;
 
@@ -0,0 +1,341 @@
+use crate::scheduler::affinity::{get_total_num_cpus, CoreId};
+use crate::util::conversions;
+use crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK;
+use crate::util::memory;
+use crate::util::options::{AffinityKind, NumaSelector};
+use crate::util::Address;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// A NUMA node that has CPUs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumaNode {
+    /// The node ID used by the OS, e.g. `1` for `node1`.
+    pub id: usize,
+    /// The CPUs on this node.
+    pub cpus: Vec<CoreId>,
+}
+
+/// The NUMA nodes of the machine. In MMTk, a node is identified by its index in [`NumaTopology::nodes`],
+/// which may differ from its OS node ID.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumaTopology {
+    nodes: Vec<NumaNode>,
+}
+
+impl NumaTopology {
+    /// Read the topology from a directory with the layout of `/sys/devices/system/node`, i.e. a
+    /// `node<N>/cpulist` file for each node. Nodes without CPUs are ignored. If the topology cannot
+    /// be read, we assume all the CPUs are on a single node.
+    pub fn read(path: &Path) -> Self {
+        match Self::read_nodes(path) {
+            Some(nodes) if !nodes.is_empty() => Self { nodes },
+            _ => {
+                warn!(
+                    "Failed to read the NUMA topology from {}. Assume a single node.",
+                    path.display()
+                );
+                Self::single_node()
+            }
+        }
+    }
+
+    /// A topology in which all the CPUs are on node 0.
+    pub fn single_node() -> Self {
+        Self {
+            nodes: vec![NumaNode {
+                id: 0,
+                cpus: (0..get_total_num_cpus()).collect(),
+            }],
+        }
+    }
+
+    fn read_nodes(path: &Path) -> Option<Vec<NumaNode>> {
+        let mut nodes = vec![];
+        for entry in std::fs::read_dir(path).ok()? {
+            let entry = entry.ok()?;
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("node"))
+                .and_then(|id| id.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let cpulist = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            // A CPU list has the same format as the `thread_affinity` option.
+            let cpus = match cpulist.trim().parse::<AffinityKind>() {
+                Ok(AffinityKind::RoundRobin(cpus)) => cpus,
+                Ok(AffinityKind::OsDefault) => vec![],
+                Err(e) => {
+                    warn!("Failed to parse the CPU list of node{}: {}", id, e);
+                    return None;
+                }
+            };
+            if !cpus.is_empty() {
+                nodes.push(NumaNode { id, cpus });
+            }
+        }
+        nodes.sort_unstable_by_key(|node| node.id);
+        Some(nodes)
+    }
+
+    /// All the nodes with CPUs, sorted by their OS node IDs.
+    pub fn nodes(&self) -> &[NumaNode] {
+        &self.nodes
+    }
+
+    /// The number of nodes with CPUs.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The node that the given CPU is on.
+    pub fn node_of_cpu(&self, cpu: CoreId) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.cpus.binary_search(&cpu).is_ok())
+    }
+}
+
+/// NUMA-aware placement of memory and GC workers. This exists if the `numa` option is enabled.
+pub struct Numa {
+    topology: NumaTopology,
+    /// The node of each GC worker.
+    worker_nodes: Vec<usize>,
+    /// For each GC worker, the order in which it steals work packets from other workers: workers
+    /// on the same node first, then workers on other nodes.
+    steal_orders: Vec<Vec<usize>>,
+    /// The node that each chunk is bound to.
+    chunk_nodes: RwLock<HashMap<Address, usize>>,
+}
+
+impl Numa {
+    /// Create the NUMA placement for `num_workers` GC workers. Return `None` if NUMA is disabled.
+    pub fn new(
+        selector: &NumaSelector,
+        num_workers: usize,
+        affinity: &AffinityKind,
+    ) -> Option<Self> {
+        match selector {
+            NumaSelector::Disabled => None,
+            NumaSelector::Enabled { path } => Some(Self::with_topology(
+                NumaTopology::read(Path::new(path)),
+                num_workers,
+                affinity,
+            )),
+        }
+    }
+
+    fn with_topology(topology: NumaTopology, num_workers: usize, affinity: &AffinityKind) -> Self {
+        let worker_nodes: Vec<usize> = (0..num_workers)
+            .map(|ordinal| match affinity {
+                // Workers are pinned to the given cores. Use the nodes of those cores.
+                AffinityKind::RoundRobin(cpuset) => topology
+                    .node_of_cpu(cpuset[ordinal % cpuset.len()])
+                    .unwrap_or(0),
+                // Spread the workers evenly over the nodes, with neighbouring ordinals on the same node.
+                AffinityKind::OsDefault => ordinal * topology.num_nodes() / num_workers,
+            })
+            .collect();
+        info!(
+            "NUMA nodes: {:?}. The nodes of GC workers: {:?}",
+            topology
+                .nodes()
+                .iter()
+                .map(|node| node.id)
+                .collect::<Vec<_>>(),
+            worker_nodes
+        );
+        Self {
+            steal_orders: compute_steal_orders(&worker_nodes, topology.num_nodes()),
+            worker_nodes,
+            topology,
+            chunk_nodes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The NUMA topology.
+    pub fn topology(&self) -> &NumaTopology {
+        &self.topology
+    }
+
+    /// The number of nodes.
+    pub fn num_nodes(&self) -> usize {
+        self.topology.num_nodes()
+    }
+
+    /// The node of a GC worker.
+    pub fn worker_node(&self, ordinal: usize) -> usize {
+        self.worker_nodes[ordinal]
+    }
+
+    /// The other GC workers, in the order in which the given worker should steal work packets from them.
+    pub fn steal_order(&self, ordinal: usize) -> &[usize] {
+        &self.steal_orders[ordinal]
+    }
+
+    /// The node of the CPU that the current thread is running on.
+    pub fn current_node(&self) -> usize {
+        current_cpu()
+            .and_then(|cpu| self.topology.node_of_cpu(cpu))
+            .unwrap_or(0)
+    }
+
+    /// Pin the current thread, which is the given GC worker, to the CPUs of its node.
+    pub fn bind_current_worker(&self, ordinal: usize) {
+        let node = &self.topology.nodes()[self.worker_node(ordinal)];
+        debug!("Set affinity for thread {} to node {}", ordinal, node.id);
+        crate::scheduler::affinity::bind_current_thread_to_cores(&node.cpus);
+    }
+
+    /// Bind the chunks in the given range to the node of the current thread. The range must be
+    /// mapped, and the memory should not be touched yet.
+    pub fn bind_chunks(&self, start: Address, bytes: usize) {
+        let end = conversions::chunk_align_up(start + bytes);
+        let start = conversions::chunk_align_down(start);
+        let node = self.current_node();
+        if let Err(e) = memory::mbind_preferred(start, end - start, self.topology.nodes()[node].id)
+        {
+            warn!(
+                "Failed to bind {} to NUMA node {}: {}",
+                start,
+                self.topology.nodes()[node].id,
+                e
+            );
+        }
+        self.set_chunk_node(start, end, node);
+    }
+
+    fn set_chunk_node(&self, start: Address, end: Address, node: usize) {
+        let mut chunk_nodes = self.chunk_nodes.write().unwrap();
+        let mut chunk = start;
+        while chunk < end {
+            chunk_nodes.insert(chunk, node);
+            chunk += BYTES_IN_CHUNK;
+        }
+    }
+
+    /// The node that the chunk of the given address is bound to, or `None` if it is not bound.
+    pub fn node_of_address(&self, addr: Address) -> Option<usize> {
+        self.chunk_nodes
+            .read()
+            .unwrap()
+            .get(&conversions::chunk_align_down(addr))
+            .copied()
+    }
+}
+
+/// For each worker, list the other workers on the same node first, and then the workers on the
+/// following nodes in a round-robin order.
+fn compute_steal_orders(worker_nodes: &[usize], num_nodes: usize) -> Vec<Vec<usize>> {
+    worker_nodes
+        .iter()
+        .enumerate()
+        .map(|(ordinal, &node)| {
+            let mut order: Vec<usize> = (0..worker_nodes.len())
+                .filter(|&other| other != ordinal)
+                .collect();
+            order.sort_by_key(|&other| (worker_nodes[other] + num_nodes - node) % num_nodes);
+            order
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn current_cpu() -> Option<CoreId> {
+    let cpu = unsafe { libc::sched_getcpu() };
+    (cpu >= 0).then_some(cpu as CoreId)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_cpu() -> Option<CoreId> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_util::with_cleanup;
+
+    /// Create a stand-in topology directory with the given CPU lists, and run `f` with its path.
+    fn with_stand_in_topology<F: FnOnce(&Path) + std::panic::UnwindSafe>(
+        name: &str,
+        cpulists: &[(usize, &str)],
+        f: F,
+    ) {
+        let root = std::env::temp_dir().join(format!("mmtk-numa-{}-{}", name, std::process::id()));
+        for (id, cpulist) in cpulists {
+            let node = root.join(format!("node{}", id));
+            std::fs::create_dir_all(&node).unwrap();
+            std::fs::write(node.join("cpulist"), cpulist).unwrap();
+        }
+        // Files other than node directories are ignored.
+        std::fs::write(root.join("possible"), "0-3\n").unwrap();
+        let path = root.clone();
+        with_cleanup(
+            move || f(&path),
+            move || {
+                let _ = std::fs::remove_dir_all(&root);
+            },
+        )
+    }
+
+    #[test]
+    fn test_read_topology() {
+        with_stand_in_topology("read", &[(1, "4-7\n"), (0, "0-3\n"), (2, "\n")], |path| {
+            let topology = NumaTopology::read(path);
+            // node2 has no CPUs.
+            assert_eq!(
+                topology.nodes(),
+                &[
+                    NumaNode {
+                        id: 0,
+                        cpus: vec![0, 1, 2, 3]
+                    },
+                    NumaNode {
+                        id: 1,
+                        cpus: vec![4, 5, 6, 7]
+                    }
+                ]
+            );
+            assert_eq!(topology.node_of_cpu(2), Some(0));
+            assert_eq!(topology.node_of_cpu(5), Some(1));
+            assert_eq!(topology.node_of_cpu(8), None);
+        })
+    }
+
+    #[test]
+    fn test_read_missing_topology() {
+        let topology = NumaTopology::read(Path::new("/mmtk-no-such-directory"));
+        assert_eq!(topology, NumaTopology::single_node());
+    }
+
+    #[test]
+    fn test_worker_nodes() {
+        with_stand_in_topology("workers", &[(0, "0-1"), (1, "2-3")], |path| {
+            let topology = NumaTopology::read(path);
+
+            let numa = Numa::with_topology(topology.clone(), 4, &AffinityKind::OsDefault);
+            assert_eq!(numa.worker_nodes, vec![0, 0, 1, 1]);
+            assert_eq!(numa.steal_order(0), &[1, 2, 3]);
+            assert_eq!(numa.steal_order(2), &[3, 0, 1]);
+
+            let numa = Numa::with_topology(topology, 3, &AffinityKind::RoundRobin(vec![2, 0]));
+            assert_eq!(numa.worker_nodes, vec![1, 0, 1]);
+            assert_eq!(numa.steal_order(0), &[2, 1]);
+            assert_eq!(numa.steal_order(1), &[0, 2]);
+        })
+    }
+
+    #[test]
+    fn test_chunk_nodes() {
+        let numa = Numa::with_topology(NumaTopology::single_node(), 1, &AffinityKind::OsDefault);
+        let start = unsafe { Address::from_usize(BYTES_IN_CHUNK * 16) };
+        numa.set_chunk_node(start, start + 2 * BYTES_IN_CHUNK, 0);
+        assert_eq!(numa.node_of_address(start + 8usize), Some(0));
+        assert_eq!(numa.node_of_address(start + BYTES_IN_CHUNK), Some(0));
+        assert_eq!(numa.node_of_address(start + 2 * BYTES_IN_CHUNK), None);
+    }
+}
@@ -241,6 +241,8 @@ macro_rules! options {
             }
 
             /// Set an option and run its validator for its value.
+            // Parsing some types, such as `String`, never fails.
+            #[allow(irrefutable_let_patterns)]
             fn set_inner(&mut self, s: &str, val: &str) -> bool {
                 match s {
                     // Parse the given value from str (by env vars or by calling process()) to the right type
@@ -602,11 +604,20 @@ impl GCTriggerSelector {
     }
 
     /// Parse a size representation, which could be a number to represents bytes,
-    /// or a number with the suffix K/k/M/m/G/g. Return the byte number if it can be
-    /// parsed properly, otherwise return an error string.
+    /// or a number with the suffix K/k/M/m/G/g, or a percentage of the available memory
+    /// (the physical memory, or the cgroup memory limit if it is smaller) with the suffix %.
+    /// Return the byte number if it can be parsed properly, otherwise return an error string.
     fn parse_size(s: &str) -> Result<usize, String> {
         let s = s.to_lowercase();
-        if s.ends_with(char::is_alphabetic) {
+        if let Some(percentage) = s.strip_suffix('%') {
+            let percentage = percentage.parse::<u64>().map_err(|e| e.to_string())?;
+            if percentage > 100 {
+                return Err(format!("percentage larger than 100%: {}", s));
+            }
+            let size = crate::util::memory::get_available_memory() / 100 * percentage;
+            size.try_into()
+                .map_err(|_| format!("size overflow: {}", size))
+        } else if s.ends_with(char::is_alphabetic) {
             let num = s[0..s.len() - 1]
                 .parse::<u64>()
                 .map_err(|e| e.to_string())?;
@@ -653,9 +664,9 @@ impl FromStr for GCTriggerSelector {
         use regex::Regex;
         lazy_static! {
             static ref FIXED_HEAP_REGEX: Regex =
-                Regex::new(r"^FixedHeapSize:(?P<size>\d+[kKmMgGtT]?)$").unwrap();
+                Regex::new(r"^FixedHeapSize:(?P<size>\d+[kKmMgGtT%]?)$").unwrap();
             static ref DYNAMIC_HEAP_REGEX: Regex =
-                Regex::new(r"^DynamicHeapSize:(?P<min>\d+[kKmMgGtT]?),(?P<max>\d+[kKmMgGtT]?)$")
+                Regex::new(r"^DynamicHeapSize:(?P<min>\d+[kKmMgGtT%]?),(?P<max>\d+[kKmMgGtT%]?)$")
                     .unwrap();
         }
 
@@ -772,6 +783,29 @@ mod gc_trigger_tests {
         assert!(GCTriggerSelector::from_str("DynamicHeapSize:1024,1024,").is_err());
     }
 
+    #[test]
+    fn test_parse_percentage_heap() {
+        // Do not run in parallel with tests that set the cgroup root.
+        crate::util::test_util::serial_test(|| {
+            let available = crate::util::memory::get_available_memory() as usize;
+            assert_eq!(
+                GCTriggerSelector::from_str("DynamicHeapSize:25%,75%"),
+                Ok(GCTriggerSelector::DynamicHeapSize(
+                    available / 100 * 25,
+                    available / 100 * 75
+                ))
+            );
+            assert_eq!(
+                GCTriggerSelector::from_str("FixedHeapSize:100%"),
+                Ok(GCTriggerSelector::FixedHeapSize(available / 100 * 100))
+            );
+
+            // incorrect
+            assert!(GCTriggerSelector::from_str("FixedHeapSize:101%").is_err());
+            assert!(GCTriggerSelector::from_str("FixedHeapSize:1.5%").is_err());
+        })
+    }
+
     #[test]
     fn test_validate() {
         assert!(GCTriggerSelector::FixedHeapSize(1024).validate());
@@ -783,6 +817,366 @@ mod gc_trigger_tests {
     }
 }
 
+/// The default directory of the NUMA node topology on Linux.
+pub const DEFAULT_NUMA_NODE_PATH: &str = "/sys/devices/system/node";
+
+/// Select whether MMTk places memory and GC work according to the NUMA topology.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NumaSelector {
+    /// Ignore NUMA.
+    Disabled,
+    /// Bind new chunks to the NUMA node of the thread that acquires them, keep reusable blocks in
+    /// per-node pools, pin GC workers to NUMA nodes (unless `thread_affinity` is set), and let GC
+    /// workers steal work from workers on the same node first.
+    Enabled {
+        /// The directory of the NUMA node topology, which contains a `node<N>/cpulist` file for each node.
+        /// It can be set to a stand-in directory to test NUMA on single-node machines.
+        path: String,
+    },
+}
+
+impl FromStr for NumaSelector {
+    type Err = String;
+
+    /// The format is one of `Disabled`, `Enabled`, or `Enabled:<path>`. `Enabled` reads the
+    /// topology from `/sys/devices/system/node` if the path is omitted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "Disabled" => Ok(Self::Disabled),
+            None if s == "Enabled" => Ok(Self::Enabled {
+                path: DEFAULT_NUMA_NODE_PATH.to_string(),
+            }),
+            Some(("Enabled", path)) if !path.is_empty() => Ok(Self::Enabled {
+                path: path.to_string(),
+            }),
+            _ => Err(format!("Failed to parse the NUMA option: {:?}", s)),
+        }
+    }
+}
+
+impl NumaSelector {
+    /// Return true if NUMA is disabled, or if we are on Linux. MMTk only reads the NUMA topology
+    /// and binds threads and memory to NUMA nodes on Linux.
+    pub fn validate(&self) -> bool {
+        *self == Self::Disabled || cfg!(target_os = "linux")
+    }
+}
+
+#[cfg(test)]
+mod numa_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numa() {
+        assert_eq!(
+            NumaSelector::from_str("Disabled"),
+            Ok(NumaSelector::Disabled)
+        );
+        assert_eq!(
+            NumaSelector::from_str("Enabled"),
+            Ok(NumaSelector::Enabled {
+                path: DEFAULT_NUMA_NODE_PATH.to_string()
+            })
+        );
+        assert_eq!(
+            NumaSelector::from_str("Enabled:/tmp/node"),
+            Ok(NumaSelector::Enabled {
+                path: "/tmp/node".to_string()
+            })
+        );
+
+        // incorrect
+        assert!(NumaSelector::from_str("").is_err());
+        assert!(NumaSelector::from_str("Enabled:").is_err());
+        assert!(NumaSelector::from_str("Disabled:/tmp/node").is_err());
+    }
+
+    #[test]
+    fn test_validate_numa() {
+        assert!(NumaSelector::Disabled.validate());
+        assert_eq!(
+            NumaSelector::from_str("Enabled").unwrap().validate(),
+            cfg!(target_os = "linux")
+        );
+    }
+}
+
+/// Select how many GC workers work for each GC.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ActiveGCWorkers {
+    /// All the GC workers work for every GC.
+    All,
+    /// The number of active GC workers is proportional to the estimated work of the GC, with one
+    /// worker for every `bytes_per_worker` bytes, but at least one and at most `threads`. Other
+    /// GC workers stay parked for the GC. The work is estimated by the bytes allocated since the
+    /// last GC for generational plans, or the reserved bytes of the heap for other plans.
+    Adaptive {
+        /// The bytes of estimated work for each active GC worker.
+        bytes_per_worker: usize,
+    },
+}
+
+impl ActiveGCWorkers {
+    /// Return true if the active GC workers option is valid
+    fn validate(&self) -> bool {
+        match self {
+            Self::All => true,
+            Self::Adaptive { bytes_per_worker } => *bytes_per_worker > 0,
+        }
+    }
+}
+
+impl FromStr for ActiveGCWorkers {
+    type Err = String;
+
+    /// The format is `All` or `Adaptive:<size>`, where the size can use the suffixes of sizes in `gc_trigger`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "All" => Ok(Self::All),
+            Some(("Adaptive", size)) => Ok(Self::Adaptive {
+                bytes_per_worker: GCTriggerSelector::parse_size(size)?,
+            }),
+            _ => Err(format!(
+                "Failed to parse the active GC workers option: {:?}",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod active_gc_workers_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_active_gc_workers() {
+        assert_eq!(ActiveGCWorkers::from_str("All"), Ok(ActiveGCWorkers::All));
+        assert_eq!(
+            ActiveGCWorkers::from_str("Adaptive:4M"),
+            Ok(ActiveGCWorkers::Adaptive {
+                bytes_per_worker: 4 * 1024 * 1024
+            })
+        );
+        assert_eq!(
+            ActiveGCWorkers::from_str("Adaptive:65536"),
+            Ok(ActiveGCWorkers::Adaptive {
+                bytes_per_worker: 65536
+            })
+        );
+
+        // incorrect
+        assert!(ActiveGCWorkers::from_str("").is_err());
+        assert!(ActiveGCWorkers::from_str("Adaptive").is_err());
+        assert!(ActiveGCWorkers::from_str("Adaptive:").is_err());
+        assert!(ActiveGCWorkers::from_str("All:1").is_err());
+        assert!(!ActiveGCWorkers::from_str("Adaptive:0").unwrap().validate());
+    }
+}
+
+/// The default path of the system-wide memory pressure stall information (PSI) on Linux.
+pub const DEFAULT_PSI_MEMORY_PATH: &str = "/proc/pressure/memory";
+/// The default path of the cgroup v2 memory events of the root cgroup on Linux.
+pub const DEFAULT_CGROUP_MEMORY_EVENTS_PATH: &str = "/sys/fs/cgroup/memory.events";
+
+/// Select the source of memory pressure of the host. When memory pressure is high, MMTk triggers a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemoryPressureSelector {
+    /// Do not monitor memory pressure.
+    Disabled,
+    /// Read a pressure stall information (PSI) file, such as `/proc/pressure/memory` or `memory.pressure` of a cgroup v2.
+    /// The pressure is high if the `some avg10` value (the percentage of time in the last 10 seconds in which some tasks
+    /// were stalled on memory) is above the threshold.
+    Psi {
+        /// The path of the PSI file.
+        path: String,
+        /// The threshold of `some avg10`, in percentage.
+        threshold: f64,
+    },
+    /// Read the `memory.events` file of a cgroup v2. The pressure is high if the `high`, `max` or `oom` counters
+    /// increased since the last check.
+    CgroupEvents {
+        /// The path of the `memory.events` file.
+        path: String,
+    },
+}
+
+impl MemoryPressureSelector {
+    /// Return true if the memory pressure selector is valid
+    fn validate(&self) -> bool {
+        match self {
+            Self::Disabled => true,
+            Self::Psi { path, threshold } => {
+                !path.is_empty() && *threshold >= 0f64 && *threshold <= 100f64
+            }
+            Self::CgroupEvents { path } => !path.is_empty(),
+        }
+    }
+}
+
+impl FromStr for MemoryPressureSelector {
+    type Err = String;
+
+    /// The format is one of `Disabled`, `Psi:<threshold>`, `Psi:<threshold>:<path>`, `CgroupEvents`, or `CgroupEvents:<path>`.
+    /// `Psi` uses `/proc/pressure/memory` and `CgroupEvents` uses `/sys/fs/cgroup/memory.events` if the path is omitted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, rest) = match s.split_once(':') {
+            Some((kind, rest)) => (kind, Some(rest)),
+            None => (s, None),
+        };
+        match (kind, rest) {
+            ("Disabled", None) => Ok(Self::Disabled),
+            ("Psi", Some(rest)) => {
+                let (threshold, path) = match rest.split_once(':') {
+                    Some((threshold, path)) => (threshold, path),
+                    None => (rest, DEFAULT_PSI_MEMORY_PATH),
+                };
+                let threshold = threshold.parse::<f64>().map_err(|e| e.to_string())?;
+                Ok(Self::Psi {
+                    path: path.to_string(),
+                    threshold,
+                })
+            }
+            ("CgroupEvents", rest) => Ok(Self::CgroupEvents {
+                path: rest
+                    .unwrap_or(DEFAULT_CGROUP_MEMORY_EVENTS_PATH)
+                    .to_string(),
+            }),
+            _ => Err(format!(
+                "Failed to parse the memory pressure option: {:?}",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod memory_pressure_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory_pressure() {
+        assert_eq!(
+            MemoryPressureSelector::from_str("Disabled"),
+            Ok(MemoryPressureSelector::Disabled)
+        );
+        assert_eq!(
+            MemoryPressureSelector::from_str("Psi:10"),
+            Ok(MemoryPressureSelector::Psi {
+                path: DEFAULT_PSI_MEMORY_PATH.to_string(),
+                threshold: 10f64
+            })
+        );
+        assert_eq!(
+            MemoryPressureSelector::from_str("Psi:2.5:/sys/fs/cgroup/app/memory.pressure"),
+            Ok(MemoryPressureSelector::Psi {
+                path: "/sys/fs/cgroup/app/memory.pressure".to_string(),
+                threshold: 2.5f64
+            })
+        );
+        assert_eq!(
+            MemoryPressureSelector::from_str("CgroupEvents"),
+            Ok(MemoryPressureSelector::CgroupEvents {
+                path: DEFAULT_CGROUP_MEMORY_EVENTS_PATH.to_string()
+            })
+        );
+        assert_eq!(
+            MemoryPressureSelector::from_str("CgroupEvents:/tmp/memory.events"),
+            Ok(MemoryPressureSelector::CgroupEvents {
+                path: "/tmp/memory.events".to_string()
+            })
+        );
+
+        // incorrect
+        assert!(MemoryPressureSelector::from_str("").is_err());
+        assert!(MemoryPressureSelector::from_str("Psi").is_err());
+        assert!(MemoryPressureSelector::from_str("Psi:abc").is_err());
+        assert!(MemoryPressureSelector::from_str("Disabled:1").is_err());
+    }
+
+    #[test]
+    fn test_validate_memory_pressure() {
+        assert!(MemoryPressureSelector::Disabled.validate());
+        assert!(MemoryPressureSelector::from_str("Psi:10")
+            .unwrap()
+            .validate());
+        assert!(!MemoryPressureSelector::from_str("Psi:101")
+            .unwrap()
+            .validate());
+        assert!(!MemoryPressureSelector::from_str("CgroupEvents:")
+            .unwrap()
+            .validate());
+    }
+}
+
+/// Select which soft references a GC clears.  Whatever the policy is, emergency GCs and GCs requested
+/// with [`crate::plan::UserCollectionKind::FullClearSoftRefs`] clear all the soft references whose
+/// referents are not strongly reachable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SoftReferencePolicy {
+    /// Retain the referents of all the soft references.
+    RetainAll,
+    /// Clear the soft references that have not been accessed recently, like the
+    /// `SoftRefLRUPolicyMSPerMB` policy of HotSpot.  A soft reference is retained if it was
+    /// accessed within `ms_per_mb` milliseconds for each MB of free heap at the end of the last GC.
+    /// The binding tells the last access time with
+    /// [`crate::vm::ReferenceGlue::get_soft_reference_timestamp`].
+    LRU {
+        /// The milliseconds a soft reference is retained for each MB of free heap.
+        ms_per_mb: usize,
+    },
+}
+
+impl FromStr for SoftReferencePolicy {
+    type Err = String;
+
+    /// The format is `RetainAll` or `LRU:<ms_per_mb>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "RetainAll" => Ok(Self::RetainAll),
+            Some(("LRU", ms_per_mb)) => Ok(Self::LRU {
+                ms_per_mb: ms_per_mb.parse().map_err(|e| {
+                    format!(
+                        "Failed to parse the milliseconds per MB {:?}: {}",
+                        ms_per_mb, e
+                    )
+                })?,
+            }),
+            _ => Err(format!(
+                "Failed to parse the soft reference policy option: {:?}",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod soft_reference_policy_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_soft_reference_policy() {
+        assert_eq!(
+            SoftReferencePolicy::from_str("RetainAll"),
+            Ok(SoftReferencePolicy::RetainAll)
+        );
+        assert_eq!(
+            SoftReferencePolicy::from_str("LRU:1000"),
+            Ok(SoftReferencePolicy::LRU { ms_per_mb: 1000 })
+        );
+        assert_eq!(
+            SoftReferencePolicy::from_str("LRU:0"),
+            Ok(SoftReferencePolicy::LRU { ms_per_mb: 0 })
+        );
+
+        // incorrect
+        assert!(SoftReferencePolicy::from_str("").is_err());
+        assert!(SoftReferencePolicy::from_str("LRU").is_err());
+        assert!(SoftReferencePolicy::from_str("LRU:").is_err());
+        assert!(SoftReferencePolicy::from_str("LRU:-1").is_err());
+        assert!(SoftReferencePolicy::from_str("RetainAll:1").is_err());
+    }
+}
+
 // Currently we allow all the options to be set by env var for the sake of convenience.
 // At some point, we may disallow this and all the options can only be set by command line.
 options! {
@@ -794,8 +1188,16 @@ options! {
     use_short_stack_scans: bool                 [env_var: true, command_line: true]  [always_valid] = false,
     /// Enable a return barrier (not supported)
     use_return_barrier:    bool                 [env_var: true, command_line: true]  [always_valid] = false,
-    /// Should we eagerly finish sweeping at the start of a collection? (not supported)
-    eager_complete_sweep:  bool                 [env_var: true, command_line: true]  [always_valid] = false,
+    /// Sweep Immix blocks, native mark-sweep blocks and `MallocSpace` chunks with GC workers after
+    /// mutators are resumed, instead of in the pause.  Allocators sweep a block or a chunk on demand
+    /// if they need it before it is swept.  All the sweeping is finished before the next GC starts.
+    /// This cannot be used with the `vo_bit` feature, which requires the VO bits of dead objects to be
+    /// cleared by the end of each GC.
+    background_sweeping:   bool                 [env_var: true, command_line: true]  [|v: &bool| !v || !cfg!(feature = "vo_bit")] = false,
+    /// Relocate large objects to free pages at lower addresses in defragmenting GCs of the Immix plans,
+    /// by remapping their pages (`mremap`) instead of copying their bytes.  This compacts the large object
+    /// space so that its free chunks can be returned.  Only Linux is supported.
+    los_page_remapping:    bool                 [env_var: true, command_line: true]  [|v: &bool| !v || cfg!(target_os = "linux")] = false,
     /// Should we ignore GCs requested by the user (e.g. java.lang.System.gc)?
     ignore_system_gc:      bool                 [env_var: true, command_line: true]  [always_valid] = false,
     /// The nursery size for generational plans. It can be one of Bounded, ProportionalBounded or Fixed.
@@ -860,10 +1262,46 @@ options! {
     // XXX: This option is currently only supported on Linux.
     thread_affinity:        AffinityKind         [env_var: true, command_line: true] [|v: &AffinityKind| v.validate()] = AffinityKind::OsDefault,
     /// Set the GC trigger. This defines the heap size and how MMTk triggers a GC.
-    /// Default to a fixed heap size of 0.5x physical memory.
-    gc_trigger:             GCTriggerSelector    [env_var: true, command_line: true] [|v: &GCTriggerSelector| v.validate()] = GCTriggerSelector::FixedHeapSize((crate::util::memory::get_system_total_memory() as f64 * 0.5f64) as usize),
+    /// Default to a fixed heap size of 0.5x the available memory, which is the physical memory, or the cgroup memory limit
+    /// if it is smaller. Sizes can be given as a percentage of the available memory, e.g. `DynamicHeapSize:25%,75%`.
+    /// The cgroup file system is read from `/sys/fs/cgroup` unless it is overridden by the environment variable `MMTK_CGROUP_ROOT`.
+    gc_trigger:             GCTriggerSelector    [env_var: true, command_line: true] [|v: &GCTriggerSelector| v.validate()] = GCTriggerSelector::FixedHeapSize((crate::util::memory::get_available_memory() as f64 * 0.5f64) as usize),
     /// Enable transparent hugepage support via madvise (only Linux is supported)
-    transparent_hugepages: bool                  [env_var: true, command_line: true]  [|v: &bool| !v || cfg!(target_os = "linux")] = false
+    transparent_hugepages: bool                  [env_var: true, command_line: true]  [|v: &bool| !v || cfg!(target_os = "linux")] = false,
+    /// Map the heap with explicit huge pages (`MAP_HUGETLB`) from the system huge page pool, which must be
+    /// large enough for the heap (only Linux is supported). This takes precedence over `transparent_hugepages`.
+    /// With either option, Immix spaces allocate free blocks from partially used huge pages first, and
+    /// the statistics report how much of the heap is backed by huge pages.
+    explicit_hugepages:    bool                  [env_var: true, command_line: true]  [|v: &bool| !v || cfg!(target_os = "linux")] = false,
+    /// Monitor the memory pressure of the host, and trigger a collection when the pressure is high. See [`MemoryPressureSelector`]
+    /// for the format. The paths can be changed to monitor a specific cgroup, or to use stand-in files for testing.
+    memory_pressure:        MemoryPressureSelector [env_var: true, command_line: true] [|v: &MemoryPressureSelector| v.validate()] = MemoryPressureSelector::Disabled,
+    /// The minimal interval (in milliseconds) between two checks of the memory pressure. The memory pressure is checked when
+    /// MMTk polls for GC in allocation, so at most one collection is triggered by memory pressure in each interval.
+    memory_pressure_interval: usize             [env_var: true, command_line: true] [|v: &usize| *v > 0] = 1000,
+    /// Should MMTk also shrink the heap when the memory pressure is high? If this is set, the dynamic heap size
+    /// (`DynamicHeapSize`) computed after a collection triggered by memory pressure uses less headroom above the live memory.
+    memory_pressure_shrink_heap: bool           [env_var: true, command_line: true] [always_valid] = false,
+    /// Trigger a full heap GC if there has been no GC for this many milliseconds, and the heap may have garbage to reclaim.
+    /// The GC is triggered by a GC worker even if no mutator allocates. 0 disables periodic GC.
+    periodic_gc_interval:   usize               [env_var: true, command_line: true] [always_valid] = 0,
+    /// How many GC workers work for each GC. See [`ActiveGCWorkers`] for the format. With `Adaptive`, small GCs (e.g. nursery GCs)
+    /// only wake up a few GC workers, and the other workers stay parked for the GC.
+    active_gc_workers:      ActiveGCWorkers     [env_var: true, command_line: true] [|v: &ActiveGCWorkers| v.validate()] = ActiveGCWorkers::All,
+    /// Place memory and GC work according to the NUMA topology (only Linux is supported). See [`NumaSelector`] for the format.
+    numa:                   NumaSelector        [env_var: true, command_line: true] [|v: &NumaSelector| v.validate()] = NumaSelector::Disabled,
+    /// If set, MMTk records GC timeline events (GCs, work packets, bucket openings and goal changes), and writes them
+    /// to this path in the Chrome Trace Event format at `harness_end` or when the binding calls
+    /// [`crate::memory_manager::write_timeline`]. The output can be loaded in Perfetto UI.
+    timeline_output:        String              [env_var: true, command_line: true] [always_valid] = String::new(),
+    /// The maximum number of timeline events kept for the GC and for each GC worker. Older events are dropped.
+    timeline_buffer_size:   usize               [env_var: true, command_line: true] [|v: &usize| *v > 0] = 65536,
+    /// Compute the occupancy and fragmentation of each space at the end of each GC, and log it at the info level.
+    /// The binding can get the report of the last GC with [`crate::memory_manager::last_fragmentation_report`].
+    fragmentation_report:   bool                [env_var: true, command_line: true] [always_valid] = false,
+    /// Select which soft references are cleared in GCs that are not emergency GCs. See [`SoftReferencePolicy`] for the format.
+    /// The default retains all the soft references.  `LRU:1000` is the default policy of HotSpot.
+    soft_reference_policy:  SoftReferencePolicy [env_var: true, command_line: true] [always_valid] = SoftReferencePolicy::RetainAll
 }
 
 #[cfg(test)]
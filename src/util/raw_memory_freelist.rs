@@ -207,9 +207,9 @@ impl RawMemoryFreeList {
 }
 
 /**
- * See documentation of `mod tests` below for the necessity of `impl Drop`.
+ * Unmap the memory of the free list.  A free list is dropped when its MMTk instance is destroyed, so
+ * that the address range can be used again.  See also the documentation of `mod tests` below.
  */
-#[cfg(test)]
 impl Drop for RawMemoryFreeList {
     fn drop(&mut self) {
         let len = self.high_water - self.base;
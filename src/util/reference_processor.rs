@@ -2,11 +2,14 @@ use std::collections::HashSet;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Mutex;
+use std::time::Instant;
 use std::vec::Vec;
 
 use crate::plan::is_nursery_gc;
 use crate::scheduler::ProcessEdgesWork;
 use crate::scheduler::WorkBucketStage;
+use crate::util::constants::{BYTES_IN_MBYTE, BYTES_IN_PAGE};
+use crate::util::options::SoftReferencePolicy;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
 use crate::vm::ReferenceGlue;
@@ -19,17 +22,28 @@ pub struct ReferenceProcessors {
     soft: ReferenceProcessor,
     weak: ReferenceProcessor,
     phantom: ReferenceProcessor,
+    /// The origin of the soft reference clock.
+    clock_origin: Instant,
 }
 
 impl ReferenceProcessors {
-    pub fn new() -> Self {
+    /// Create the reference processors.  The reference tables are divided into `num_shards`
+    /// shards, which is usually the number of GC workers.
+    pub fn new(num_shards: usize) -> Self {
         ReferenceProcessors {
-            soft: ReferenceProcessor::new(Semantics::SOFT),
-            weak: ReferenceProcessor::new(Semantics::WEAK),
-            phantom: ReferenceProcessor::new(Semantics::PHANTOM),
+            soft: ReferenceProcessor::new(Semantics::SOFT, num_shards),
+            weak: ReferenceProcessor::new(Semantics::WEAK, num_shards),
+            phantom: ReferenceProcessor::new(Semantics::PHANTOM, num_shards),
+            clock_origin: Instant::now(),
         }
     }
 
+    /// The soft reference clock, in milliseconds since the reference processors were created.
+    /// See [`crate::memory_manager::soft_reference_clock`].
+    pub fn soft_reference_clock(&self) -> u64 {
+        self.clock_origin.elapsed().as_millis() as u64
+    }
+
     pub fn get(&self, semantics: Semantics) -> &ReferenceProcessor {
         match semantics {
             Semantics::SOFT => &self.soft,
@@ -66,45 +80,65 @@ impl ReferenceProcessors {
     /// However, for some plans like mark compact, at the point we do ref scanning, we do not know
     /// the forwarding addresses yet, thus we cannot do forwarding during scan refs. And for those
     /// plans, this separate step is required.
-    pub fn forward_refs<E: ProcessEdgesWork>(&self, trace: &mut E, mmtk: &'static MMTK<E::VM>) {
+    ///
+    /// This adds work packets to the `RefForwarding` bucket to forward the references in parallel.
+    pub fn forward_refs<E: ProcessEdgesWork>(&self, mmtk: &'static MMTK<E::VM>) {
         debug_assert!(
             mmtk.get_plan().constraints().needs_forward_after_liveness,
             "A plan with needs_forward_after_liveness=false does not need a separate forward step"
         );
-        self.soft
-            .forward::<E>(trace, is_nursery_gc(mmtk.get_plan()));
-        self.weak
-            .forward::<E>(trace, is_nursery_gc(mmtk.get_plan()));
-        self.phantom
-            .forward::<E>(trace, is_nursery_gc(mmtk.get_plan()));
+        let nursery = is_nursery_gc(mmtk.get_plan());
+        let mut packets = vec![];
+        for processor in [&self.soft, &self.weak, &self.phantom] {
+            processor.forward::<E>(nursery, &mut packets);
+        }
+        mmtk.scheduler.work_buckets[WorkBucketStage::RefForwarding].bulk_add(packets);
     }
 
     // Methods for scanning weak references. It needs to be called in a decreasing order of reference strengths, i.e. soft > weak > phantom
 
-    pub fn retain_soft_refs<E: ProcessEdgesWork>(&self, trace: &mut E, mmtk: &'static MMTK<E::VM>) {
-        self.soft.retain::<E>(trace, is_nursery_gc(mmtk.get_plan()));
+    /// Retain the referents of soft references, according to `retention`.  This adds work packets
+    /// to the `SoftRefClosure` bucket to retain the referents in parallel.
+    pub fn retain_soft_refs<E: ProcessEdgesWork>(
+        &self,
+        mmtk: &'static MMTK<E::VM>,
+        retention: SoftReferenceRetention,
+    ) {
+        let mut packets = vec![];
+        self.soft
+            .retain::<E>(is_nursery_gc(mmtk.get_plan()), retention, &mut packets);
+        mmtk.scheduler.work_buckets[WorkBucketStage::SoftRefClosure].bulk_add(packets);
     }
 
-    /// Scan soft references.
-    pub fn scan_soft_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
+    /// Scan soft references.  This adds work packets to the bucket of `stage`.
+    pub fn scan_soft_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>, stage: WorkBucketStage) {
         // This will update the references (and the referents).
-        self.soft.scan::<VM>(is_nursery_gc(mmtk.get_plan()));
+        self.scan(&self.soft, mmtk, stage);
     }
 
-    /// Scan weak references.
-    pub fn scan_weak_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        self.weak.scan::<VM>(is_nursery_gc(mmtk.get_plan()));
+    /// Scan weak references.  This adds work packets to the bucket of `stage`.
+    pub fn scan_weak_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>, stage: WorkBucketStage) {
+        self.scan(&self.weak, mmtk, stage);
     }
 
-    /// Scan phantom references.
-    pub fn scan_phantom_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        self.phantom.scan::<VM>(is_nursery_gc(mmtk.get_plan()));
+    /// Scan phantom references.  This adds work packets to the bucket of `stage`.
+    pub fn scan_phantom_refs<VM: VMBinding>(
+        &self,
+        mmtk: &'static MMTK<VM>,
+        stage: WorkBucketStage,
+    ) {
+        self.scan(&self.phantom, mmtk, stage);
     }
-}
 
-impl Default for ReferenceProcessors {
-    fn default() -> Self {
-        Self::new()
+    fn scan<VM: VMBinding>(
+        &self,
+        processor: &ReferenceProcessor,
+        mmtk: &'static MMTK<VM>,
+        stage: WorkBucketStage,
+    ) {
+        let mut packets = vec![];
+        processor.scan::<VM>(is_nursery_gc(mmtk.get_plan()), &mut packets);
+        mmtk.scheduler.work_buckets[stage].bulk_add(packets);
     }
 }
 
@@ -117,6 +151,59 @@ impl Default for ReferenceProcessors {
 //      luckily this is also the value used by Java MMTk.)
 const INITIAL_SIZE: usize = 256;
 
+/// The maximum number of references processed by each work packet.
+const REFERENCES_PER_PACKET: usize = 4096;
+
+/// Which soft references to retain in a GC that does not clear all the soft references.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoftReferenceRetention {
+    /// Retain all the soft references.
+    All,
+    /// Retain the soft references accessed recently.
+    RecentlyAccessed {
+        /// The soft reference clock at the start of the GC.
+        clock: u64,
+        /// The maximum milliseconds since the last access of a retained soft reference.
+        max_interval: u64,
+    },
+}
+
+impl SoftReferenceRetention {
+    /// Decide the retention for the current GC according to the policy and the free heap at the
+    /// end of the last GC.
+    fn new<VM: VMBinding>(mmtk: &MMTK<VM>) -> Self {
+        match *mmtk.options.soft_reference_policy {
+            SoftReferencePolicy::RetainAll => Self::All,
+            SoftReferencePolicy::LRU { ms_per_mb } => {
+                let free_pages = mmtk
+                    .get_plan()
+                    .get_total_pages()
+                    .saturating_sub(mmtk.state.reserved_pages_at_last_gc.load(Ordering::SeqCst));
+                let free_mb = (free_pages * BYTES_IN_PAGE / BYTES_IN_MBYTE) as u64;
+                Self::RecentlyAccessed {
+                    clock: mmtk.reference_processors.soft_reference_clock(),
+                    max_interval: free_mb * ms_per_mb as u64,
+                }
+            }
+        }
+    }
+
+    /// Should we retain a soft reference last accessed at `timestamp`?  References with unknown
+    /// access time are always retained.
+    fn should_retain(&self, timestamp: Option<u64>) -> bool {
+        match (self, timestamp) {
+            (Self::All, _) | (_, None) => true,
+            (
+                Self::RecentlyAccessed {
+                    clock,
+                    max_interval,
+                },
+                Some(timestamp),
+            ) => clock.saturating_sub(timestamp) <= *max_interval,
+        }
+    }
+}
+
 /// We create a reference processor for each semantics. Generally we expect these
 /// to happen for each processor:
 /// 1. The VM adds reference candidates. They could either do it when a weak reference
@@ -124,9 +211,19 @@ const INITIAL_SIZE: usize = 256;
 /// 2. We scan references after the GC determins liveness.
 /// 3. We forward references if the GC needs forwarding after liveness.
 /// 4. We inform the binding of references whose referents are cleared during this GC by enqueue'ing.
+///
+/// The reference table is divided into shards by the addresses of the references, so that GC
+/// workers can add candidates without contending for one lock.  Scanning, retaining and
+/// forwarding are done by work packets in parallel.  In nursery GCs, only the references added
+/// since the last GC are processed.  Other references are mature, and so are their referents.
 pub struct ReferenceProcessor {
-    /// Most of the reference processor is protected by a mutex.
-    sync: Mutex<ReferenceProcessorSync>,
+    /// The shards of the reference table.  A reference is always in the shard for its current
+    /// address, so that adding the same reference twice is detected.
+    shards: Vec<Mutex<ReferenceShard>>,
+
+    /// References whose referents are cleared during this GC. We add references to this table during
+    /// scanning, and we pop from this table during the enqueue work at the end of GC.
+    enqueued_references: Mutex<Vec<ObjectReference>>,
 
     /// The semantics for the reference processor
     semantics: Semantics,
@@ -142,54 +239,129 @@ pub struct ReferenceProcessor {
     // 4. Weak reference forward: call trace_object for WR, which pushes WR to the node buffer and update WR -> WR' in our reference table.
     // 5. When we trace objects in the node buffer, we will attempt to add WR as a candidate. As we have updated WR to WR' in our reference
     //    table, we would accept WR as a candidate. But we will not trace WR again, and WR will be invalid after this GC.
-    // This flag is set to false after Step 4, so in Step 5, we will ignore adding WR.
+    // This flag is set to false before Step 4, so in Step 5, we will ignore adding WR.
     allow_new_candidate: AtomicBool,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Semantics {
     SOFT,
     WEAK,
     PHANTOM,
 }
 
-struct ReferenceProcessorSync {
-    /// The table of reference objects for the current semantics. We add references to this table by
-    /// add_candidate(). After scanning this table, a reference in the table should either
-    /// stay in the table (if the referent is alive) or go to enqueued_reference (if the referent is dead and cleared).
-    /// Note that this table should not have duplicate entries, otherwise we will scan the duplicates multiple times, and
-    /// that may lead to incorrect results.
-    references: HashSet<ObjectReference>,
+/// A shard of the reference table. After scanning the table, a reference in the table should
+/// either stay in the table (if the referent is alive) or go to `enqueued_references` (if the
+/// referent is dead and cleared).  The two sets are disjoint.  The table should not have
+/// duplicate entries, otherwise we will scan the duplicates multiple times, and that may lead to
+/// incorrect results.
+#[derive(Default)]
+struct ReferenceShard {
+    /// References that were in the table when the last GC finished scanning it.
+    mature: HashSet<ObjectReference>,
+    /// References added since the last GC finished scanning the table.
+    nursery: HashSet<ObjectReference>,
+}
 
-    /// References whose referents are cleared during this GC. We add references to this table during
-    /// scanning, and we pop from this table during the enqueue work at the end of GC.
-    enqueued_references: Vec<ObjectReference>,
+impl ReferenceShard {
+    /// Take the references to process in this GC out of the shard.
+    fn take(&mut self, nursery: bool) -> HashSet<ObjectReference> {
+        let mut references = std::mem::take(&mut self.nursery);
+        if !nursery {
+            if references.is_empty() {
+                references = std::mem::take(&mut self.mature);
+            } else {
+                references.extend(self.mature.drain());
+            }
+        }
+        references
+    }
 
-    /// Index into the references table for the start of nursery objects
-    nursery_index: usize,
+    /// Copy the references to process in this GC.
+    fn copy_to(&self, nursery: bool, references: &mut Vec<ObjectReference>) {
+        references.extend(self.nursery.iter().copied());
+        if !nursery {
+            references.extend(self.mature.iter().copied());
+        }
+    }
 }
 
 impl ReferenceProcessor {
-    pub fn new(semantics: Semantics) -> Self {
+    pub fn new(semantics: Semantics, num_shards: usize) -> Self {
+        let num_shards = num_shards.max(1);
         ReferenceProcessor {
-            sync: Mutex::new(ReferenceProcessorSync {
-                references: HashSet::with_capacity(INITIAL_SIZE),
-                enqueued_references: vec![],
-                nursery_index: 0,
-            }),
+            shards: (0..num_shards)
+                .map(|_| {
+                    Mutex::new(ReferenceShard {
+                        mature: HashSet::with_capacity(INITIAL_SIZE / num_shards),
+                        nursery: HashSet::new(),
+                    })
+                })
+                .collect(),
+            enqueued_references: Mutex::new(vec![]),
             semantics,
             allow_new_candidate: AtomicBool::new(true),
         }
     }
 
+    /// The shard for a reference at its current address.
+    fn shard_index(&self, reff: ObjectReference) -> usize {
+        // Fibonacci hashing, so that references of the same size are spread over all the shards.
+        let hash = (reff.to_raw_address().as_usize() as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        (hash >> 32) as usize % self.shards.len()
+    }
+
     /// Add a candidate.
     pub fn add_candidate(&self, reff: ObjectReference) {
         if !self.allow_new_candidate.load(Ordering::SeqCst) {
             return;
         }
 
-        let mut sync = self.sync.lock().unwrap();
-        sync.references.insert(reff);
+        let mut shard = self.shards[self.shard_index(reff)].lock().unwrap();
+        if !shard.mature.contains(&reff) {
+            shard.nursery.insert(reff);
+        }
+    }
+
+    /// Put references that stay in the table after processing back to their shards.  They are
+    /// mature from now on.
+    fn add_processed(&self, references: Vec<ObjectReference>) {
+        let mut by_shard = vec![vec![]; self.shards.len()];
+        for reff in references {
+            by_shard[self.shard_index(reff)].push(reff);
+        }
+        for (shard, references) in self.shards.iter().zip(by_shard) {
+            if references.is_empty() {
+                continue;
+            }
+            let mut shard = shard.lock().unwrap();
+            for reff in references {
+                // The binding may have added it again while we processed it.
+                shard.nursery.remove(&reff);
+                shard.mature.insert(reff);
+            }
+        }
+    }
+
+    /// Split references into chunks, and create a work packet for each chunk.
+    fn create_packets<VM: VMBinding>(
+        references: impl IntoIterator<Item = ObjectReference>,
+        packets: &mut Vec<Box<dyn GCWork<VM>>>,
+        mut create: impl FnMut(Vec<ObjectReference>) -> Box<dyn GCWork<VM>>,
+    ) {
+        let mut chunk = Vec::with_capacity(REFERENCES_PER_PACKET);
+        for reff in references {
+            chunk.push(reff);
+            if chunk.len() == REFERENCES_PER_PACKET {
+                packets.push(create(std::mem::replace(
+                    &mut chunk,
+                    Vec::with_capacity(REFERENCES_PER_PACKET),
+                )));
+            }
+        }
+        if !chunk.is_empty() {
+            packets.push(create(chunk));
+        }
     }
 
     fn disallow_new_candidate(&self) {
@@ -252,47 +424,82 @@ impl ReferenceProcessor {
 
     /// Inform the binding to enqueue the weak references whose referents were cleared in this GC.
     pub fn enqueue<VM: VMBinding>(&self, tls: VMWorkerThread) {
-        let mut sync = self.sync.lock().unwrap();
+        let mut enqueued_references = self.enqueued_references.lock().unwrap();
 
         // This is the end of a GC. We do some assertions here to make sure our reference tables are correct.
         #[cfg(debug_assertions)]
         {
             // For references in the table, the reference needs to be valid, and if the referent is not cleared, it should be valid as well
-            sync.references.iter().for_each(|reff| {
-                debug_assert!(reff.is_in_any_space::<VM>());
-                if let Some(referent) = VM::VMReferenceGlue::get_referent(*reff) {
-                    debug_assert!(
-                        referent.is_in_any_space::<VM>(),
-                        "Referent {:?} (of reference {:?}) is not in any space",
-                        referent,
-                        reff
-                    );
-                }
-            });
+            for shard in self.shards.iter() {
+                let shard = shard.lock().unwrap();
+                debug_assert!(shard.mature.is_disjoint(&shard.nursery));
+                shard
+                    .mature
+                    .iter()
+                    .chain(shard.nursery.iter())
+                    .for_each(|reff| {
+                        debug_assert!(reff.is_in_any_space::<VM>());
+                        if let Some(referent) = VM::VMReferenceGlue::get_referent(*reff) {
+                            debug_assert!(
+                                referent.is_in_any_space::<VM>(),
+                                "Referent {:?} (of reference {:?}) is not in any space",
+                                referent,
+                                reff
+                            );
+                        }
+                    });
+            }
             // For references that will be enqueue'd, the reference needs to be valid, and the referent needs to be cleared.
-            sync.enqueued_references.iter().for_each(|reff| {
+            enqueued_references.iter().for_each(|reff| {
                 debug_assert!(reff.is_in_any_space::<VM>());
                 let maybe_referent = VM::VMReferenceGlue::get_referent(*reff);
                 debug_assert!(maybe_referent.is_none());
             });
         }
 
-        if !sync.enqueued_references.is_empty() {
-            trace!("enqueue: {:?}", sync.enqueued_references);
-            VM::VMReferenceGlue::enqueue_references(&sync.enqueued_references, tls);
-            sync.enqueued_references.clear();
+        if !enqueued_references.is_empty() {
+            trace!("enqueue: {:?}", enqueued_references);
+            VM::VMReferenceGlue::enqueue_references(&enqueued_references, tls);
+            enqueued_references.clear();
         }
 
         self.allow_new_candidate();
     }
 
-    /// Forward the reference tables in the reference processor. This is only needed if a plan does not forward
-    /// objects in their first transitive closure.
-    /// nursery is not used for this.
-    pub fn forward<E: ProcessEdgesWork>(&self, trace: &mut E, _nursery: bool) {
-        let mut sync = self.sync.lock().unwrap();
+    /// Create work packets to forward the reference tables in the reference processor. This is
+    /// only needed if a plan does not forward objects in their first transitive closure.
+    fn forward<E: ProcessEdgesWork>(
+        &self,
+        nursery: bool,
+        packets: &mut Vec<Box<dyn GCWork<E::VM>>>,
+    ) {
         debug!("Starting ReferenceProcessor.forward({:?})", self.semantics);
 
+        // We start forwarding. No longer accept new candidates.  See `allow_new_candidate`.
+        self.disallow_new_candidate();
+
+        let semantics = self.semantics;
+        for shard in self.shards.iter() {
+            let references = shard.lock().unwrap().take(nursery);
+            Self::create_packets(references, packets, |references| {
+                Box::new(ForwardReferences::<E>::new(semantics, references, false))
+            });
+        }
+
+        let enqueued_references = std::mem::take(&mut *self.enqueued_references.lock().unwrap());
+        Self::create_packets(enqueued_references, packets, |references| {
+            Box::new(ForwardReferences::<E>::new(semantics, references, true))
+        });
+    }
+
+    /// Forward references and their referents.  `enqueued` tells if they are references to
+    /// enqueue, or references in the reference table.
+    fn forward_references<E: ProcessEdgesWork>(
+        &self,
+        trace: &mut E,
+        references: Vec<ObjectReference>,
+        enqueued: bool,
+    ) {
         // Forward a single reference
         fn forward_reference<E: ProcessEdgesWork>(
             trace: &mut E,
@@ -326,81 +533,95 @@ impl ReferenceProcessor {
             new_reference
         }
 
-        sync.references = sync
-            .references
-            .iter()
-            .map(|reff| forward_reference::<E>(trace, *reff))
-            .collect();
-
-        sync.enqueued_references = sync
-            .enqueued_references
-            .iter()
-            .map(|reff| forward_reference::<E>(trace, *reff))
+        let forwarded: Vec<ObjectReference> = references
+            .into_iter()
+            .map(|reff| forward_reference::<E>(trace, reff))
             .collect();
 
-        debug!("Ending ReferenceProcessor.forward({:?})", self.semantics);
-
-        // We finish forwarding. No longer accept new candidates.
-        self.disallow_new_candidate();
+        if enqueued {
+            self.enqueued_references.lock().unwrap().extend(forwarded);
+        } else {
+            self.add_processed(forwarded);
+        }
     }
 
-    /// Scan the reference table, and update each reference/referent.
+    /// Create work packets to scan the reference table, and update each reference/referent.
     /// It doesn't keep the reference or the referent alive.
-    // TODO: nursery is currently ignored. We used to use Vec for the reference table, and use an int
-    // to point to the reference that we last scanned. However, when we use HashSet for reference table,
-    // we can no longer do that.
-    fn scan<VM: VMBinding>(&self, _nursery: bool) {
-        let mut sync = self.sync.lock().unwrap();
+    fn scan<VM: VMBinding>(&self, nursery: bool, packets: &mut Vec<Box<dyn GCWork<VM>>>) {
+        debug!(
+            "Starting ReferenceProcessor.scan({:?}, nursery: {})",
+            self.semantics, nursery
+        );
 
-        debug!("Starting ReferenceProcessor.scan({:?})", self.semantics);
+        let semantics = self.semantics;
+        for shard in self.shards.iter() {
+            let references = shard.lock().unwrap().take(nursery);
+            Self::create_packets(references, packets, |references| {
+                Box::new(ScanReferences::<VM>::new(semantics, references))
+            });
+        }
+    }
 
-        trace!(
-            "{:?} Reference table is {:?}",
-            self.semantics,
-            sync.references
-        );
+    /// Scan references taken out of the reference table.
+    fn scan_references<VM: VMBinding>(&self, references: Vec<ObjectReference>) {
+        trace!("{:?} references to scan: {:?}", self.semantics, references);
 
-        //debug_assert!(sync.enqueued_references.is_empty());
         // Put enqueued reference in this vec
         let mut enqueued_references = vec![];
 
         // Determinine liveness for each reference and only keep the refs if `process_reference()` returns Some.
-        let new_set: HashSet<ObjectReference> = sync
-            .references
-            .iter()
-            .filter_map(|reff| self.process_reference::<VM>(*reff, &mut enqueued_references))
+        let num_references = references.len();
+        let kept: Vec<ObjectReference> = references
+            .into_iter()
+            .filter_map(|reff| self.process_reference::<VM>(reff, &mut enqueued_references))
             .collect();
 
-        debug!(
-            "{:?} reference table from {} to {} ({} enqueued)",
+        trace!(
+            "{:?} references scanned: {} to {} ({} enqueued)",
             self.semantics,
-            sync.references.len(),
-            new_set.len(),
+            num_references,
+            kept.len(),
             enqueued_references.len()
         );
-        sync.references = new_set;
-        sync.enqueued_references.extend(enqueued_references);
-
-        debug!("Ending ReferenceProcessor.scan({:?})", self.semantics);
+        self.add_processed(kept);
+        if !enqueued_references.is_empty() {
+            self.enqueued_references
+                .lock()
+                .unwrap()
+                .extend(enqueued_references);
+        }
     }
 
-    /// Retain referent in the reference table. This method deals only with soft references.
-    /// It retains the referent if the reference is definitely reachable. This method does
-    /// not update reference or referent. So after this method, scan() should be used to update
-    /// the references/referents.
-    fn retain<E: ProcessEdgesWork>(&self, trace: &mut E, _nursery: bool) {
+    /// Create work packets to retain referents in the reference table. This method deals only
+    /// with soft references.  The references are not taken out of the table.
+    fn retain<E: ProcessEdgesWork>(
+        &self,
+        nursery: bool,
+        retention: SoftReferenceRetention,
+        packets: &mut Vec<Box<dyn GCWork<E::VM>>>,
+    ) {
         debug_assert!(self.semantics == Semantics::SOFT);
-
-        let sync = self.sync.lock().unwrap();
-
         debug!("Starting ReferenceProcessor.retain({:?})", self.semantics);
-        trace!(
-            "{:?} Reference table is {:?}",
-            self.semantics,
-            sync.references
-        );
 
-        for reference in sync.references.iter() {
+        let mut references = vec![];
+        for shard in self.shards.iter() {
+            shard.lock().unwrap().copy_to(nursery, &mut references);
+        }
+        Self::create_packets(references, packets, |references| {
+            Box::new(RetainSoftReferences::<E>::new(references, retention))
+        });
+    }
+
+    /// Retain referents of the given soft references. It retains the referent if the reference is
+    /// definitely reachable, and `retention` says so for the reference. This method does not
+    /// update reference or referent. So after this method, scan() should be used to update the
+    /// references/referents.
+    fn retain_references<E: ProcessEdgesWork>(
+        trace: &mut E,
+        references: &[ObjectReference],
+        retention: SoftReferenceRetention,
+    ) {
+        for reference in references.iter() {
             trace!("Processing reference: {:?}", reference);
 
             if !reference.is_live::<E::VM>() {
@@ -408,6 +629,16 @@ impl ReferenceProcessor {
                 // following trace. We postpone the decision.
                 continue;
             }
+            if retention != SoftReferenceRetention::All
+                && !retention.should_retain(
+                    <E::VM as VMBinding>::VMReferenceGlue::get_soft_reference_timestamp(*reference),
+                )
+            {
+                // Not accessed recently.  Let scan() clear it unless the referent is reachable
+                // in other ways.
+                trace!(" not accessed recently");
+                continue;
+            }
             // Reference is definitely reachable.  Retain the referent.
             if let Some(referent) = <E::VM as VMBinding>::VMReferenceGlue::get_referent(*reference)
             {
@@ -415,8 +646,6 @@ impl ReferenceProcessor {
                 trace!(" ~> {:?} (retained)", referent);
             }
         }
-
-        debug!("Ending ReferenceProcessor.retain({:?})", self.semantics);
     }
 
     /// Process a reference.
@@ -490,20 +719,22 @@ use crate::scheduler::GCWorker;
 use crate::MMTK;
 use std::marker::PhantomData;
 
-#[derive(Default)]
+/// Rescan references at the end of a transitive closure.  This is used as the sentinel of the
+/// bucket of `stage`, and the scanning work packets are added to the same bucket.
 pub(crate) struct RescanReferences<VM: VMBinding> {
     pub soft: bool,
     pub weak: bool,
+    pub stage: WorkBucketStage,
     pub phantom_data: PhantomData<VM>,
 }
 
 impl<VM: VMBinding> GCWork<VM> for RescanReferences<VM> {
     fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
         if self.soft {
-            mmtk.reference_processors.scan_soft_refs(mmtk);
+            mmtk.reference_processors.scan_soft_refs(mmtk, self.stage);
         }
         if self.weak {
-            mmtk.reference_processors.scan_weak_refs(mmtk);
+            mmtk.reference_processors.scan_weak_refs(mmtk, self.stage);
         }
     }
 }
@@ -512,25 +743,26 @@ impl<VM: VMBinding> GCWork<VM> for RescanReferences<VM> {
 pub(crate) struct SoftRefProcessing<E: ProcessEdgesWork>(PhantomData<E>);
 impl<E: ProcessEdgesWork> GCWork<E::VM> for SoftRefProcessing<E> {
     fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
-        if !mmtk.state.is_emergency_collection() {
+        if !mmtk.state.should_clear_soft_references() {
             // Postpone the scanning to the end of the transitive closure from strongly reachable
             // soft references.
             let rescan = Box::new(RescanReferences {
                 soft: true,
                 weak: false,
+                stage: WorkBucketStage::SoftRefClosure,
                 phantom_data: PhantomData,
             });
             worker.scheduler().work_buckets[WorkBucketStage::SoftRefClosure].set_sentinel(rescan);
 
-            // Retain soft references.  This will expand the transitive closure.  We create an
-            // instance of `E` for this.
-            let mut w = E::new(vec![], false, mmtk, WorkBucketStage::SoftRefClosure);
-            w.set_worker(worker);
-            mmtk.reference_processors.retain_soft_refs(&mut w, mmtk);
-            w.flush();
+            // Retain soft references.  This will expand the transitive closure.
+            let retention = SoftReferenceRetention::new(mmtk);
+            debug!("Soft reference retention: {:?}", retention);
+            mmtk.reference_processors
+                .retain_soft_refs::<E>(mmtk, retention);
         } else {
             // Scan soft references immediately without retaining.
-            mmtk.reference_processors.scan_soft_refs(mmtk);
+            mmtk.reference_processors
+                .scan_soft_refs(mmtk, WorkBucketStage::SoftRefClosure);
         }
     }
 }
@@ -544,7 +776,8 @@ impl<E: ProcessEdgesWork> SoftRefProcessing<E> {
 pub(crate) struct WeakRefProcessing<VM: VMBinding>(PhantomData<VM>);
 impl<VM: VMBinding> GCWork<VM> for WeakRefProcessing<VM> {
     fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
-        mmtk.reference_processors.scan_weak_refs(mmtk);
+        mmtk.reference_processors
+            .scan_weak_refs(mmtk, WorkBucketStage::WeakRefClosure);
     }
 }
 impl<VM: VMBinding> WeakRefProcessing<VM> {
@@ -557,7 +790,8 @@ impl<VM: VMBinding> WeakRefProcessing<VM> {
 pub(crate) struct PhantomRefProcessing<VM: VMBinding>(PhantomData<VM>);
 impl<VM: VMBinding> GCWork<VM> for PhantomRefProcessing<VM> {
     fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
-        mmtk.reference_processors.scan_phantom_refs(mmtk);
+        mmtk.reference_processors
+            .scan_phantom_refs(mmtk, WorkBucketStage::PhantomRefClosure);
     }
 }
 impl<VM: VMBinding> PhantomRefProcessing<VM> {
@@ -569,16 +803,90 @@ impl<VM: VMBinding> PhantomRefProcessing<VM> {
 #[derive(Default)]
 pub(crate) struct RefForwarding<E: ProcessEdgesWork>(PhantomData<E>);
 impl<E: ProcessEdgesWork> GCWork<E::VM> for RefForwarding<E> {
+    fn do_work(&mut self, _worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        mmtk.reference_processors.forward_refs::<E>(mmtk);
+    }
+}
+impl<E: ProcessEdgesWork> RefForwarding<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Scan a chunk of references taken out of the reference table of `semantics`.
+pub(crate) struct ScanReferences<VM: VMBinding> {
+    semantics: Semantics,
+    references: Vec<ObjectReference>,
+    phantom_data: PhantomData<VM>,
+}
+impl<VM: VMBinding> GCWork<VM> for ScanReferences<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        mmtk.reference_processors
+            .get(self.semantics)
+            .scan_references::<VM>(std::mem::take(&mut self.references));
+    }
+}
+impl<VM: VMBinding> ScanReferences<VM> {
+    fn new(semantics: Semantics, references: Vec<ObjectReference>) -> Self {
+        Self {
+            semantics,
+            references,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+/// Retain the referents of a chunk of soft references.
+pub(crate) struct RetainSoftReferences<E: ProcessEdgesWork> {
+    references: Vec<ObjectReference>,
+    retention: SoftReferenceRetention,
+    phantom_data: PhantomData<E>,
+}
+impl<E: ProcessEdgesWork> GCWork<E::VM> for RetainSoftReferences<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        // We create an instance of `E` for expanding the transitive closure.
+        let mut w = E::new(vec![], false, mmtk, WorkBucketStage::SoftRefClosure);
+        w.set_worker(worker);
+        ReferenceProcessor::retain_references(&mut w, &self.references, self.retention);
+        w.flush();
+    }
+}
+impl<E: ProcessEdgesWork> RetainSoftReferences<E> {
+    fn new(references: Vec<ObjectReference>, retention: SoftReferenceRetention) -> Self {
+        Self {
+            references,
+            retention,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+/// Forward a chunk of references taken out of the reference table of `semantics`, or out of the
+/// references to enqueue if `enqueued` is true.
+pub(crate) struct ForwardReferences<E: ProcessEdgesWork> {
+    semantics: Semantics,
+    references: Vec<ObjectReference>,
+    enqueued: bool,
+    phantom_data: PhantomData<E>,
+}
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ForwardReferences<E> {
     fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
         let mut w = E::new(vec![], false, mmtk, WorkBucketStage::RefForwarding);
         w.set_worker(worker);
-        mmtk.reference_processors.forward_refs(&mut w, mmtk);
+        mmtk.reference_processors
+            .get(self.semantics)
+            .forward_references(&mut w, std::mem::take(&mut self.references), self.enqueued);
         w.flush();
     }
 }
-impl<E: ProcessEdgesWork> RefForwarding<E> {
-    pub fn new() -> Self {
-        Self(PhantomData)
+impl<E: ProcessEdgesWork> ForwardReferences<E> {
+    fn new(semantics: Semantics, references: Vec<ObjectReference>, enqueued: bool) -> Self {
+        Self {
+            semantics,
+            references,
+            enqueued,
+            phantom_data: PhantomData,
+        }
     }
 }
 
@@ -594,3 +902,135 @@ impl<VM: VMBinding> RefEnqueue<VM> {
         Self(PhantomData)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Address;
+
+    fn object(i: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(0x1000_0000 + i * 32) })
+            .unwrap()
+    }
+
+    fn table_len(processor: &ReferenceProcessor, nursery: bool) -> usize {
+        processor
+            .shards
+            .iter()
+            .map(|shard| {
+                let shard = shard.lock().unwrap();
+                if nursery {
+                    shard.nursery.len()
+                } else {
+                    shard.nursery.len() + shard.mature.len()
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_shards() {
+        let processor = ReferenceProcessor::new(Semantics::WEAK, 8);
+        for i in 0..1000 {
+            processor.add_candidate(object(i));
+            processor.add_candidate(object(i));
+        }
+        assert_eq!(table_len(&processor, false), 1000);
+        // Objects of the same size are spread over all the shards.
+        for shard in processor.shards.iter() {
+            assert!(!shard.lock().unwrap().nursery.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_nursery_references() {
+        let processor = ReferenceProcessor::new(Semantics::WEAK, 4);
+        for i in 0..100 {
+            processor.add_candidate(object(i));
+        }
+        // A GC keeps the first 50 references.  They become mature.
+        let mut references = vec![];
+        for shard in processor.shards.iter() {
+            references.extend(shard.lock().unwrap().take(true));
+        }
+        assert_eq!(references.len(), 100);
+        assert_eq!(table_len(&processor, false), 0);
+        references.retain(|reff| *reff < object(50));
+        processor.add_processed(references);
+        assert_eq!(table_len(&processor, false), 50);
+        assert_eq!(table_len(&processor, true), 0);
+
+        // Adding a mature reference again does not make it a nursery reference.
+        for i in 25..75 {
+            processor.add_candidate(object(i));
+        }
+        assert_eq!(table_len(&processor, false), 75);
+        assert_eq!(table_len(&processor, true), 25);
+
+        // A nursery GC only processes the nursery references.
+        let mut nursery = vec![];
+        for shard in processor.shards.iter() {
+            shard.lock().unwrap().copy_to(true, &mut nursery);
+        }
+        nursery.sort();
+        assert_eq!(nursery, (50..75).map(object).collect::<Vec<_>>());
+
+        // A full heap GC processes all of them.
+        let mut all = vec![];
+        for shard in processor.shards.iter() {
+            all.extend(shard.lock().unwrap().take(false));
+        }
+        all.sort();
+        assert_eq!(all, (0..75).map(object).collect::<Vec<_>>());
+        assert_eq!(table_len(&processor, false), 0);
+    }
+
+    #[test]
+    fn test_add_processed_moved_references() {
+        let processor = ReferenceProcessor::new(Semantics::SOFT, 4);
+        processor.add_candidate(object(1));
+        for shard in processor.shards.iter() {
+            let taken = shard.lock().unwrap().take(false);
+            assert!(taken.is_empty() || taken.contains(&object(1)));
+        }
+        // The reference moved during the GC, and the binding added its new address while we
+        // processed it.
+        processor.add_candidate(object(2));
+        processor.add_processed(vec![object(2)]);
+        assert_eq!(table_len(&processor, false), 1);
+        assert_eq!(table_len(&processor, true), 0);
+        let index = processor.shard_index(object(2));
+        assert!(processor.shards[index]
+            .lock()
+            .unwrap()
+            .mature
+            .contains(&object(2)));
+    }
+
+    #[test]
+    fn test_soft_reference_retention() {
+        let all = SoftReferenceRetention::All;
+        assert!(all.should_retain(None));
+        assert!(all.should_retain(Some(0)));
+
+        let lru = SoftReferenceRetention::RecentlyAccessed {
+            clock: 10_000,
+            max_interval: 3_000,
+        };
+        assert!(lru.should_retain(None));
+        assert!(lru.should_retain(Some(10_000)));
+        assert!(lru.should_retain(Some(7_000)));
+        assert!(!lru.should_retain(Some(6_999)));
+        assert!(!lru.should_retain(Some(0)));
+        // Accessed after the GC started.
+        assert!(lru.should_retain(Some(10_001)));
+
+        // With no free heap, only the references accessed just now are retained.
+        let no_free_heap = SoftReferenceRetention::RecentlyAccessed {
+            clock: 10_000,
+            max_interval: 0,
+        };
+        assert!(no_free_heap.should_retain(Some(10_000)));
+        assert!(!no_free_heap.should_retain(Some(9_999)));
+    }
+}
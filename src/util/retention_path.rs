@@ -0,0 +1,458 @@
+//! Find out why an object is alive.
+//!
+//! A retention path query stops the world like a GC, scans the roots, and walks the object graph
+//! breadth-first from the roots until it finds the paths that lead to a given object.  The walk
+//! does not move, mark or reclaim any object.  See
+//! [`crate::memory_manager::find_retention_paths`].
+
+use crate::global_state::GcStatus;
+use crate::plan::{NoGC, ObjectQueue};
+use crate::scheduler::gc_work::{EdgeOf, ProcessEdgesBase, ScanObjectsWork, StopMutators};
+use crate::scheduler::{GCWork, GCWorkContext, GCWorker, ProcessEdgesWork, WorkBucketStage};
+use crate::util::ObjectReference;
+use crate::vm::edge_shape::Edge;
+use crate::vm::{Scanning, VMBinding};
+use crate::MMTK;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering;
+
+/// How a root refers to an object.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RootKind<ES: Edge> {
+    /// A root edge reported by [`RootsWorkFactory::create_process_edge_roots_work`].
+    Edge(ES),
+    /// A root object reported by [`RootsWorkFactory::create_process_pinning_roots_work`].
+    PinningNode,
+    /// A root object reported by [`RootsWorkFactory::create_process_tpinning_roots_work`].
+    TransitivelyPinningNode,
+}
+
+/// A path from a root to an object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPath<ES: Edge> {
+    /// How the root refers to the first object in `objects`.
+    pub root: RootKind<ES>,
+    /// The objects on the path, starting from the object referred to by the root, and ending
+    /// with the queried object.
+    pub objects: Vec<ObjectReference>,
+    /// `edges[i]` is the field of `objects[i]` that refers to `objects[i + 1]`.  It is `None` if
+    /// `objects[i]` does not support edge enqueuing and was scanned with
+    /// [`Scanning::scan_object_and_trace_edges`].
+    pub edges: Vec<Option<ES>>,
+}
+
+/// The error returned by [`crate::memory_manager::find_retention_paths`] if another retention
+/// path query is in progress.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueryInProgress;
+
+/// A root reported to a retention path query.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Root<ES: Edge> {
+    pub kind: RootKind<ES>,
+    pub object: ObjectReference,
+}
+
+/// A pending retention path query.
+pub(crate) struct RetentionQuery<ES: Edge> {
+    /// The object to find paths to.
+    target: ObjectReference,
+    /// The maximum number of paths to find.
+    max_paths: usize,
+    /// Roots reported during the walk.
+    roots: Vec<Root<ES>>,
+    /// The result.  It is `None` until the walk has finished.
+    paths: Option<Vec<RetentionPath<ES>>>,
+}
+
+impl<ES: Edge> RetentionQuery<ES> {
+    pub fn new(target: ObjectReference, max_paths: usize) -> Self {
+        Self {
+            target,
+            max_paths,
+            roots: vec![],
+            paths: None,
+        }
+    }
+
+    /// Take the result if the walk has finished.
+    pub fn take_paths(&mut self) -> Option<Vec<RetentionPath<ES>>> {
+        self.paths.take()
+    }
+}
+
+/// How the walk first reached an object.
+#[derive(Copy, Clone)]
+enum ReachedFrom<ES: Edge> {
+    /// The root at the index in the root list.
+    Root(usize),
+    /// A field of another object.
+    Object {
+        parent: ObjectReference,
+        edge: Option<ES>,
+    },
+}
+
+/// Find at most `max_paths` paths from `roots` to `target` with a breadth-first search.  Paths
+/// are returned from the shortest to the longest.  Each path reaches `target` through a
+/// different root or a different field, and each object on a path is reached through one of its
+/// shortest paths.
+///
+/// `scan` should call the given closure for each object referred to by an object, together with
+/// the edge if the edge is known.
+pub(crate) fn find_paths<ES: Edge>(
+    roots: &[Root<ES>],
+    target: ObjectReference,
+    max_paths: usize,
+    mut scan: impl FnMut(ObjectReference, &mut dyn FnMut(ObjectReference, Option<ES>)),
+) -> Vec<RetentionPath<ES>> {
+    let mut reached: HashMap<ObjectReference, ReachedFrom<ES>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    let mut paths = vec![];
+
+    let build_path = |reached: &HashMap<ObjectReference, ReachedFrom<ES>>,
+                      mut from: ReachedFrom<ES>| {
+        let mut objects = vec![target];
+        let mut edges = vec![];
+        loop {
+            match from {
+                ReachedFrom::Root(index) => {
+                    objects.reverse();
+                    edges.reverse();
+                    let root = &roots[index];
+                    return RetentionPath {
+                        root: root.kind,
+                        objects,
+                        edges,
+                    };
+                }
+                ReachedFrom::Object { parent, edge } => {
+                    objects.push(parent);
+                    edges.push(edge);
+                    from = reached[&parent];
+                }
+            }
+        }
+    };
+
+    for (index, root) in roots.iter().enumerate() {
+        if paths.len() >= max_paths {
+            return paths;
+        }
+        if root.object == target {
+            paths.push(build_path(&reached, ReachedFrom::Root(index)));
+        } else if let Entry::Vacant(entry) = reached.entry(root.object) {
+            entry.insert(ReachedFrom::Root(index));
+            queue.push_back(root.object);
+        }
+    }
+
+    while let Some(parent) = queue.pop_front() {
+        if paths.len() >= max_paths {
+            break;
+        }
+        let mut children = vec![];
+        scan(parent, &mut |child, edge| children.push((child, edge)));
+        for (child, edge) in children {
+            let from = ReachedFrom::Object { parent, edge };
+            if child == target {
+                if paths.len() < max_paths {
+                    paths.push(build_path(&reached, from));
+                }
+            } else if let Entry::Vacant(entry) = reached.entry(child) {
+                entry.insert(from);
+                queue.push_back(child);
+            }
+        }
+    }
+
+    paths
+}
+
+/// Record roots for the pending retention path query.
+fn add_roots<VM: VMBinding>(
+    mmtk: &'static MMTK<VM>,
+    roots: impl Iterator<Item = (RootKind<VM::VMEdge>, ObjectReference)>,
+) {
+    let mut query = mmtk.retention_query.lock().unwrap();
+    let query = query.as_mut().expect("No retention path query");
+    query
+        .roots
+        .extend(roots.map(|(kind, object)| Root { kind, object }));
+}
+
+/// The `GCWorkContext` of a retention path query.  It lets the query reuse `StopMutators`,
+/// `ScanMutatorRoots` and `ScanVMSpecificRoots` to scan roots.
+pub(crate) struct RetentionWalkContext<VM: VMBinding>(PhantomData<VM>);
+
+impl<VM: VMBinding> GCWorkContext for RetentionWalkContext<VM> {
+    type VM = VM;
+    // The query does not prepare or release the plan, so the plan type is not used.
+    type PlanType = NoGC<VM>;
+    type DefaultProcessEdges = RetentionProcessEdges<VM, false>;
+    type PinningProcessEdges = RetentionProcessEdges<VM, true>;
+    const FLUSH_MUTATORS: bool = false;
+}
+
+/// A `ProcessEdgesWork` that records the roots for the retention path query instead of tracing
+/// them.
+///
+/// `ProcessRootNode` traces root nodes with `PinningProcessEdges`, and creates the work packet to
+/// scan them with `DefaultProcessEdges` for pinning roots, or with `PinningProcessEdges` for
+/// transitively pinning roots.  `TRANSITIVE` tells which kind of root nodes the packets created by
+/// `create_scan_work` record.
+pub(crate) struct RetentionProcessEdges<VM: VMBinding, const TRANSITIVE: bool> {
+    base: ProcessEdgesBase<VM>,
+}
+
+impl<VM: VMBinding, const TRANSITIVE: bool> ProcessEdgesWork
+    for RetentionProcessEdges<VM, TRANSITIVE>
+{
+    type VM = VM;
+    type ScanObjectsWorkType = RecordRootNodes<VM, TRANSITIVE>;
+
+    const OVERWRITE_REFERENCE: bool = false;
+
+    fn new(
+        edges: Vec<EdgeOf<Self>>,
+        roots: bool,
+        mmtk: &'static MMTK<VM>,
+        bucket: WorkBucketStage,
+    ) -> Self {
+        let base = ProcessEdgesBase::new(edges, roots, mmtk, bucket);
+        Self { base }
+    }
+
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        // Only called for root nodes.  They are recorded in `create_scan_work`.
+        self.base.nodes.enqueue(object);
+        object
+    }
+
+    fn process_edges(&mut self) {
+        add_roots(
+            self.mmtk(),
+            self.edges
+                .iter()
+                .filter_map(|edge| edge.load().map(|object| (RootKind::Edge(*edge), object))),
+        );
+    }
+
+    fn create_scan_work(&self, nodes: Vec<ObjectReference>) -> Self::ScanObjectsWorkType {
+        RecordRootNodes {
+            nodes,
+            bucket: self.bucket,
+            phantom: PhantomData,
+        }
+    }
+
+    // The roots of a query are not the roots of a GC.
+    #[cfg(feature = "sanity")]
+    fn cache_roots_for_sanity_gc(&mut self) {}
+
+    #[cfg(feature = "sanity")]
+    fn cache_root_nodes_for_sanity_gc(&self, _nodes: Vec<ObjectReference>) {}
+}
+
+impl<VM: VMBinding, const TRANSITIVE: bool> Deref for RetentionProcessEdges<VM, TRANSITIVE> {
+    type Target = ProcessEdgesBase<VM>;
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding, const TRANSITIVE: bool> DerefMut for RetentionProcessEdges<VM, TRANSITIVE> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Record root nodes for the retention path query.  This is created by `ProcessRootNode` in place
+/// of the work packet that scans the root nodes.
+pub(crate) struct RecordRootNodes<VM: VMBinding, const TRANSITIVE: bool> {
+    nodes: Vec<ObjectReference>,
+    bucket: WorkBucketStage,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding, const TRANSITIVE: bool> ScanObjectsWork<VM>
+    for RecordRootNodes<VM, TRANSITIVE>
+{
+    type E = RetentionProcessEdges<VM, TRANSITIVE>;
+
+    fn post_scan_object(&self, _object: ObjectReference) {}
+
+    fn get_bucket(&self) -> WorkBucketStage {
+        self.bucket
+    }
+}
+
+impl<VM: VMBinding, const TRANSITIVE: bool> GCWork<VM> for RecordRootNodes<VM, TRANSITIVE> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let kind = if TRANSITIVE {
+            RootKind::TransitivelyPinningNode
+        } else {
+            RootKind::PinningNode
+        };
+        add_roots(mmtk, self.nodes.iter().map(|object| (kind, *object)));
+    }
+}
+
+/// Schedule the work packets of a retention path query instead of a GC.  Return false if no
+/// query is pending.  This is called from `ScheduleCollection`.
+pub(crate) fn schedule_retention_walk<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> bool {
+    {
+        let query = mmtk.retention_query.lock().unwrap();
+        if !query.as_ref().is_some_and(|query| query.paths.is_none()) {
+            return false;
+        }
+        mmtk.retention_walking.store(true, Ordering::SeqCst);
+    }
+    mmtk.set_gc_status(GcStatus::GcPrepare);
+    mmtk.scheduler.work_buckets[WorkBucketStage::Unconstrained]
+        .add(StopMutators::<RetentionWalkContext<VM>>::new());
+    // The roots are recorded in the closure stages.  The Release bucket is opened after all of
+    // them are drained.
+    mmtk.scheduler.work_buckets[WorkBucketStage::Release].add(RetentionWalk);
+    true
+}
+
+/// Called when all the work packets of the current pause have been executed.  Return true if the
+/// pause was for a retention path query rather than a GC.
+pub(crate) fn on_pause_finished<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> bool {
+    mmtk.retention_walking.swap(false, Ordering::SeqCst)
+}
+
+/// Walk the object graph from the recorded roots, and record the paths to the queried object.
+/// This is done in a single work packet so that the search is breadth-first.
+pub(crate) struct RetentionWalk;
+
+impl<VM: VMBinding> GCWork<VM> for RetentionWalk {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let tls = worker.tls;
+        // Do not hold the lock during the walk.
+        let (roots, target, max_paths) = {
+            let mut query = mmtk.retention_query.lock().unwrap();
+            let query = query.as_mut().expect("No retention path query");
+            (
+                std::mem::take(&mut query.roots),
+                query.target,
+                query.max_paths,
+            )
+        };
+        let paths = find_paths(&roots, target, max_paths, |object, visit| {
+            if VM::VMScanning::support_edge_enqueuing(tls, object) {
+                VM::VMScanning::scan_object(tls, object, &mut |edge: VM::VMEdge| {
+                    if let Some(child) = edge.load() {
+                        visit(child, Some(edge));
+                    }
+                });
+            } else {
+                VM::VMScanning::scan_object_and_trace_edges(tls, object, &mut |child| {
+                    visit(child, None);
+                    child
+                });
+            }
+        });
+        debug!(
+            "Found {} retention paths to {} from {} roots",
+            paths.len(),
+            target,
+            roots.len()
+        );
+        let mut query = mmtk.retention_query.lock().unwrap();
+        query.as_mut().expect("No retention path query").paths = Some(paths);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Address;
+
+    fn object(addr: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(addr) }).unwrap()
+    }
+
+    fn edge(addr: usize) -> Address {
+        unsafe { Address::from_usize(addr) }
+    }
+
+    /// Find paths in a graph given as a list of (parent, field, child).
+    fn find_paths_in_graph(
+        roots: &[Root<Address>],
+        graph: &[(usize, usize, usize)],
+        target: usize,
+        max_paths: usize,
+    ) -> Vec<RetentionPath<Address>> {
+        find_paths(roots, object(target), max_paths, |parent, visit| {
+            for (p, field, child) in graph.iter().copied() {
+                if object(p) == parent {
+                    visit(object(child), Some(edge(field)));
+                }
+            }
+        })
+    }
+
+    fn root(kind: RootKind<Address>, addr: usize) -> Root<Address> {
+        Root {
+            kind,
+            object: object(addr),
+        }
+    }
+
+    #[test]
+    fn shortest_path_first() {
+        // root -> 0x1000 -> 0x2000 -> 0x3000 -> 0x5000
+        //           \-----> 0x4000 ------------/
+        let roots = [root(RootKind::Edge(edge(0x100)), 0x1000)];
+        let graph = [
+            (0x1000, 0x1008, 0x2000),
+            (0x1000, 0x1010, 0x4000),
+            (0x2000, 0x2008, 0x3000),
+            (0x3000, 0x3008, 0x5000),
+            (0x4000, 0x4008, 0x5000),
+        ];
+        let paths = find_paths_in_graph(&roots, &graph, 0x5000, 1);
+        assert_eq!(
+            paths,
+            vec![RetentionPath {
+                root: RootKind::Edge(edge(0x100)),
+                objects: vec![object(0x1000), object(0x4000), object(0x5000)],
+                edges: vec![Some(edge(0x1010)), Some(edge(0x4008))],
+            }]
+        );
+
+        let paths = find_paths_in_graph(&roots, &graph, 0x5000, 10);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[1].objects.len(), 4);
+        assert_eq!(paths[1].edges[2], Some(edge(0x3008)));
+    }
+
+    #[test]
+    fn target_is_root() {
+        let roots = [
+            root(RootKind::PinningNode, 0x1000),
+            root(RootKind::TransitivelyPinningNode, 0x2000),
+        ];
+        let graph = [(0x1000, 0x1008, 0x2000)];
+        let paths = find_paths_in_graph(&roots, &graph, 0x2000, 10);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].root, RootKind::TransitivelyPinningNode);
+        assert_eq!(paths[0].objects, vec![object(0x2000)]);
+        assert!(paths[0].edges.is_empty());
+        assert_eq!(paths[1].root, RootKind::PinningNode);
+        assert_eq!(paths[1].objects, vec![object(0x1000), object(0x2000)]);
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let roots = [root(RootKind::PinningNode, 0x1000)];
+        // A cycle that does not lead to the target.
+        let graph = [(0x1000, 0x1008, 0x2000), (0x2000, 0x2008, 0x1000)];
+        assert!(find_paths_in_graph(&roots, &graph, 0x3000, 10).is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::mmtk::SFT_MAP;
 use crate::plan::Plan;
 use crate::scheduler::gc_work::*;
 use crate::util::ObjectReference;
@@ -5,13 +6,31 @@ use crate::vm::edge_shape::Edge;
 use crate::vm::*;
 use crate::MMTK;
 use crate::{scheduler::*, ObjectQueue};
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
+/// How the sanity GC first reached an object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReachedFrom<ES: Edge> {
+    /// A root edge.
+    RootEdge(ES),
+    /// A root node, i.e. a root object reported by the binding without an edge.
+    RootNode,
+    /// A reference field of another object. `edge` is `None` if `parent` does not support edge
+    /// enqueuing, and was scanned with `Scanning::scan_object_and_trace_edges`.
+    Object {
+        parent: ObjectReference,
+        edge: Option<ES>,
+    },
+}
+
 #[allow(dead_code)]
 pub struct SanityChecker<ES: Edge> {
-    /// Visited objects
-    refs: HashSet<ObjectReference>,
+    /// Visited objects, and how each of them was first reached
+    refs: HashMap<ObjectReference, ReachedFrom<ES>>,
     /// Cached root edges for sanity root scanning
     root_edges: Vec<Vec<ES>>,
     /// Cached root nodes for sanity root scanning
@@ -27,7 +46,7 @@ impl<ES: Edge> Default for SanityChecker<ES> {
 impl<ES: Edge> SanityChecker<ES> {
     pub fn new() -> Self {
         Self {
-            refs: HashSet::new(),
+            refs: HashMap::new(),
             root_edges: vec![],
             root_nodes: vec![],
         }
@@ -47,6 +66,136 @@ impl<ES: Edge> SanityChecker<ES> {
         self.root_edges.clear();
         self.root_nodes.clear();
     }
+
+    /// Record that `object` is reached from `from`. Return true if this is the first time the
+    /// object is reached. Only the first path to an object is kept.
+    pub fn record(&mut self, object: ObjectReference, from: ReachedFrom<ES>) -> bool {
+        match self.refs.entry(object) {
+            Entry::Vacant(entry) => {
+                entry.insert(from);
+                true
+            }
+            Entry::Occupied(_) => false,
+        }
+    }
+
+    /// Return the path through which `object` was reached, starting from a root and ending with
+    /// `object` itself. Each element is an object and how it was reached from the previous one.
+    pub fn root_path(
+        &self,
+        object: ObjectReference,
+        from: ReachedFrom<ES>,
+    ) -> Vec<(ObjectReference, ReachedFrom<ES>)> {
+        let mut path = vec![(object, from)];
+        let mut current = from;
+        // A parent is always recorded before its children are, so following the parents
+        // terminates at a root.
+        while let ReachedFrom::Object { parent, .. } = current {
+            let Some(&parent_from) = self.refs.get(&parent) else {
+                break;
+            };
+            path.push((parent, parent_from));
+            current = parent_from;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Format the path returned by [`SanityChecker::root_path`], one edge per line.
+    pub fn format_root_path(&self, object: ObjectReference, from: ReachedFrom<ES>) -> String {
+        let mut out = String::new();
+        for (object, from) in self.root_path(object, from) {
+            match from {
+                ReachedFrom::RootEdge(edge) => {
+                    writeln!(out, "  root edge {:?} -> {}", edge, object)
+                }
+                ReachedFrom::RootNode => writeln!(out, "  root node {}", object),
+                ReachedFrom::Object {
+                    parent,
+                    edge: Some(edge),
+                } => writeln!(out, "  {} field {:?} -> {}", parent, edge, object),
+                ReachedFrom::Object { parent, edge: None } => {
+                    writeln!(out, "  {} -> {}", parent, object)
+                }
+            }
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Verify an object that is reached by the sanity GC for the first time. Return the reason if the
+/// object is not sane.
+fn verify_object<VM: VMBinding>(
+    mmtk: &'static MMTK<VM>,
+    object: ObjectReference,
+) -> Result<(), String> {
+    // FIXME steveb consider VM-specific integrity check on reference.
+    let sft = SFT_MAP.get_checked(object.to_address::<VM>());
+    if !object.is_sane::<VM>() {
+        return Err(format!("the object is not sane in space {}", sft.name()));
+    }
+
+    // A reference to an object that has been forwarded was not updated by the GC.
+    if let Some(new_object) = object.get_forwarded_object::<VM>() {
+        return Err(format!("the object has been forwarded to {}", new_object));
+    }
+
+    // Let plan check object
+    let plan = mmtk.get_plan();
+    if !plan.sanity_check_object(object) {
+        return Err("the plan does not consider the object sane".to_string());
+    }
+
+    // Let policy check object. The policies cannot tell the liveness of objects that are not
+    // traced in a nursery GC.
+    let full_heap = plan
+        .generational()
+        .map_or(true, |gen| !gen.is_current_gc_nursery());
+    if full_heap && !sft.sanity_check_object(object) {
+        return Err(format!(
+            "the object is not marked as live in space {}",
+            sft.name()
+        ));
+    }
+
+    // Let VM check object
+    if !VM::VMObjectModel::is_object_sane(object) {
+        return Err("the binding does not consider the object sane".to_string());
+    }
+
+    // If the valid object (VO) bit metadata is enabled, all live objects should have the VO
+    // bit set when sanity GC starts.
+    #[cfg(feature = "vo_bit")]
+    if !crate::util::metadata::vo_bit::is_vo_bit_set::<VM>(object) {
+        return Err("VO bit is not set".to_string());
+    }
+
+    Ok(())
+}
+
+/// Trace `object` which is reached from `from`. The object is verified the first time it is
+/// reached, and the sanity GC panics with the path from the root if it is not sane. Return true
+/// if the object is reached for the first time and needs to be scanned.
+fn sanity_trace_object<VM: VMBinding>(
+    mmtk: &'static MMTK<VM>,
+    object: ObjectReference,
+    from: ReachedFrom<VM::VMEdge>,
+) -> bool {
+    let mut sanity_checker = mmtk.sanity_checker.lock().unwrap();
+    if !sanity_checker.record(object, from) {
+        return false;
+    }
+    if let Err(reason) = verify_object(mmtk, object) {
+        panic!(
+            "Invalid reference {}: {}\nPath from root:\n{}",
+            object,
+            reason,
+            sanity_checker.format_root_path(object, from)
+        );
+    }
+    trace!("Sanity mark object {}", object);
+    true
 }
 
 pub struct ScheduleSanityGC<P: Plan> {
@@ -153,9 +302,11 @@ impl<P: Plan> GCWork<P::VM> for SanityRelease<P> {
     }
 }
 
-// #[derive(Default)]
+/// The `ProcessEdgesWork` for the root edges and root nodes of the sanity GC.
 pub struct SanityGCProcessEdges<VM: VMBinding> {
     base: ProcessEdgesBase<VM>,
+    /// The root edge being processed, or `None` if we are tracing root nodes.
+    current_edge: Option<VM::VMEdge>,
 }
 
 impl<VM: VMBinding> Deref for SanityGCProcessEdges<VM> {
@@ -173,7 +324,7 @@ impl<VM: VMBinding> DerefMut for SanityGCProcessEdges<VM> {
 
 impl<VM: VMBinding> ProcessEdgesWork for SanityGCProcessEdges<VM> {
     type VM = VM;
-    type ScanObjectsWorkType = ScanObjects<Self>;
+    type ScanObjectsWorkType = SanityScanObjects<VM>;
 
     const OVERWRITE_REFERENCE: bool = false;
     fn new(
@@ -184,47 +335,97 @@ impl<VM: VMBinding> ProcessEdgesWork for SanityGCProcessEdges<VM> {
     ) -> Self {
         Self {
             base: ProcessEdgesBase::new(edges, roots, mmtk, bucket),
-            // ..Default::default()
+            current_edge: None,
         }
     }
 
+    fn process_edge(&mut self, slot: EdgeOf<Self>) {
+        let Some(object) = slot.load() else {
+            // Skip slots that are not holding an object reference.
+            return;
+        };
+        self.current_edge = Some(slot);
+        self.trace_object(object);
+        self.current_edge = None;
+    }
+
     fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
-        let mut sanity_checker = self.mmtk().sanity_checker.lock().unwrap();
-        if !sanity_checker.refs.contains(&object) {
-            // FIXME steveb consider VM-specific integrity check on reference.
-            assert!(object.is_sane::<VM>(), "Invalid reference {:?}", object);
-
-            // Let plan check object
-            assert!(
-                self.mmtk().get_plan().sanity_check_object(object),
-                "Invalid reference {:?}",
-                object
-            );
-
-            // Let VM check object
-            assert!(
-                VM::VMObjectModel::is_object_sane(object),
-                "Invalid reference {:?}",
-                object
-            );
-
-            // Object is not "marked"
-            sanity_checker.refs.insert(object); // "Mark" it
-            trace!("Sanity mark object {}", object);
+        debug_assert!(self.roots, "SanityGCProcessEdges only processes roots");
+        let from = match self.current_edge {
+            Some(edge) => ReachedFrom::RootEdge(edge),
+            None => ReachedFrom::RootNode,
+        };
+        if sanity_trace_object(self.mmtk(), object, from) {
             self.nodes.enqueue(object);
         }
+        object
+    }
+
+    fn create_scan_work(&self, nodes: Vec<ObjectReference>) -> Self::ScanObjectsWorkType {
+        SanityScanObjects::new(nodes)
+    }
+}
+
+/// Scan objects for the sanity GC, and trace the objects they refer to. Unlike
+/// [`ScanObjects`], this records each scanned object as the parent of the objects it reaches, so
+/// that we can report the path from a root if an object is found not sane.
+pub struct SanityScanObjects<VM: VMBinding> {
+    buffer: Vec<ObjectReference>,
+    phantom: PhantomData<VM>,
+}
 
-        // If the valid object (VO) bit metadata is enabled, all live objects should have the VO
-        // bit set when sanity GC starts.
-        #[cfg(feature = "vo_bit")]
-        if !crate::util::metadata::vo_bit::is_vo_bit_set::<VM>(object) {
-            panic!("VO bit is not set: {}", object);
+impl<VM: VMBinding> SanityScanObjects<VM> {
+    pub fn new(buffer: Vec<ObjectReference>) -> Self {
+        Self {
+            buffer,
+            phantom: PhantomData,
         }
+    }
+}
 
-        object
+impl<VM: VMBinding> ScanObjectsWork<VM> for SanityScanObjects<VM> {
+    type E = SanityGCProcessEdges<VM>;
+
+    fn get_bucket(&self) -> WorkBucketStage {
+        WorkBucketStage::Closure
     }
 
-    fn create_scan_work(&self, nodes: Vec<ObjectReference>) -> Self::ScanObjectsWorkType {
-        ScanObjects::<Self>::new(nodes, false, WorkBucketStage::Closure)
+    fn post_scan_object(&self, _object: ObjectReference) {
+        // Do nothing.
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for SanityScanObjects<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        trace!("SanityScanObjects");
+        let tls = worker.tls;
+        let mut new_objects = vec![];
+        for parent in self.buffer.iter().copied() {
+            if VM::VMScanning::support_edge_enqueuing(tls, parent) {
+                VM::VMScanning::scan_object(tls, parent, &mut |edge: VM::VMEdge| {
+                    if let Some(object) = edge.load() {
+                        let from = ReachedFrom::Object {
+                            parent,
+                            edge: Some(edge),
+                        };
+                        if sanity_trace_object(mmtk, object, from) {
+                            new_objects.push(object);
+                        }
+                    }
+                });
+            } else {
+                VM::VMScanning::scan_object_and_trace_edges(tls, parent, &mut |object| {
+                    let from = ReachedFrom::Object { parent, edge: None };
+                    if sanity_trace_object(mmtk, object, from) {
+                        new_objects.push(object);
+                    }
+                    object
+                });
+            }
+        }
+        for objects in new_objects.chunks(SanityGCProcessEdges::<VM>::CAPACITY) {
+            worker.add_work(self.get_bucket(), SanityScanObjects::new(objects.to_vec()));
+        }
+        trace!("SanityScanObjects End");
     }
 }
@@ -1,4 +1,6 @@
 use crate::mmtk::MMTK;
+use crate::util::heap::vm_layout::vm_layout;
+use crate::util::memory::{self, MmapStrategy};
 use crate::util::options::Options;
 use crate::util::statistics::counter::*;
 use crate::util::statistics::Timer;
@@ -189,7 +191,7 @@ impl Stats {
         println!(
             "============================ MMTk Statistics Totals ============================"
         );
-        let scheduler_stat = mmtk.scheduler.statistics();
+        let scheduler_stat = Self::extra_statistics(mmtk);
         self.print_column_names(&scheduler_stat);
         print!("{}\t", self.get_phase() / 2);
         let counter = self.counters.lock().unwrap();
@@ -214,6 +216,28 @@ impl Stats {
         println!("------------------------------ End MMTk Statistics -----------------------------")
     }
 
+    /// The statistics printed after the counters: the scheduler statistics, and the huge page
+    /// coverage of the heap if the heap uses huge pages.
+    fn extra_statistics<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> HashMap<String, String> {
+        let mut stat = mmtk.scheduler.statistics();
+        if MmapStrategy::from_options(&mmtk.options).uses_huge_pages() {
+            let layout = vm_layout();
+            if let Some(coverage) =
+                memory::get_huge_page_coverage(layout.heap_start, layout.heap_end)
+            {
+                stat.insert(
+                    "hugepage.bytes".to_string(),
+                    coverage.huge_page_bytes.to_string(),
+                );
+                stat.insert(
+                    "hugepage.coverage".to_string(),
+                    format!("{:.3}", coverage.ratio()),
+                );
+            }
+        }
+        stat
+    }
+
     pub fn print_column_names(&self, scheduler_stat: &HashMap<String, String>) {
         print!("GC\t");
         let counter = self.counters.lock().unwrap();
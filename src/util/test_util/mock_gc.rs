@@ -0,0 +1,231 @@
+//! Run real GCs with `MockVM`.
+//!
+//! [`MockGC`] creates an MMTk instance with one mutator, and sets up `MockVM` so that GC workers
+//! run in their own threads, the mutator is 'stopped' while the test thread waits for a GC, and
+//! the root edges given by the test are reported to MMTk.  A panic in a GC worker is resumed in
+//! the test thread.  The GC workers can be joined after `MMTK::prepare_to_destroy`.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::mock_method::*;
+use super::mock_vm::*;
+use crate::util::retention_path::RetentionPath;
+use crate::util::{
+    Address, ObjectReference, OpaquePointer, VMMutatorThread, VMThread, VMWorkerThread,
+};
+use crate::vm::GCThreadContext;
+use crate::{memory_manager, MMTKBuilder, Mutator, UserCollectionKind, MMTK};
+
+// Don't block the CI if a GC does not finish.
+const GC_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct GCStatus {
+    /// The number of GCs that have resumed the mutators.
+    finished_gcs: usize,
+    /// The payload of the first panic in a GC worker.  Other workers may panic afterwards, for
+    /// example, on a mutex poisoned by the first panic.
+    worker_panic: Option<Box<dyn Any + Send>>,
+    /// The GC workers that have not been joined.
+    workers: Vec<JoinHandle<()>>,
+    /// The number of GC workers that have returned from `start_worker`.
+    exited_workers: usize,
+}
+
+lazy_static! {
+    static ref ROOTS: Mutex<Vec<Address>> = Mutex::default();
+    static ref STATUS: Mutex<GCStatus> = Mutex::default();
+    static ref STATUS_CHANGED: Condvar = Condvar::new();
+}
+
+/// A `MockAny` that ignores its arguments, and returns a fixed value.
+struct IgnoreArgs<R: Clone>(R);
+
+impl<R: Clone + 'static> MockAny for IgnoreArgs<R> {
+    fn call_any(&mut self, _args: Box<dyn Any>) -> Box<dyn Any> {
+        Box::new(self.0.clone())
+    }
+}
+
+/// An MMTk instance with one mutator that can run GCs in mock tests.
+pub struct MockGC {
+    pub mmtk: &'static MMTK<MockVM>,
+    mutator: *mut Mutator<MockVM>,
+}
+
+impl MockGC {
+    /// Create an MMTk instance, bind a mutator, and start the GC workers.  The mock methods that
+    /// are needed to run a GC are set on `mock_vm`, and `mock_vm` is installed as the `MockVM`.
+    /// Other methods that the GC calls, such as `get_object_size` and `scan_object`, are up to the
+    /// test.  Immix GCs need the feature `mock_test_side_metadata`, as Immix does not support mark
+    /// bits in the header.
+    pub fn new(builder: &MMTKBuilder, mock_vm: MockVM) -> Self {
+        let mmtk: &'static MMTK<MockVM> = Box::leak(memory_manager::mmtk_init(builder));
+        let mutator = Box::into_raw(memory_manager::bind_mutator(
+            mmtk,
+            VMMutatorThread(VMThread::UNINITIALIZED),
+        ));
+        // Mock closures need to be `Send` and `Sync`.
+        let mutator_addr = mutator as usize;
+        let the_mutator = move || unsafe { &mut *(mutator_addr as *mut Mutator<MockVM>) };
+
+        let mock_vm = MockVM {
+            number_of_mutators: MockMethod::new_fixed(Box::new(|_| 1)),
+            mutator: MockMethod::new_fixed(Box::new(move |_| the_mutator())),
+            mutators: MockMethod::new_fixed(Box::new(move |_| {
+                Box::new(std::iter::once(the_mutator()))
+            })),
+            stop_all_mutators: MockMethod::new_fixed(Box::new(move |(_, mut visitor)| {
+                visitor(the_mutator())
+            })),
+            resume_mutators: MockMethod::new_fixed(Box::new(|_| {
+                STATUS.lock().unwrap().finished_gcs += 1;
+                STATUS_CHANGED.notify_all();
+            })),
+            // The test thread waits for the GC in `run_gc`.
+            block_for_gc: MockMethod::new_default(),
+            spawn_gc_thread: MockMethod::new_fixed(Box::new(move |(_, context)| {
+                let GCThreadContext::Worker(worker) = context;
+                let join_handle = std::thread::spawn(move || {
+                    // A GC worker needs a non-null TLS.  Any distinct value works for `MockVM`.
+                    let tls = VMWorkerThread(VMThread(OpaquePointer::from_address(unsafe {
+                        Address::from_usize(worker.ordinal + 1)
+                    })));
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        memory_manager::start_worker(mmtk, tls, worker)
+                    }));
+                    let mut status = STATUS.lock().unwrap();
+                    status.exited_workers += 1;
+                    if let Err(payload) = result {
+                        status.worker_panic.get_or_insert(payload);
+                    }
+                    STATUS_CHANGED.notify_all();
+                });
+                STATUS.lock().unwrap().workers.push(join_handle);
+            })),
+            scan_roots_in_mutator_thread: MockMethod::new_default(),
+            scan_vm_specific_roots: MockMethod::new_fixed(Box::new(|(_, factory)| {
+                factory.create_process_edge_roots_work(ROOTS.lock().unwrap().clone());
+            })),
+            notify_initial_thread_scan_complete: MockMethod::new_default(),
+            // MarkCompact scans the roots again to update them.
+            prepare_for_roots_re_scanning: MockMethod::new_default(),
+            process_weak_refs: Box::new(IgnoreArgs(false)),
+            forward_weak_refs: Box::new(IgnoreArgs(())),
+            ..mock_vm
+        };
+        write_mockvm(move |mock_vm_ref| *mock_vm_ref = mock_vm);
+
+        memory_manager::initialize_collection(mmtk, VMThread::UNINITIALIZED);
+        MockGC { mmtk, mutator }
+    }
+
+    /// The mutator.  GCs only access it while the test thread waits in [`MockGC::run_gc`].
+    pub fn mutator(&mut self) -> &mut Mutator<MockVM> {
+        unsafe { &mut *self.mutator }
+    }
+
+    /// Set the root edges that are reported in `Scanning::scan_vm_specific_roots`.
+    pub fn set_roots(&self, roots: Vec<Address>) {
+        *ROOTS.lock().unwrap() = roots;
+    }
+
+    /// The number of GCs that have finished, including the GCs triggered by allocation.
+    pub fn finished_gcs(&self) -> usize {
+        STATUS.lock().unwrap().finished_gcs
+    }
+
+    /// Request a GC of the given kind, and wait for it to finish.  If a GC worker panics, the
+    /// panic is resumed in the current thread.
+    pub fn run_gc(&mut self, kind: UserCollectionKind) {
+        let finished_gcs = STATUS.lock().unwrap().finished_gcs;
+        memory_manager::handle_user_collection_request(
+            self.mmtk,
+            VMMutatorThread(VMThread::UNINITIALIZED),
+            kind,
+        );
+        let status = STATUS.lock().unwrap();
+        let (mut status, timeout_result) = STATUS_CHANGED
+            .wait_timeout_while(status, GC_TIMEOUT, |status| {
+                status.finished_gcs == finished_gcs && status.worker_panic.is_none()
+            })
+            .unwrap();
+        if let Some(payload) = status.worker_panic.take() {
+            drop(status);
+            panic::resume_unwind(payload);
+        }
+        assert!(!timeout_result.timed_out(), "The GC did not finish in time");
+    }
+
+    /// Wait for the GC workers to exit after `MMTK::prepare_to_destroy`, join them, and return the
+    /// number of joined workers.  If a GC worker panics, the panic is resumed in the current thread.
+    pub fn join_gc_workers(&mut self) -> usize {
+        let status = STATUS.lock().unwrap();
+        let (mut status, timeout_result) = STATUS_CHANGED
+            .wait_timeout_while(status, GC_TIMEOUT, |status| {
+                status.exited_workers < status.workers.len() && status.worker_panic.is_none()
+            })
+            .unwrap();
+        if let Some(payload) = status.worker_panic.take() {
+            drop(status);
+            panic::resume_unwind(payload);
+        }
+        assert!(
+            !timeout_result.timed_out(),
+            "The GC workers did not exit in time"
+        );
+        let workers = std::mem::take(&mut status.workers);
+        status.exited_workers = 0;
+        drop(status);
+        let joined = workers.len();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        joined
+    }
+
+    /// Find the retention paths to `object`, and wait for the query to finish.  The query is made
+    /// in another thread, because `find_retention_paths` keeps requesting until the query is done
+    /// when `block_for_gc` returns immediately.  If a GC worker panics, the panic is resumed in
+    /// the current thread.
+    pub fn find_retention_paths(
+        &mut self,
+        object: ObjectReference,
+        max_paths: usize,
+    ) -> Vec<RetentionPath<Address>> {
+        let mmtk = self.mmtk;
+        let query = std::thread::spawn(move || {
+            memory_manager::find_retention_paths(
+                mmtk,
+                VMMutatorThread(VMThread::UNINITIALIZED),
+                object,
+                max_paths,
+            )
+        });
+        let start = Instant::now();
+        while !query.is_finished() {
+            let mut status = STATUS.lock().unwrap();
+            if let Some(payload) = status.worker_panic.take() {
+                drop(status);
+                panic::resume_unwind(payload);
+            }
+            assert!(
+                start.elapsed() < GC_TIMEOUT,
+                "The query did not finish in time"
+            );
+            drop(
+                STATUS_CHANGED
+                    .wait_timeout(status, Duration::from_millis(10))
+                    .unwrap(),
+            );
+        }
+        query
+            .join()
+            .unwrap()
+            .expect("Another retention path query is in progress")
+    }
+}
@@ -2,7 +2,6 @@
 #![allow(clippy::type_complexity)]
 
 use crate::plan::ObjectQueue;
-use crate::scheduler::gc_work::ProcessEdgesWorkRootsWorkFactory;
 use crate::scheduler::gc_work::ProcessEdgesWorkTracerContext;
 use crate::scheduler::gc_work::SFTProcessEdges;
 use crate::scheduler::*;
@@ -46,6 +45,9 @@ macro_rules! lifetime {
     ($e: expr) => {
         unsafe { std::mem::transmute($e) }
     };
+    ($e: expr, $from: ty => $to: ty) => {
+        unsafe { std::mem::transmute::<$from, $to>($e) }
+    };
 }
 
 /// Call `MockMethod`.
@@ -114,6 +116,26 @@ pub fn default_setup() -> MockVM {
 /// No extra clean up after the test.
 pub fn no_cleanup() {}
 
+/// [`RootsWorkFactory`] is not object safe, so the mock methods for scanning roots take the
+/// factory as this trait object instead.
+pub trait MockRootsWorkFactory {
+    fn create_process_edge_roots_work(&mut self, edges: Vec<Address>);
+    fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>);
+    fn create_process_tpinning_roots_work(&mut self, nodes: Vec<ObjectReference>);
+}
+
+impl<F: RootsWorkFactory<Address>> MockRootsWorkFactory for F {
+    fn create_process_edge_roots_work(&mut self, edges: Vec<Address>) {
+        RootsWorkFactory::create_process_edge_roots_work(self, edges)
+    }
+    fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        RootsWorkFactory::create_process_pinning_roots_work(self, nodes)
+    }
+    fn create_process_tpinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        RootsWorkFactory::create_process_tpinning_roots_work(self, nodes)
+    }
+}
+
 /// A struct that allows us to mock the behavior of a `VMBinding` and the VM traits for testing.
 /// For simplicity, we implement `VMBinding` as well as `ActivePlan`, `Collection`,
 /// `ObjectModel`, `ReferenceGlue`, `Scanning` on the `MockVM` type, and forward each
@@ -155,7 +177,8 @@ pub fn no_cleanup() {}
 /// `fn<Q: ObjectQueue>(&mut Q, ObjectReference, &mut GCWorker<VM>) -> ObjectReference`,
 /// we can mock `&mut Q` as `&mut dyn ObjectQueue`, and use
 /// `MockMethod<(&'static mut dyn ObjectQueue, ObjectReference, &'static mut GCWorker<MockVM>), ObjectReference>`
-/// for the method.
+/// for the method. If the trait is not object safe, we may wrap it in an object-safe trait,
+/// like [`MockRootsWorkFactory`] for the `RootsWorkFactory` in the roots scanning methods.
 ///
 /// ### Use `MockAny`
 ///
@@ -224,6 +247,7 @@ pub struct MockVM {
     pub get_type_descriptor: MockMethod<(), &'static [i8]>,
     pub get_object_reference_when_copied_to:
         MockMethod<(ObjectReference, Address), ObjectReference>,
+    pub on_object_remapped: MockMethod<(ObjectReference, ObjectReference), ()>,
     pub ref_to_object_start: MockMethod<ObjectReference, Address>,
     pub ref_to_header: MockMethod<ObjectReference, Address>,
     pub ref_to_address: MockMethod<ObjectReference, Address>,
@@ -252,8 +276,16 @@ pub struct MockVM {
         ),
         (),
     >,
-    pub scan_roots_in_mutator_thread: Box<dyn MockAny>,
-    pub scan_vm_specific_roots: Box<dyn MockAny>,
+    pub scan_roots_in_mutator_thread: MockMethod<
+        (
+            VMWorkerThread,
+            &'static mut Mutator<MockVM>,
+            &'static mut dyn MockRootsWorkFactory,
+        ),
+        (),
+    >,
+    pub scan_vm_specific_roots:
+        MockMethod<(VMWorkerThread, &'static mut dyn MockRootsWorkFactory), ()>,
     pub notify_initial_thread_scan_complete: MockMethod<(bool, VMWorkerThread), ()>,
     pub supports_return_barrier: MockMethod<(), bool>,
     pub prepare_for_roots_re_scanning: MockMethod<(), ()>,
@@ -295,6 +327,7 @@ impl Default for MockVM {
             get_object_align_offset_when_copied: MockMethod::new_fixed(Box::new(|_| 0)),
             get_type_descriptor: MockMethod::new_unimplemented(),
             get_object_reference_when_copied_to: MockMethod::new_unimplemented(),
+            on_object_remapped: MockMethod::new_default(),
             ref_to_object_start: MockMethod::new_fixed(Box::new(|object| {
                 object.to_raw_address().sub(DEFAULT_OBJECT_REF_OFFSET)
             })),
@@ -315,38 +348,8 @@ impl Default for MockVM {
             support_edge_enqueuing: MockMethod::new_fixed(Box::new(|_| true)),
             scan_object: MockMethod::new_unimplemented(),
             scan_object_and_trace_edges: MockMethod::new_unimplemented(),
-            // We instantiate a `MockMethod` with the arguments as ProcessEdgesWorkRootsWorkFactory<..., SFTProcessEdges<MockVM>, ...>,
-            // thus the mock method expects the actual call arguments to match the type.
-            // In most cases, this won't work and this `MockMethod` is just a place holder. It is
-            // fine as long as the method is not actually called.
-            // If the user will need this method, and would like to mock the method in their particular test,
-            // they are expected to provide their own
-            // `MockMethod` that matches the argument types they will pass for the test case.
-            // See the documents on the section about `MockAny` on the `MockVM` type.
-            scan_roots_in_mutator_thread: Box::new(MockMethod::<
-                (
-                    VMWorkerThread,
-                    &'static mut Mutator<MockVM>,
-                    ProcessEdgesWorkRootsWorkFactory<
-                        MockVM,
-                        SFTProcessEdges<MockVM>,
-                        SFTProcessEdges<MockVM>,
-                    >,
-                ),
-                (),
-            >::new_unimplemented()),
-            // Same here: the `MockMethod` is just a place holder. See the above comments.
-            scan_vm_specific_roots: Box::new(MockMethod::<
-                (
-                    VMWorkerThread,
-                    ProcessEdgesWorkRootsWorkFactory<
-                        MockVM,
-                        SFTProcessEdges<MockVM>,
-                        SFTProcessEdges<MockVM>,
-                    >,
-                ),
-                (),
-            >::new_unimplemented()),
+            scan_roots_in_mutator_thread: MockMethod::new_unimplemented(),
+            scan_vm_specific_roots: MockMethod::new_unimplemented(),
             notify_initial_thread_scan_complete: MockMethod::new_unimplemented(),
             supports_return_barrier: MockMethod::new_unimplemented(),
             prepare_for_roots_re_scanning: MockMethod::new_unimplemented(),
@@ -467,14 +470,25 @@ impl crate::vm::Collection<MockVM> for MockVM {
 }
 
 impl crate::vm::ObjectModel<MockVM> for MockVM {
+    #[cfg(not(feature = "mock_test_side_metadata"))]
     const GLOBAL_LOG_BIT_SPEC: VMGlobalLogBitSpec = VMGlobalLogBitSpec::in_header(0);
+    #[cfg(feature = "mock_test_side_metadata")]
+    const GLOBAL_LOG_BIT_SPEC: VMGlobalLogBitSpec = VMGlobalLogBitSpec::side_first();
     const LOCAL_FORWARDING_POINTER_SPEC: VMLocalForwardingPointerSpec =
         VMLocalForwardingPointerSpec::in_header(0);
     const LOCAL_FORWARDING_BITS_SPEC: VMLocalForwardingBitsSpec =
         VMLocalForwardingBitsSpec::in_header(0);
+    #[cfg(not(feature = "mock_test_side_metadata"))]
     const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec = VMLocalMarkBitSpec::in_header(0);
+    #[cfg(feature = "mock_test_side_metadata")]
+    const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec =
+        VMLocalMarkBitSpec::side_after(Self::LOCAL_LOS_MARK_NURSERY_SPEC.as_spec());
+    #[cfg(not(feature = "mock_test_side_metadata"))]
     const LOCAL_LOS_MARK_NURSERY_SPEC: VMLocalLOSMarkNurserySpec =
         VMLocalLOSMarkNurserySpec::in_header(0);
+    #[cfg(feature = "mock_test_side_metadata")]
+    const LOCAL_LOS_MARK_NURSERY_SPEC: VMLocalLOSMarkNurserySpec =
+        VMLocalLOSMarkNurserySpec::side_first();
 
     #[cfg(feature = "object_pinning")]
     const LOCAL_PINNING_BIT_SPEC: VMLocalPinningBitSpec = VMLocalPinningBitSpec::in_header(0);
@@ -518,6 +532,10 @@ impl crate::vm::ObjectModel<MockVM> for MockVM {
         mock!(get_object_reference_when_copied_to(from, to))
     }
 
+    fn on_object_remapped(from: ObjectReference, to: ObjectReference) {
+        mock!(on_object_remapped(from, to))
+    }
+
     fn ref_to_object_start(object: ObjectReference) -> Address {
         mock!(ref_to_object_start(object))
     }
@@ -566,11 +584,18 @@ impl crate::vm::Scanning<MockVM> for MockVM {
         object: ObjectReference,
         edge_visitor: &mut EV,
     ) {
+        // The edge visitor may call other `MockVM` methods, for example, in a sanity GC.  Collect
+        // the edges, and visit them after the mock method returns and releases the lock.
+        let mut edges = vec![];
+        let mut collect = |edge| edges.push(edge);
         mock!(scan_object(
             tls,
             object,
-            lifetime!(edge_visitor as &mut dyn EdgeVisitor<<MockVM as VMBinding>::VMEdge>)
-        ))
+            lifetime!(&mut collect as &mut dyn EdgeVisitor<<MockVM as VMBinding>::VMEdge>)
+        ));
+        for edge in edges {
+            edge_visitor.visit_edge(edge);
+        }
     }
     fn scan_object_and_trace_edges<OT: ObjectTracer>(
         tls: VMWorkerThread,
@@ -586,19 +611,28 @@ impl crate::vm::Scanning<MockVM> for MockVM {
     fn scan_roots_in_mutator_thread(
         tls: VMWorkerThread,
         mutator: &'static mut Mutator<Self>,
-        factory: impl RootsWorkFactory<<MockVM as VMBinding>::VMEdge>,
+        mut factory: impl RootsWorkFactory<<MockVM as VMBinding>::VMEdge>,
     ) {
-        mock_any!(scan_roots_in_mutator_thread(
+        mock!(scan_roots_in_mutator_thread(
             tls,
             mutator,
-            Box::new(factory)
+            lifetime!(
+                &mut factory,
+                &mut dyn MockRootsWorkFactory => &'static mut dyn MockRootsWorkFactory
+            )
         ))
     }
     fn scan_vm_specific_roots(
         tls: VMWorkerThread,
-        factory: impl RootsWorkFactory<<MockVM as VMBinding>::VMEdge>,
+        mut factory: impl RootsWorkFactory<<MockVM as VMBinding>::VMEdge>,
     ) {
-        mock_any!(scan_vm_specific_roots(tls, Box::new(factory)))
+        mock!(scan_vm_specific_roots(
+            tls,
+            lifetime!(
+                &mut factory,
+                &mut dyn MockRootsWorkFactory => &'static mut dyn MockRootsWorkFactory
+            )
+        ))
     }
     fn notify_initial_thread_scan_complete(partial_scan: bool, tls: VMWorkerThread) {
         mock!(notify_initial_thread_scan_complete(partial_scan, tls))
@@ -0,0 +1,260 @@
+//! An in-process tracer that records GC timeline events, and writes them in the Chrome Trace Event
+//! format which can be loaded in Perfetto UI (<https://ui.perfetto.dev/>) or `chrome://tracing`.
+//!
+//! It records the same events as the USDT tracepoints used by the scripts in `tools/tracing/timeline`,
+//! but it does not need root privilege or bpftrace.  It is enabled by the `timeline_output` option.
+//!
+//! Each GC worker records events into its own ring buffer so that recording work packets does not
+//! contend on a lock.  When a ring buffer is full, its oldest events are dropped.
+
+use crate::util::options::Options;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The kind of an event, i.e. the `ph` field in the Chrome Trace Event format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    /// An event with a duration.
+    Complete(Duration),
+    /// An event without a duration.
+    Instant,
+}
+
+#[derive(Clone, Debug)]
+struct TimelineEvent {
+    name: &'static str,
+    phase: Phase,
+    /// The time since the timeline is created.
+    ts: Duration,
+    arg: Option<(&'static str, String)>,
+}
+
+/// The GC timeline.  It exists if the `timeline_output` option is set.
+pub struct Timeline {
+    /// The path of the output file.
+    output: String,
+    /// Timestamps are relative to this time.
+    start: Instant,
+    /// The maximum number of events in each track.
+    capacity: usize,
+    /// Track 0 is for GC events.  Track `i + 1` is for the GC worker with ordinal `i`.
+    tracks: Vec<Mutex<VecDeque<TimelineEvent>>>,
+}
+
+impl Timeline {
+    /// Create a timeline for `num_workers` GC workers.  Return `None` if `timeline_output` is not set.
+    pub fn new(options: &Options, num_workers: usize) -> Option<Self> {
+        if options.timeline_output.is_empty() {
+            return None;
+        }
+        Some(Self::with_capacity(
+            options.timeline_output.to_string(),
+            *options.timeline_buffer_size,
+            num_workers,
+        ))
+    }
+
+    fn with_capacity(output: String, capacity: usize, num_workers: usize) -> Self {
+        Self {
+            output,
+            start: Instant::now(),
+            capacity,
+            tracks: (0..=num_workers)
+                .map(|_| Mutex::new(VecDeque::with_capacity(capacity)))
+                .collect(),
+        }
+    }
+
+    fn record(&self, track: usize, event: TimelineEvent) {
+        let mut events = self.tracks[track].lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    fn since_start(&self, time: Instant) -> Duration {
+        time.saturating_duration_since(self.start)
+    }
+
+    /// Record a GC, from `start` to now.
+    pub fn record_gc(&self, start: Instant) {
+        let ts = self.since_start(start);
+        let end = self.since_start(Instant::now());
+        self.record(
+            0,
+            TimelineEvent {
+                name: "GC",
+                phase: Phase::Complete(end.saturating_sub(ts)),
+                ts,
+                arg: None,
+            },
+        );
+    }
+
+    /// Record a work packet executed by a GC worker, from `start` to now.
+    pub fn record_work_packet(&self, ordinal: usize, name: &'static str, start: Instant) {
+        let ts = self.since_start(start);
+        let end = self.since_start(Instant::now());
+        self.record(
+            ordinal + 1,
+            TimelineEvent {
+                name,
+                phase: Phase::Complete(end.saturating_sub(ts)),
+                ts,
+                arg: None,
+            },
+        );
+    }
+
+    /// Record an event without duration on the track of a GC worker, such as opening a bucket.
+    pub fn record_instant(
+        &self,
+        ordinal: usize,
+        name: &'static str,
+        arg: Option<(&'static str, String)>,
+    ) {
+        let ts = self.since_start(Instant::now());
+        self.record(
+            ordinal + 1,
+            TimelineEvent {
+                name,
+                phase: Phase::Instant,
+                ts,
+                arg,
+            },
+        );
+    }
+
+    /// Write the recorded events to the output file.
+    pub fn write(&self) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&self.output)?);
+        self.write_to(&mut file)?;
+        file.flush()?;
+        info!("GC timeline written to {}", self.output);
+        Ok(())
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let pid = std::process::id();
+        write!(out, "{{\"traceEvents\":[")?;
+        for track in 0..self.tracks.len() {
+            let name = if track == 0 {
+                "GC".to_string()
+            } else {
+                format!("GC worker {}", track - 1)
+            };
+            if track != 0 {
+                write!(out, ",")?;
+            }
+            write!(
+                out,
+                "\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":{},\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+                pid, track, name
+            )?;
+        }
+        for (track, events) in self.tracks.iter().enumerate() {
+            for event in events.lock().unwrap().iter() {
+                write!(
+                    out,
+                    ",\n{{\"name\":\"{}\",\"pid\":{},\"tid\":{},\"ts\":{}",
+                    escape(event.name),
+                    pid,
+                    track,
+                    micros(event.ts)
+                )?;
+                match event.phase {
+                    Phase::Complete(dur) => write!(out, ",\"ph\":\"X\",\"dur\":{}", micros(dur))?,
+                    Phase::Instant => write!(out, ",\"ph\":\"i\",\"s\":\"t\"")?,
+                }
+                if let Some((key, value)) = &event.arg {
+                    write!(out, ",\"args\":{{\"{}\":\"{}\"}}", key, escape(value))?;
+                }
+                write!(out, "}}")?;
+            }
+        }
+        writeln!(out, "\n]}}")
+    }
+}
+
+/// Format a duration in microseconds, which is the time unit of the Chrome Trace Event format.
+fn micros(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    format!("{}.{:03}", nanos / 1000, nanos % 1000)
+}
+
+/// Escape a string for JSON.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_to_string(timeline: &Timeline) -> String {
+        let mut out = vec![];
+        timeline.write_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let timeline = Timeline::with_capacity(String::new(), 2, 1);
+        for name in ["A", "B", "C"] {
+            timeline.record_work_packet(0, name, Instant::now());
+        }
+        let output = write_to_string(&timeline);
+        // The oldest event is dropped.
+        assert!(!output.contains("\"name\":\"A\""));
+        assert!(output.contains("\"name\":\"B\",\"pid\""));
+        assert!(output.contains("\"name\":\"C\",\"pid\""));
+    }
+
+    #[test]
+    fn test_trace_events() {
+        let timeline = Timeline::with_capacity(String::new(), 16, 2);
+        let gc_start = Instant::now();
+        timeline.record_instant(1, "BUCKET_OPEN", Some(("stage", "Closure".to_string())));
+        timeline.record_work_packet(1, "mmtk::scheduler::gc_work::Prepare<\"P\">", gc_start);
+        timeline.record_gc(gc_start);
+        let output = write_to_string(&timeline);
+
+        assert!(output.starts_with("{\"traceEvents\":["));
+        assert!(output.trim_end().ends_with("]}"));
+        assert!(output.contains("\"tid\":0,\"args\":{\"name\":\"GC\"}"));
+        assert!(output.contains("\"tid\":2,\"args\":{\"name\":\"GC worker 1\"}"));
+        assert!(output.contains("\"name\":\"GC\",\"pid\""));
+        assert!(output.contains("\"ph\":\"i\",\"s\":\"t\",\"args\":{\"stage\":\"Closure\"}"));
+        assert!(output.contains("\"name\":\"mmtk::scheduler::gc_work::Prepare<\\\"P\\\">\""));
+        assert_eq!(output.matches("\"ph\":\"X\"").count(), 2);
+    }
+
+    #[test]
+    fn test_write_file() {
+        let path = std::env::temp_dir().join(format!("mmtk-timeline-{}.json", std::process::id()));
+        let timeline = Timeline::with_capacity(path.to_str().unwrap().to_string(), 16, 1);
+        timeline.record_gc(Instant::now());
+        timeline.write().unwrap();
+        let output = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(output, write_to_string(&timeline));
+    }
+
+    #[test]
+    fn test_micros() {
+        assert_eq!(micros(Duration::from_nanos(1_234_567)), "1234.567");
+        assert_eq!(micros(Duration::from_nanos(5)), "0.005");
+    }
+}
@@ -59,6 +59,16 @@ impl TreadMill {
     }
 
     pub fn copy(&self, object: ObjectReference, is_in_nursery: bool) {
+        self.copy_to(object, object, is_in_nursery)
+    }
+
+    /// Like `copy`, but the object has been moved, and `new_object` is added to the to-space.
+    pub fn copy_to(
+        &self,
+        object: ObjectReference,
+        new_object: ObjectReference,
+        is_in_nursery: bool,
+    ) {
         if is_in_nursery {
             let mut guard = self.collect_nursery.lock().unwrap();
             debug_assert!(
@@ -76,7 +86,19 @@ impl TreadMill {
             );
             guard.remove(&object);
         }
-        self.to_space.lock().unwrap().insert(object);
+        self.to_space.lock().unwrap().insert(new_object);
+    }
+
+    /// Call `f` for each object in the treadmill.
+    pub fn for_each_object(&self, mut f: impl FnMut(ObjectReference)) {
+        for set in [
+            &self.from_space,
+            &self.to_space,
+            &self.collect_nursery,
+            &self.alloc_nursery,
+        ] {
+            set.lock().unwrap().iter().copied().for_each(&mut f);
+        }
     }
 
     pub fn is_to_space_empty(&self) -> bool {
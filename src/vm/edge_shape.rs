@@ -22,9 +22,10 @@ use crate::util::{Address, ObjectReference};
 ///
 /// For example:
 /// -   The VM uses compressed pointer (Compressed OOP in OpenJDK's terminology), where the heap
-///     size is limited, and a 64-bit pointer is stored in a 32-bit slot.
+///     size is limited, and a 64-bit pointer is stored in a 32-bit slot.  Such VMs can use the
+///     `CompressedEdge` we provide.
 /// -   The VM uses tagged pointer, where some bits of a word are used as metadata while the rest
-///     are used as pointer.
+///     are used as pointer.  Such VMs can use the `TaggedEdge` we provide.
 /// -   A field holds a pointer to the middle of an object (an object field, or an array element,
 ///     or some arbitrary offset) for some reasons.
 ///
@@ -154,6 +155,241 @@ fn a_simple_edge_should_have_the_same_size_as_a_pointer() {
     );
 }
 
+/// How object references are compressed into 32 bits in a [`CompressedEdge`].
+///
+/// A reference to the object at `addr` is compressed to `(addr - base) >> SHIFT`, and the
+/// compressed value 0 represents null.  Objects must be aligned to `1 << SHIFT` bytes, and the
+/// heap must fit in `4G << SHIFT` bytes above the base.  [`ZeroBasedCompression`] and
+/// [`VMLayoutCompression`] cover the common cases.  A VM that chooses its own base can implement
+/// this trait.
+pub trait PointerCompression: Copy + Send + Sync + Debug + PartialEq + Eq + Hash + 'static {
+    /// The number of low bits dropped when compressing a reference.
+    const SHIFT: usize;
+
+    /// The address that is compressed to 0.
+    fn base() -> Address;
+
+    /// Compress a reference.
+    #[inline(always)]
+    fn compress(object: ObjectReference) -> u32 {
+        let offset = object.to_raw_address() - Self::base();
+        debug_assert_eq!(
+            offset & ((1 << Self::SHIFT) - 1),
+            0,
+            "{} is not aligned to the compression shift",
+            object
+        );
+        debug_assert!(
+            offset >> Self::SHIFT <= u32::MAX as usize,
+            "{} is out of the range of compressed references",
+            object
+        );
+        (offset >> Self::SHIFT) as u32
+    }
+
+    /// Decompress a reference.  Return `None` for null.
+    #[inline(always)]
+    fn decompress(compressed: u32) -> Option<ObjectReference> {
+        if compressed == 0 {
+            None
+        } else {
+            ObjectReference::from_raw_address(Self::base() + ((compressed as usize) << Self::SHIFT))
+        }
+    }
+}
+
+/// Compression with a zero base.  The heap must end below `4G << SHIFT`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ZeroBasedCompression<const SHIFT: usize>;
+
+impl<const SHIFT: usize> PointerCompression for ZeroBasedCompression<SHIFT> {
+    const SHIFT: usize = SHIFT;
+
+    #[inline(always)]
+    fn base() -> Address {
+        Address::ZERO
+    }
+}
+
+/// Compression with the base chosen by the current VM layout (see
+/// [`crate::util::heap::vm_layout::VMLayout::compressed_heap_base`]).  It is zero-based if the heap
+/// ends below `4G << SHIFT`, and base-relative otherwise.  Use it with a layout created by
+/// [`crate::util::heap::vm_layout::VMLayout::new_compressed`].
+#[cfg(target_pointer_width = "64")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VMLayoutCompression<const SHIFT: usize>;
+
+#[cfg(target_pointer_width = "64")]
+impl<const SHIFT: usize> PointerCompression for VMLayoutCompression<SHIFT> {
+    const SHIFT: usize = SHIFT;
+
+    #[inline(always)]
+    fn base() -> Address {
+        crate::util::heap::vm_layout::vm_layout().compressed_heap_base(SHIFT)
+    }
+}
+
+/// An edge that holds an object reference compressed into a 32-bit slot.  The compression is
+/// defined by `C`.
+#[repr(transparent)]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct CompressedEdge<C: PointerCompression> {
+    slot_addr: *mut Atomic<u32>,
+    _compression: PhantomData<C>,
+}
+
+// Derive would add a `C: Copy` bound to `Clone` and `Copy`, which we do not need.
+impl<C: PointerCompression> Clone for CompressedEdge<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: PointerCompression> Copy for CompressedEdge<C> {}
+
+impl<C: PointerCompression> CompressedEdge<C> {
+    /// Create a compressed edge from the address of a 32-bit slot.
+    pub fn from_address(address: Address) -> Self {
+        Self {
+            slot_addr: address.to_mut_ptr(),
+            _compression: PhantomData,
+        }
+    }
+
+    /// Get the address of the slot.
+    pub fn as_address(&self) -> Address {
+        Address::from_mut_ptr(self.slot_addr)
+    }
+}
+
+unsafe impl<C: PointerCompression> Send for CompressedEdge<C> {}
+
+impl<C: PointerCompression> Edge for CompressedEdge<C> {
+    #[inline(always)]
+    fn load(&self) -> Option<ObjectReference> {
+        C::decompress(unsafe { (*self.slot_addr).load(atomic::Ordering::Relaxed) })
+    }
+
+    #[inline(always)]
+    fn store(&self, object: ObjectReference) {
+        unsafe { (*self.slot_addr).store(C::compress(object), atomic::Ordering::Relaxed) }
+    }
+}
+
+/// How object references are tagged in a [`TaggedEdge`], such as low-bit tags or NaN-boxing.
+///
+/// A tagged word may hold an object reference, or an immediate value such as a small integer.
+/// Implementations decode a word into an object reference, and re-encode an updated object
+/// reference while keeping the tag of the word.
+pub trait TagScheme: Copy + Send + Sync + Debug + PartialEq + Eq + Hash + 'static {
+    /// Decode a tagged word.  Return `None` if it is an immediate value or null.
+    fn decode(word: usize) -> Option<ObjectReference>;
+
+    /// Encode `object` into a word that replaces `old`, which holds an object reference.
+    fn encode(old: usize, object: ObjectReference) -> usize;
+}
+
+/// Every word is a reference with tag bits in `MASK`.  The tag bits are stripped when loading,
+/// and preserved when storing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PreservedTag<const MASK: usize>;
+
+impl<const MASK: usize> TagScheme for PreservedTag<MASK> {
+    #[inline(always)]
+    fn decode(word: usize) -> Option<ObjectReference> {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(word & !MASK) })
+    }
+
+    #[inline(always)]
+    fn encode(old: usize, object: ObjectReference) -> usize {
+        let addr = object.to_raw_address().as_usize();
+        debug_assert_eq!(addr & MASK, 0, "{} overlaps with the tag bits", object);
+        addr | (old & MASK)
+    }
+}
+
+/// A word is a reference if its bits in `MASK` equal `TAG`, and is an immediate value otherwise.
+/// For example, `PointerTag<0b1, 0b1>` matches a VM that tags small integers with 0 and heap
+/// objects with 1 in the lowest bit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PointerTag<const MASK: usize, const TAG: usize>;
+
+impl<const MASK: usize, const TAG: usize> TagScheme for PointerTag<MASK, TAG> {
+    #[inline(always)]
+    fn decode(word: usize) -> Option<ObjectReference> {
+        if word & MASK == TAG {
+            ObjectReference::from_raw_address(unsafe { Address::from_usize(word & !MASK) })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn encode(_old: usize, object: ObjectReference) -> usize {
+        let addr = object.to_raw_address().as_usize();
+        debug_assert_eq!(addr & MASK, 0, "{} overlaps with the tag bits", object);
+        addr | TAG
+    }
+}
+
+/// The bits that hold the NaN-boxing tag: the sign bit, the exponent, the quiet bit of a double,
+/// and one more bit, leaving 47 bits for pointers.
+#[cfg(target_pointer_width = "64")]
+pub const NAN_BOXING_TAG_MASK: usize = 0xFFFF_8000_0000_0000;
+
+/// NaN-boxing, where doubles and immediates share a 64-bit word with pointers, and a word is a
+/// reference if its upper 17 bits equal `TAG`.
+#[cfg(target_pointer_width = "64")]
+pub type NanBoxing<const TAG: usize> = PointerTag<NAN_BOXING_TAG_MASK, TAG>;
+
+/// An edge that holds a word-sized tagged value.  The tagging is defined by `T`.  Loading an
+/// immediate value returns `None`.
+#[repr(transparent)]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct TaggedEdge<T: TagScheme> {
+    slot_addr: *mut Atomic<usize>,
+    _scheme: PhantomData<T>,
+}
+
+impl<T: TagScheme> Clone for TaggedEdge<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: TagScheme> Copy for TaggedEdge<T> {}
+
+impl<T: TagScheme> TaggedEdge<T> {
+    /// Create a tagged edge from the address of a word-sized slot.
+    pub fn from_address(address: Address) -> Self {
+        Self {
+            slot_addr: address.to_mut_ptr(),
+            _scheme: PhantomData,
+        }
+    }
+
+    /// Get the address of the slot.
+    pub fn as_address(&self) -> Address {
+        Address::from_mut_ptr(self.slot_addr)
+    }
+}
+
+unsafe impl<T: TagScheme> Send for TaggedEdge<T> {}
+
+impl<T: TagScheme> Edge for TaggedEdge<T> {
+    #[inline(always)]
+    fn load(&self) -> Option<ObjectReference> {
+        T::decode(unsafe { (*self.slot_addr).load(atomic::Ordering::Relaxed) })
+    }
+
+    #[inline(always)]
+    fn store(&self, object: ObjectReference) {
+        let slot = unsafe { &*self.slot_addr };
+        let old = slot.load(atomic::Ordering::Relaxed);
+        slot.store(T::encode(old, object), atomic::Ordering::Relaxed)
+    }
+}
+
 /// A abstract memory slice represents a piece of **heap** memory.
 pub trait MemorySlice: Send + Debug + PartialEq + Eq + Clone + Hash {
     /// The associate type to define how to access edges from a memory slice.
@@ -276,6 +512,167 @@ impl<E: Edge> MemorySlice for UnimplementedMemorySlice<E> {
     }
 }
 
+/// A memory slice of 32-bit compressed slots, such as the elements of a reference array in a VM
+/// that compresses references.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct CompressedMemorySlice<C: PointerCompression> {
+    object: Option<ObjectReference>,
+    range: Range<Address>,
+    _compression: PhantomData<C>,
+}
+
+impl<C: PointerCompression> CompressedMemorySlice<C> {
+    /// Create a slice of the 32-bit slots in `range`.  `object` is the object that the slice
+    /// belongs to, if it is known.
+    pub fn new(object: Option<ObjectReference>, range: Range<Address>) -> Self {
+        debug_assert_eq!(
+            (range.end - range.start) % std::mem::size_of::<u32>(),
+            0,
+            "bytes are not a multiple of compressed slots"
+        );
+        Self {
+            object,
+            range,
+            _compression: PhantomData,
+        }
+    }
+}
+
+/// Iterate edges within a `CompressedMemorySlice`.
+pub struct CompressedMemorySliceIterator<C: PointerCompression> {
+    cursor: Address,
+    limit: Address,
+    _compression: PhantomData<C>,
+}
+
+impl<C: PointerCompression> Iterator for CompressedMemorySliceIterator<C> {
+    type Item = CompressedEdge<C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor >= self.limit {
+            None
+        } else {
+            let edge = CompressedEdge::from_address(self.cursor);
+            self.cursor += std::mem::size_of::<u32>();
+            Some(edge)
+        }
+    }
+}
+
+impl<C: PointerCompression> MemorySlice for CompressedMemorySlice<C> {
+    type Edge = CompressedEdge<C>;
+    type EdgeIterator = CompressedMemorySliceIterator<C>;
+
+    fn iter_edges(&self) -> Self::EdgeIterator {
+        CompressedMemorySliceIterator {
+            cursor: self.range.start,
+            limit: self.range.end,
+            _compression: PhantomData,
+        }
+    }
+
+    fn object(&self) -> Option<ObjectReference> {
+        self.object
+    }
+
+    fn start(&self) -> Address {
+        self.range.start
+    }
+
+    fn bytes(&self) -> usize {
+        self.range.end - self.range.start
+    }
+
+    fn copy(src: &Self, tgt: &Self) {
+        debug_assert_eq!(src.bytes(), tgt.bytes());
+        // Compressed references are copied as they are.
+        unsafe {
+            let slots = tgt.bytes() / std::mem::size_of::<u32>();
+            let src = src.start().to_ptr::<u32>();
+            let tgt = tgt.start().to_mut_ptr::<u32>();
+            std::ptr::copy(src, tgt, slots)
+        }
+    }
+}
+
+/// A memory slice of word-sized tagged slots.  Iterating the slice skips the slots that hold
+/// immediate values or null.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct TaggedMemorySlice<T: TagScheme> {
+    object: Option<ObjectReference>,
+    range: Range<Address>,
+    _scheme: PhantomData<T>,
+}
+
+impl<T: TagScheme> TaggedMemorySlice<T> {
+    /// Create a slice of the tagged slots in `range`.  `object` is the object that the slice
+    /// belongs to, if it is known.
+    pub fn new(object: Option<ObjectReference>, range: Range<Address>) -> Self {
+        debug_assert_eq!(
+            (range.end - range.start) & ((1 << LOG_BYTES_IN_ADDRESS) - 1),
+            0,
+            "bytes are not a multiple of words"
+        );
+        Self {
+            object,
+            range,
+            _scheme: PhantomData,
+        }
+    }
+}
+
+/// Iterate the edges that hold references within a `TaggedMemorySlice`.
+pub struct TaggedMemorySliceIterator<T: TagScheme> {
+    cursor: Address,
+    limit: Address,
+    _scheme: PhantomData<T>,
+}
+
+impl<T: TagScheme> Iterator for TaggedMemorySliceIterator<T> {
+    type Item = TaggedEdge<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.cursor < self.limit {
+            let edge = TaggedEdge::from_address(self.cursor);
+            self.cursor += BYTES_IN_ADDRESS;
+            if edge.load().is_some() {
+                return Some(edge);
+            }
+        }
+        None
+    }
+}
+
+impl<T: TagScheme> MemorySlice for TaggedMemorySlice<T> {
+    type Edge = TaggedEdge<T>;
+    type EdgeIterator = TaggedMemorySliceIterator<T>;
+
+    fn iter_edges(&self) -> Self::EdgeIterator {
+        TaggedMemorySliceIterator {
+            cursor: self.range.start,
+            limit: self.range.end,
+            _scheme: PhantomData,
+        }
+    }
+
+    fn object(&self) -> Option<ObjectReference> {
+        self.object
+    }
+
+    fn start(&self) -> Address {
+        self.range.start
+    }
+
+    fn bytes(&self) -> usize {
+        self.range.end - self.range.start
+    }
+
+    fn copy(src: &Self, tgt: &Self) {
+        // Tagged words, including immediate values, are copied as they are.
+        MemorySlice::copy(&src.range, &tgt.range)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,4 +696,68 @@ mod tests {
         MemorySlice::copy(&src_slice, &dst_slice);
         assert_eq!(dst.iter().sum::<u8>(), src.len() as u8);
     }
+
+    const SHIFT: usize = 3;
+    type Zero = ZeroBasedCompression<SHIFT>;
+
+    // The addresses in the tests below do not fit in a 32-bit address space.
+    #[cfg(target_pointer_width = "64")]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    struct TestBase;
+
+    #[cfg(target_pointer_width = "64")]
+    impl PointerCompression for TestBase {
+        const SHIFT: usize = SHIFT;
+
+        fn base() -> Address {
+            unsafe { Address::from_usize(0x7000_0000_0000) }
+        }
+    }
+
+    fn objref(addr: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(addr) }).unwrap()
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn compressed_edge_zero_based() {
+        let mut slot: u32 = 0;
+        let edge = CompressedEdge::<Zero>::from_address(Address::from_mut_ptr(&mut slot));
+        assert_eq!(edge.load(), None);
+
+        let object = objref(0x4_0000_1000);
+        edge.store(object);
+        assert_eq!(slot, (0x4_0000_1000usize >> SHIFT) as u32);
+        assert_eq!(edge.load(), Some(object));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn compressed_edge_base_relative() {
+        let mut slot: u32 = 0;
+        let edge = CompressedEdge::<TestBase>::from_address(Address::from_mut_ptr(&mut slot));
+        let object = objref(0x7000_0000_0000 + (1 << 34));
+        edge.store(object);
+        assert_eq!(slot, 1 << (34 - SHIFT));
+        assert_eq!(edge.load(), Some(object));
+    }
+
+    #[test]
+    fn compressed_slice_iteration_and_copy() {
+        let src: Vec<u32> = (1..=8).collect();
+        let mut dst = [0u32; 8];
+        let src_start = Address::from_ptr(&src[0]);
+        let dst_start = Address::from_mut_ptr(&mut dst[0]);
+        let bytes = std::mem::size_of_val(&dst);
+        let src_slice = CompressedMemorySlice::<Zero>::new(None, src_start..src_start + bytes);
+        let dst_slice = CompressedMemorySlice::<Zero>::new(None, dst_start..dst_start + bytes);
+        assert_eq!(src_slice.bytes(), bytes);
+
+        let loaded: Vec<_> = src_slice.iter_edges().map(|e| e.load().unwrap()).collect();
+        let expected: Vec<_> = (1..=8).map(|i| objref(i << SHIFT)).collect();
+        assert_eq!(loaded, expected);
+
+        MemorySlice::copy(&src_slice, &dst_slice);
+        assert_eq!(&dst[..], &src[..]);
+    }
 }
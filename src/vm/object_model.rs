@@ -365,6 +365,17 @@ pub trait ObjectModel<VM: VMBinding> {
     /// * `to`: The region to be copied to.
     fn get_reference_when_copied_to(from: ObjectReference, to: Address) -> ObjectReference;
 
+    /// Called after an object is moved without copying, by remapping the pages of a large object
+    /// to a new address (see the option `los_page_remapping`).  The content of the object is
+    /// already at `to`, and `from` is no longer accessible.  The default implementation does
+    /// nothing.  A binding can update the per-object state that depends on the address of the
+    /// object or that is kept outside the object, as it would do in [`ObjectModel::copy`].
+    ///
+    /// Arguments:
+    /// * `from`: The reference of the object before it is moved.
+    /// * `to`: The reference of the object after it is moved.
+    fn on_object_remapped(_from: ObjectReference, _to: ObjectReference) {}
+
     /// Return the size used by an object.
     ///
     /// Arguments:
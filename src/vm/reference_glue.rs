@@ -34,6 +34,13 @@ pub trait ReferenceGlue<VM: VMBinding> {
 
     /// Set the referent in a weak reference object.
     ///
+    /// In nursery GCs of generational plans, MMTk only processes the references added as
+    /// candidates since the last GC. The other references survived a GC, and MMTk assumes that
+    /// their referents survived it, too. MMTk only calls this method to update a referent that
+    /// has moved, but the binding must never make a reference that has survived a GC point to a
+    /// nursery object in its own code. Otherwise the referent may be reclaimed in a nursery GC
+    /// without the reference being cleared.
+    ///
     /// Arguments:
     /// * `reff`: The object reference for the reference.
     /// * `referent`: The referent object reference.
@@ -47,6 +54,20 @@ pub trait ReferenceGlue<VM: VMBinding> {
     /// the references slice will be cleared after this call is returned. That means
     /// MMTk will no longer keep these references alive once this method is returned.
     fn enqueue_references(references: &[ObjectReference], tls: VMWorkerThread);
+
+    /// Get the time when a soft reference was last accessed, i.e. when its referent was last
+    /// loaded by the application.  The time is in milliseconds, read from
+    /// [`crate::memory_manager::soft_reference_clock`] when the reference was accessed.  This is
+    /// only called if the option `soft_reference_policy` is `LRU`.
+    ///
+    /// The default implementation returns `None`, which means the time is unknown, and the
+    /// reference is treated as if it was just accessed.
+    ///
+    /// Arguments:
+    /// * `reference`: The soft reference object.
+    fn get_soft_reference_timestamp(_reference: ObjectReference) -> Option<u64> {
+        None
+    }
 }
 
 use crate::scheduler::gc_work::ProcessEdgesWork;
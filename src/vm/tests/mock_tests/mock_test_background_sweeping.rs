@@ -0,0 +1,71 @@
+// GITHUB-CI: MMTK_PLAN=Immix,MarkSweep
+// GITHUB-CI: FEATURES=mock_test_side_metadata
+
+// Allocate garbage, GC, and allocate again with background sweeping.  The memory that is not swept
+// yet after a GC must not make the next allocation trigger another GC.
+
+use super::mock_test_prelude::*;
+use crate::util::options::GCTriggerSelector;
+use crate::util::test_util::mock_gc::MockGC;
+use crate::{AllocationSemantics, MMTKBuilder, UserCollectionKind};
+
+const OBJECT_SIZE: usize = 1024;
+const MB: usize = 1024 * 1024;
+const HEAP_SIZE: usize = 32 * MB;
+/// More than half of the heap, so two rounds of garbage do not fit in the heap together.
+const GARBAGE_SIZE: usize = 20 * MB;
+
+fn allocate_garbage(gc: &mut MockGC) {
+    for _ in 0..GARBAGE_SIZE / OBJECT_SIZE {
+        let addr = memory_manager::alloc(
+            gc.mutator(),
+            OBJECT_SIZE,
+            8,
+            0,
+            AllocationSemantics::Default,
+        );
+        assert!(!addr.is_zero());
+        memory_manager::post_alloc(
+            gc.mutator(),
+            MockVM::address_to_ref(addr),
+            OBJECT_SIZE,
+            AllocationSemantics::Default,
+        );
+    }
+}
+
+#[test]
+pub fn background_sweeping() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut builder = MMTKBuilder::new();
+            builder
+                .options
+                .gc_trigger
+                .set(GCTriggerSelector::FixedHeapSize(HEAP_SIZE));
+            assert!(builder.options.background_sweeping.set(true));
+            let mut gc = MockGC::new(
+                &builder,
+                MockVM {
+                    get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                    scan_object: MockMethod::new_default(),
+                    ..MockVM::default()
+                },
+            );
+
+            allocate_garbage(&mut gc);
+            for _ in 0..3 {
+                gc.run_gc(UserCollectionKind::Full);
+                let finished_gcs = gc.finished_gcs();
+                allocate_garbage(&mut gc);
+                assert_eq!(
+                    gc.finished_gcs(),
+                    finished_gcs,
+                    "The allocation after a GC triggered another GC"
+                );
+            }
+        },
+        no_cleanup,
+    )
+}
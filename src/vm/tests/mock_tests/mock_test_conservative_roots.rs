@@ -0,0 +1,109 @@
+// GITHUB-CI: MMTK_PLAN=all
+// GITHUB-CI: FEATURES=is_mmtk_object
+
+use super::mock_test_prelude::*;
+
+use crate::util::is_mmtk_object::ConservativeScanStats;
+use crate::util::*;
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    static ref FIXTURE: Fixture<TwoObjects> = Fixture::new();
+}
+
+/// The size of the objects in `TwoObjects`.
+const OBJECT_SIZE: usize = 128;
+
+/// A factory that records the pinning roots it is given.
+#[derive(Clone, Default)]
+struct RecordingFactory {
+    pinning_roots: Arc<Mutex<Vec<ObjectReference>>>,
+}
+
+impl RootsWorkFactory<Address> for RecordingFactory {
+    fn create_process_edge_roots_work(&mut self, _edges: Vec<Address>) {
+        panic!("Conservative scanning should not report edges");
+    }
+
+    fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        self.pinning_roots.lock().unwrap().extend(nodes);
+    }
+
+    fn create_process_tpinning_roots_work(&mut self, _nodes: Vec<ObjectReference>) {
+        panic!("Conservative scanning should not report transitively pinning roots");
+    }
+}
+
+fn scan(words: &[usize]) -> (Vec<ObjectReference>, ConservativeScanStats) {
+    let start = Address::from_ptr(words.as_ptr());
+    let end = start + std::mem::size_of_val(words);
+    let mut factory = RecordingFactory::default();
+    let stats = memory_manager::scan_conservative_range::<MockVM>(start, end, &mut factory);
+    let mut roots = factory.pinning_roots.lock().unwrap().clone();
+    roots.sort();
+    (roots, stats)
+}
+
+fn setup() -> MockVM {
+    MockVM {
+        get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+        ..MockVM::default()
+    }
+}
+
+#[test]
+pub fn exact_and_duplicated_pointers() {
+    with_mockvm(
+        setup,
+        || {
+            FIXTURE.with_fixture(|fixture| {
+                let obj1 = fixture.objref1.to_raw_address().as_usize();
+                let obj2 = fixture.objref2.to_raw_address().as_usize();
+                let (roots, stats) = scan(&[0, obj1, 42, obj2, obj1, usize::MAX]);
+
+                let mut expected = vec![fixture.objref1, fixture.objref2];
+                expected.sort();
+                assert_eq!(roots, expected);
+                assert_eq!(stats.words_scanned, 6);
+                assert_eq!(stats.ambiguous_roots, 3);
+                assert_eq!(stats.pinned_objects, 2);
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+pub fn interior_pointers() {
+    with_mockvm(
+        setup,
+        || {
+            FIXTURE.with_fixture(|fixture| {
+                let start = fixture.objref1.to_object_start::<MockVM>().as_usize();
+                let words = [start, start + 17, start + OBJECT_SIZE - 1];
+                let (roots, stats) = scan(&words);
+
+                assert_eq!(roots, vec![fixture.objref1]);
+                assert_eq!(stats.ambiguous_roots, 3);
+                assert_eq!(stats.pinned_objects, 1);
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+pub fn no_roots() {
+    with_mockvm(
+        setup,
+        || {
+            FIXTURE.with_fixture(|_| {
+                let (roots, stats) = scan(&[0, 1, 0x1000, usize::MAX]);
+                assert!(roots.is_empty());
+                assert_eq!(stats.words_scanned, 4);
+                assert_eq!(stats.ambiguous_roots, 0);
+            });
+        },
+        no_cleanup,
+    )
+}
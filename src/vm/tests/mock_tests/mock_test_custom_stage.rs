@@ -0,0 +1,56 @@
+// GITHUB-CI: MMTK_PLAN=NoGC
+
+use super::mock_test_prelude::*;
+
+use crate::scheduler::{CustomStage, GCWork, GCWorker, WorkBucketStage};
+use crate::{MMTKBuilder, MMTK};
+use enum_map::Enum;
+
+struct DummyWork;
+
+impl GCWork<MockVM> for DummyWork {
+    fn do_work(&mut self, _worker: &mut GCWorker<MockVM>, _mmtk: &'static MMTK<MockVM>) {}
+}
+
+#[test]
+pub fn custom_stage() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut builder = MMTKBuilder::new();
+            let interned_strings = builder.add_custom_stage(WorkBucketStage::VMRefClosure);
+            assert_eq!(interned_strings, WorkBucketStage::Custom(CustomStage(0)));
+            let mmtk: &'static MMTK<MockVM> = Box::leak(Box::new(builder.build::<MockVM>()));
+            let scheduler = &mmtk.scheduler;
+
+            assert!(scheduler.is_stage_registered(interned_strings));
+            assert!(!scheduler.is_stage_registered(WorkBucketStage::Custom(CustomStage(1))));
+
+            memory_manager::add_work_packet(mmtk, interned_strings, DummyWork);
+            memory_manager::set_work_bucket_sentinel(mmtk, interned_strings, DummyWork);
+            assert!(!scheduler.work_buckets[interned_strings].is_empty());
+            assert!(scheduler.work_buckets[interned_strings].has_sentinel());
+
+            // Pretend that all the stages up to VMRefClosure have been done.
+            let mut stage = WorkBucketStage::first_stw_stage();
+            while stage != WorkBucketStage::VMRefClosure {
+                scheduler.work_buckets[stage].activate();
+                stage = WorkBucketStage::from_usize(stage.into_usize() + 1);
+            }
+            assert!(scheduler.update_buckets());
+            // VMRefClosure is empty, so the custom stage after it is opened, too.
+            assert!(scheduler.work_buckets[WorkBucketStage::VMRefClosure].is_activated());
+            assert!(scheduler.work_buckets[interned_strings].is_activated());
+            // The stage after the custom stage waits for the custom stage to be drained.
+            let next = WorkBucketStage::from_usize(WorkBucketStage::VMRefClosure.into_usize() + 1);
+            assert!(!scheduler.work_buckets[next].is_activated());
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+#[should_panic(expected = "A custom stage cannot be placed after Final")]
+pub fn custom_stage_after_final() {
+    MMTKBuilder::new().add_custom_stage(WorkBucketStage::Final);
+}
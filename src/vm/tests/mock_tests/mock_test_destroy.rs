@@ -0,0 +1,93 @@
+// GITHUB-CI: MMTK_PLAN=all
+// GITHUB-CI: FEATURES=mock_test_side_metadata
+
+use super::mock_test_prelude::*;
+use crate::mmtk::{MMAPPER, SFT_MAP};
+use crate::policy::sft::EMPTY_SFT_NAME;
+use crate::util::options::GCTriggerSelector;
+use crate::util::test_util::mock_gc::MockGC;
+use crate::util::{Address, VMMutatorThread, VMThread};
+use crate::{AllocationSemantics, MMTKBuilder, UserCollectionKind, MMTK};
+
+// We fix the number of threads so that we know how many GC threads should exit.
+const NUM_WORKER_THREADS: usize = 4;
+
+const MB: usize = 1024 * 1024;
+
+fn create_builder() -> MMTKBuilder {
+    let mut builder = MMTKBuilder::new();
+    builder
+        .options
+        .gc_trigger
+        .set(GCTriggerSelector::FixedHeapSize(16 * MB));
+    builder.options.threads.set(NUM_WORKER_THREADS);
+    builder
+}
+
+fn space_ranges(mmtk: &MMTK<MockVM>) -> Vec<(Address, usize)> {
+    let mut ranges = vec![];
+    mmtk.get_plan()
+        .for_each_space(&mut |space| ranges.push((space.common().start, space.common().extent)));
+    ranges
+}
+
+/// Allocate a few objects, and return their addresses.
+fn allocate_objects(mmtk: &'static MMTK<MockVM>) -> Vec<Address> {
+    let mut mutator = memory_manager::bind_mutator(mmtk, VMMutatorThread(VMThread::UNINITIALIZED));
+    let addrs = (0..16)
+        .map(|_| {
+            let addr = memory_manager::alloc(&mut mutator, 64, 8, 0, AllocationSemantics::Default);
+            assert!(!addr.is_zero());
+            assert_ne!(SFT_MAP.get_checked(addr).name(), EMPTY_SFT_NAME);
+            addr
+        })
+        .collect();
+    memory_manager::destroy_mutator(&mut mutator);
+    addrs
+}
+
+/// Destroy an MMTk instance, and check that its memory is released.
+fn destroy_mmtk(mmtk: &'static MMTK<MockVM>, addrs: &[Address]) {
+    unsafe { memory_manager::mmtk_destroy(Box::from_raw(mmtk as *const _ as *mut MMTK<MockVM>)) };
+    for addr in addrs {
+        assert_eq!(SFT_MAP.get_checked(*addr).name(), EMPTY_SFT_NAME);
+        assert!(!MMAPPER.is_mapped_address(*addr));
+    }
+}
+
+#[test]
+pub fn test_destroy_and_recreate() {
+    let mut gc = MockGC::new(
+        &create_builder(),
+        MockVM {
+            get_object_size: MockMethod::new_fixed(Box::new(|_| 64)),
+            // The objects have no fields.
+            scan_object: MockMethod::new_default(),
+            ..MockVM::default()
+        },
+    );
+    let mmtk = gc.mmtk;
+    let ranges = space_ranges(mmtk);
+    let addrs = allocate_objects(mmtk);
+
+    // Run a GC first, so the instance is destroyed with the state that a GC leaves in the spaces
+    // and the GC workers.  No object is rooted, so the objects are dead, and nothing is copied.
+    // Immix GCs need the mark bits on the side.
+    if cfg!(feature = "mock_test_side_metadata") && mmtk.get_plan().constraints().collects_garbage {
+        gc.run_gc(UserCollectionKind::Full);
+    }
+    memory_manager::destroy_mutator(gc.mutator());
+
+    // GC worker threads should exit.
+    mmtk.prepare_to_destroy();
+    assert_eq!(gc.join_gc_workers(), NUM_WORKER_THREADS);
+
+    destroy_mmtk(mmtk, &addrs);
+
+    // A new instance reuses the address ranges of the destroyed instance.
+    let mmtk: &'static MMTK<MockVM> =
+        Box::leak(memory_manager::mmtk_init::<MockVM>(&create_builder()));
+    assert_eq!(space_ranges(mmtk), ranges);
+    let addrs = allocate_objects(mmtk);
+    destroy_mmtk(mmtk, &addrs);
+}
@@ -0,0 +1,17 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep
+// GITHUB-CI: FEATURES=malloc_mark_sweep
+
+use super::mock_test_prelude::*;
+
+use crate::util::options::PlanSelector;
+use crate::MMTKBuilder;
+
+#[test]
+#[should_panic(expected = "cannot be destroyed")]
+pub fn test_destroy_malloc_mark_sweep() {
+    let mut builder = MMTKBuilder::new();
+    builder.options.plan.set(PlanSelector::MarkSweep);
+    let mmtk = memory_manager::mmtk_init::<MockVM>(&builder);
+    // The memory allocated by malloc cannot be released. MMTk rejects the request.
+    unsafe { memory_manager::mmtk_destroy(mmtk) };
+}
@@ -336,6 +336,156 @@ mod tagged_edge {
     }
 }
 
+mod tag_schemes {
+    use super::*;
+    use crate::util::constants::BYTES_IN_WORD;
+    use crate::vm::edge_shape::{
+        MemorySlice, PointerTag, PreservedTag, TaggedEdge, TaggedMemorySlice,
+    };
+
+    /// Tag heap objects with 0b01, and small integers with 0b00.
+    type ObjectTag = PointerTag<0b11, 0b01>;
+
+    /// A small integer in the same encoding as `ObjectTag`.
+    fn small_int(value: usize) -> usize {
+        value << 2
+    }
+
+    #[test]
+    pub fn preserved_tag() {
+        with_mockvm(
+            default_setup,
+            || {
+                FIXTURE.with_fixture(|fixture| {
+                    let addr1 = fixture.objref1.to_raw_address().as_usize();
+                    let addr2 = fixture.objref2.to_raw_address().as_usize();
+                    let mut slot: Atomic<usize> = Atomic::new(addr1 | 0b10);
+
+                    let edge =
+                        TaggedEdge::<PreservedTag<0b11>>::from_address(Address::from_ref(&slot));
+                    assert_eq!(edge.load(), Some(fixture.objref1));
+
+                    edge.store(fixture.objref2);
+                    assert_eq!(slot.load(Ordering::SeqCst), addr2 | 0b10);
+                    assert_eq!(edge.load(), Some(fixture.objref2));
+
+                    // The tag bits of null are ignored.
+                    slot.store(0b01, Ordering::SeqCst);
+                    assert_eq!(edge.load(), None);
+                });
+            },
+            no_cleanup,
+        )
+    }
+
+    #[test]
+    pub fn pointer_tag() {
+        with_mockvm(
+            default_setup,
+            || {
+                FIXTURE.with_fixture(|fixture| {
+                    let addr1 = fixture.objref1.to_raw_address().as_usize();
+                    let addr2 = fixture.objref2.to_raw_address().as_usize();
+                    let mut slot: Atomic<usize> = Atomic::new(addr1 | 0b01);
+
+                    let edge = TaggedEdge::<ObjectTag>::from_address(Address::from_ref(&slot));
+                    assert_eq!(edge.load(), Some(fixture.objref1));
+
+                    edge.store(fixture.objref2);
+                    assert_eq!(slot.load(Ordering::SeqCst), addr2 | 0b01);
+                    assert_eq!(edge.load(), Some(fixture.objref2));
+
+                    // Immediate values are not references, even if they look like addresses.
+                    slot.store(small_int(42), Ordering::SeqCst);
+                    assert_eq!(edge.load(), None);
+                    slot.store(addr1, Ordering::SeqCst);
+                    assert_eq!(edge.load(), None);
+                });
+            },
+            no_cleanup,
+        )
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    pub fn nan_boxing() {
+        use crate::vm::edge_shape::NanBoxing;
+
+        /// The tag of objects in the upper 17 bits.
+        const OBJECT_TAG: usize = 0xFFFE_0000_0000_0000;
+        const INT32_TAG: usize = 0xFFF8_8000_0000_0000;
+
+        with_mockvm(
+            default_setup,
+            || {
+                FIXTURE.with_fixture(|fixture| {
+                    let addr1 = fixture.objref1.to_raw_address().as_usize();
+                    let addr2 = fixture.objref2.to_raw_address().as_usize();
+                    let mut slot: Atomic<usize> = Atomic::new(addr1 | OBJECT_TAG);
+
+                    let edge =
+                        TaggedEdge::<NanBoxing<OBJECT_TAG>>::from_address(Address::from_ref(&slot));
+                    assert_eq!(edge.load(), Some(fixture.objref1));
+
+                    edge.store(fixture.objref2);
+                    assert_eq!(slot.load(Ordering::SeqCst), addr2 | OBJECT_TAG);
+                    assert_eq!(edge.load(), Some(fixture.objref2));
+
+                    // Doubles and other boxed values are not references.
+                    slot.store(1.5f64.to_bits() as usize, Ordering::SeqCst);
+                    assert_eq!(edge.load(), None);
+                    slot.store(INT32_TAG | 42, Ordering::SeqCst);
+                    assert_eq!(edge.load(), None);
+                });
+            },
+            no_cleanup,
+        )
+    }
+
+    #[test]
+    pub fn tagged_memory_slice() {
+        with_mockvm(
+            default_setup,
+            || {
+                FIXTURE.with_fixture(|fixture| {
+                    let addr1 = fixture.objref1.to_raw_address().as_usize();
+                    let addr2 = fixture.objref2.to_raw_address().as_usize();
+                    let src: [usize; 5] =
+                        [addr1 | 0b01, small_int(1), 0, addr2 | 0b01, small_int(2)];
+                    let mut dst = [0usize; 5];
+                    let bytes = std::mem::size_of_val(&src);
+                    let src_start = Address::from_ptr(&src[0]);
+                    let dst_start = Address::from_mut_ptr(&mut dst[0]);
+                    let src_slice = TaggedMemorySlice::<ObjectTag>::new(
+                        Some(fixture.objref1),
+                        src_start..src_start + bytes,
+                    );
+                    let dst_slice =
+                        TaggedMemorySlice::<ObjectTag>::new(None, dst_start..dst_start + bytes);
+                    assert_eq!(src_slice.object(), Some(fixture.objref1));
+                    assert_eq!(src_slice.bytes(), bytes);
+
+                    // Immediate values and null are skipped.
+                    let edges: Vec<_> = src_slice.iter_edges().collect();
+                    assert_eq!(
+                        edges.iter().map(|e| e.as_address()).collect::<Vec<_>>(),
+                        vec![src_start, src_start + 3 * BYTES_IN_WORD]
+                    );
+                    assert_eq!(
+                        edges.iter().map(|e| e.load()).collect::<Vec<_>>(),
+                        vec![Some(fixture.objref1), Some(fixture.objref2)]
+                    );
+
+                    // Immediate values are copied as well.
+                    MemorySlice::copy(&src_slice, &dst_slice);
+                    assert_eq!(dst, src);
+                });
+            },
+            no_cleanup,
+        )
+    }
+}
+
 mod mixed {
     #[cfg(target_pointer_width = "64")]
     use super::compressed_oop::CompressedOopEdge;
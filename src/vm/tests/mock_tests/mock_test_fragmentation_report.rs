@@ -0,0 +1,106 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+use super::mock_test_prelude::*;
+
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::fragmentation::PolicyReport;
+use crate::util::options::PlanSelector;
+use crate::AllocationSemantics;
+
+/// The size of the large object.  Small objects are not asked for their sizes.
+const LARGE_OBJECT_SIZE: usize = 3 * BYTES_IN_PAGE + 8;
+
+#[test]
+pub fn fragmentation_report() {
+    with_mockvm(
+        || MockVM {
+            get_object_size: MockMethod::new_fixed(Box::new(|_| LARGE_OBJECT_SIZE)),
+            ..MockVM::default()
+        },
+        || {
+            const MB: usize = 1024 * 1024;
+            let mut fixture = MutatorFixture::create_with_heapsize(16 * MB);
+            for _ in 0..16 {
+                memory_manager::alloc(&mut fixture.mutator, 64, 8, 0, AllocationSemantics::Default);
+            }
+            let large = memory_manager::alloc(
+                &mut fixture.mutator,
+                LARGE_OBJECT_SIZE,
+                8,
+                0,
+                AllocationSemantics::Los,
+            );
+            let large = MockVM::address_to_ref(large);
+            memory_manager::post_alloc(
+                &mut fixture.mutator,
+                large,
+                LARGE_OBJECT_SIZE,
+                AllocationSemantics::Los,
+            );
+
+            let mmtk = fixture.mmtk();
+            let reports = memory_manager::fragmentation_report(mmtk);
+            let mut names: Vec<_> = reports.iter().map(|report| report.name).collect();
+            names.sort_unstable();
+            names.dedup();
+            assert_eq!(names.len(), reports.len());
+
+            let mut policies = vec![];
+            let mut immix_blocks = 0;
+            let mut large_objects = 0;
+            for report in reports.iter() {
+                if let Some(live_bytes) = report.live_bytes {
+                    assert!(live_bytes <= report.reserved_bytes, "{:?}", report);
+                }
+                match &report.policy {
+                    PolicyReport::Other => {}
+                    PolicyReport::Immix(immix) => {
+                        policies.push("Immix");
+                        immix_blocks += immix.blocks;
+                        assert!(immix.marked_lines <= immix.lines);
+                        assert_eq!(immix.blocks_by_holes.iter().sum::<usize>(), immix.blocks);
+                    }
+                    PolicyReport::MarkSweep(ms) => {
+                        policies.push("MarkSweep");
+                        assert!(!ms.size_classes.is_empty());
+                        for class in ms.size_classes.iter() {
+                            assert!(class.cell_size >= 64);
+                            assert!(class.free_cells <= class.cells);
+                        }
+                        assert!(report.free_list.is_some());
+                    }
+                    PolicyReport::LargeObject(los) => {
+                        policies.push("LargeObject");
+                        large_objects += los.objects;
+                        assert_eq!(los.object_bytes, los.objects * LARGE_OBJECT_SIZE);
+                        assert_eq!(los.pages, los.objects * 4);
+                        assert_eq!(report.live_bytes, Some(los.object_bytes));
+                        assert!(report.free_list.is_some());
+                    }
+                }
+            }
+
+            let plan = *mmtk.get_options().plan;
+            let has_los = !matches!(plan, PlanSelector::NoGC);
+            assert_eq!(policies.contains(&"LargeObject"), has_los);
+            assert_eq!(large_objects, usize::from(has_los));
+            let has_immix = matches!(
+                plan,
+                PlanSelector::Immix | PlanSelector::GenImmix | PlanSelector::StickyImmix
+            );
+            assert_eq!(policies.contains(&"Immix"), has_immix);
+            // GenImmix allocates small objects in the nursery.
+            assert_eq!(
+                immix_blocks > 0,
+                matches!(plan, PlanSelector::Immix | PlanSelector::StickyImmix)
+            );
+            let has_native_ms =
+                matches!(plan, PlanSelector::MarkSweep) && !cfg!(feature = "malloc_mark_sweep");
+            assert_eq!(policies.contains(&"MarkSweep"), has_native_ms);
+
+            // No GC has happened, and the option is not enabled.
+            assert!(memory_manager::last_fragmentation_report(mmtk).is_empty());
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,85 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep
+
+// The fragmentation report of a native mark-sweep space at the end of a GC counts the cells that
+// the GC marked, even if the blocks are swept lazily after the GC.
+
+use super::mock_test_prelude::*;
+use crate::util::fragmentation::PolicyReport;
+use crate::util::options::GCTriggerSelector;
+use crate::util::test_util::mock_gc::MockGC;
+use crate::util::{Address, ObjectReference};
+use crate::{AllocationSemantics, MMTKBuilder, UserCollectionKind};
+
+const OBJECT_SIZE: usize = 64;
+const OBJECTS: usize = 16;
+const LIVE_OBJECTS: usize = 4;
+const MB: usize = 1024 * 1024;
+
+fn allocate_object(gc: &mut MockGC) -> ObjectReference {
+    let addr = memory_manager::alloc(
+        gc.mutator(),
+        OBJECT_SIZE,
+        8,
+        0,
+        AllocationSemantics::Default,
+    );
+    assert!(!addr.is_zero());
+    let object = MockVM::address_to_ref(addr);
+    memory_manager::post_alloc(
+        gc.mutator(),
+        object,
+        OBJECT_SIZE,
+        AllocationSemantics::Default,
+    );
+    object
+}
+
+#[test]
+pub fn fragmentation_report_after_gc() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut builder = MMTKBuilder::new();
+            builder
+                .options
+                .gc_trigger
+                .set(GCTriggerSelector::FixedHeapSize(32 * MB));
+            assert!(builder.options.fragmentation_report.set(true));
+            let mut gc = MockGC::new(
+                &builder,
+                MockVM {
+                    get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                    // The objects have no fields.
+                    scan_object: MockMethod::new_default(),
+                    ..MockVM::default()
+                },
+            );
+
+            let objects: Vec<ObjectReference> =
+                (0..OBJECTS).map(|_| allocate_object(&mut gc)).collect();
+            let mut slots: Vec<ObjectReference> = objects[..LIVE_OBJECTS].to_vec();
+            gc.set_roots(
+                slots
+                    .iter_mut()
+                    .map(|slot| Address::from_mut_ptr(slot))
+                    .collect(),
+            );
+            gc.run_gc(UserCollectionKind::Full);
+
+            let reports = memory_manager::last_fragmentation_report(gc.mmtk);
+            let report = reports
+                .iter()
+                .find(|report| matches!(report.policy, PolicyReport::MarkSweep(_)))
+                .expect("No report for the mark-sweep space");
+            let PolicyReport::MarkSweep(ms) = &report.policy else {
+                unreachable!()
+            };
+            // All the objects are in one size class.
+            assert_eq!(ms.size_classes.len(), 1, "{:?}", ms);
+            let class = &ms.size_classes[0];
+            assert_eq!(class.cells - class.free_cells, LIVE_OBJECTS);
+            assert_eq!(report.live_bytes, Some(LIVE_OBJECTS * class.cell_size));
+        },
+        no_cleanup,
+    )
+}
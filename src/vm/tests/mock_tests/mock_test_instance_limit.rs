@@ -0,0 +1,43 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+use super::mock_test_prelude::*;
+
+use crate::MMTK;
+
+fn create_mmtk() -> Box<MMTK<MockVM>> {
+    memory_manager::mmtk_init::<MockVM>(&crate::MMTKBuilder::new())
+}
+
+#[test]
+pub fn create_instances_until_out_of_address_space() {
+    with_mockvm(
+        default_setup,
+        || {
+            // Each instance takes at least one of the 15 spaces in the heap range.
+            let mut instances = vec![];
+            let payload = loop {
+                match std::panic::catch_unwind(create_mmtk) {
+                    Ok(mmtk) => instances.push(mmtk),
+                    Err(payload) => break payload,
+                }
+                assert!(instances.len() <= 15);
+            };
+            let message = payload.downcast_ref::<String>().unwrap();
+            assert!(
+                message.starts_with("Out of virtual address space"),
+                "{}",
+                message
+            );
+
+            // The failure does not affect other instances.  We can still destroy an instance, and
+            // create a new one in its place.
+            let last = instances.pop().unwrap();
+            unsafe { memory_manager::mmtk_destroy(last) };
+            instances.push(create_mmtk());
+            for mmtk in instances {
+                unsafe { memory_manager::mmtk_destroy(mmtk) };
+            }
+        },
+        no_cleanup,
+    )
+}
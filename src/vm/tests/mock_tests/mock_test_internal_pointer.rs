@@ -0,0 +1,187 @@
+// GITHUB-CI: MMTK_PLAN=all
+// GITHUB-CI: FEATURES=is_mmtk_object
+
+use super::mock_test_prelude::*;
+
+use crate::util::*;
+use crate::AllocationSemantics;
+use std::sync::{Arc, Mutex};
+
+/// Where we store the object size in an object. MockVM uses the first word as the object header.
+const SIZE_OFFSET: usize = 16;
+
+lazy_static! {
+    static ref MUTATOR: Fixture<MutatorFixture> = Fixture::new();
+}
+
+/// Allocate an object, and record its size in the object so `get_object_size` can find it.
+fn alloc_object(
+    fixture: &mut MutatorFixture,
+    size: usize,
+    semantics: AllocationSemantics,
+) -> ObjectReference {
+    let addr = memory_manager::alloc(&mut fixture.mutator, size, 8, 0, semantics);
+    assert!(!addr.is_zero());
+    unsafe { (addr + SIZE_OFFSET).store::<usize>(size) };
+    let objref = ObjectReference::from_raw_address(addr + DEFAULT_OBJECT_REF_OFFSET).unwrap();
+    memory_manager::post_alloc(&mut fixture.mutator, objref, size, semantics);
+    objref
+}
+
+fn setup() -> MockVM {
+    MockVM {
+        get_object_size: MockMethod::new_fixed(Box::new(|object: ObjectReference| unsafe {
+            (object.to_raw_address() - DEFAULT_OBJECT_REF_OFFSET + SIZE_OFFSET).load::<usize>()
+        })),
+        ..MockVM::default()
+    }
+}
+
+fn assert_internal_pointers(object: ObjectReference, size: usize) {
+    let start = object.to_raw_address() - DEFAULT_OBJECT_REF_OFFSET;
+    for offset in 0..size {
+        assert_eq!(
+            memory_manager::find_object_from_internal_pointer::<MockVM>(start + offset, size),
+            Some(object),
+            "{} (offset {}) should be resolved to {}",
+            start + offset,
+            offset,
+            object
+        );
+    }
+    // Just beyond the object.
+    assert_ne!(
+        memory_manager::find_object_from_internal_pointer::<MockVM>(start + size, size),
+        Some(object)
+    );
+}
+
+#[test]
+pub fn small_object() {
+    with_mockvm(
+        setup,
+        || {
+            MUTATOR.with_fixture_mut(|fixture| {
+                const SIZE: usize = 128;
+                let object = alloc_object(fixture, SIZE, AllocationSemantics::Default);
+                assert_internal_pointers(object, SIZE);
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+pub fn large_object() {
+    with_mockvm(
+        setup,
+        || {
+            MUTATOR.with_fixture_mut(|fixture| {
+                const SIZE: usize = 32 * 1024;
+                let object = alloc_object(fixture, SIZE, AllocationSemantics::Los);
+                assert_internal_pointers(object, SIZE);
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+pub fn address_after_object_start() {
+    // The VO bit is set at `ref_to_address`, which is 8 bytes after the object start.  The
+    // object reference is 4 bytes after the start, as `OBJECT_REF_OFFSET_LOWER_BOUND` says.
+    const ADDRESS_OFFSET: usize = 4;
+    with_mockvm(
+        || MockVM {
+            ref_to_address: MockMethod::new_fixed(Box::new(|object: ObjectReference| {
+                object.to_raw_address() + ADDRESS_OFFSET
+            })),
+            address_to_ref: MockMethod::new_fixed(Box::new(|addr: Address| {
+                ObjectReference::from_raw_address(addr - ADDRESS_OFFSET).unwrap()
+            })),
+            ..setup()
+        },
+        || {
+            MUTATOR.with_fixture_mut(|fixture| {
+                const SIZE: usize = 128;
+                let object = alloc_object(fixture, SIZE, AllocationSemantics::Default);
+                let start = object.to_raw_address() - DEFAULT_OBJECT_REF_OFFSET;
+                assert_eq!(object.to_address::<MockVM>(), start + 8usize);
+                assert_internal_pointers(object, SIZE);
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+pub fn search_limit() {
+    with_mockvm(
+        setup,
+        || {
+            MUTATOR.with_fixture_mut(|fixture| {
+                const SIZE: usize = 4096;
+                let object = alloc_object(fixture, SIZE, AllocationSemantics::Default);
+                let start = object.to_raw_address() - DEFAULT_OBJECT_REF_OFFSET;
+                let ptr = start + 1024usize;
+                assert_eq!(
+                    memory_manager::find_object_from_internal_pointer::<MockVM>(ptr, 1024),
+                    Some(object)
+                );
+                assert_eq!(
+                    memory_manager::find_object_from_internal_pointer::<MockVM>(ptr, 512),
+                    None
+                );
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[derive(Clone, Default)]
+struct RecordingFactory {
+    pinning_roots: Arc<Mutex<Vec<ObjectReference>>>,
+}
+
+impl RootsWorkFactory<Address> for RecordingFactory {
+    fn create_process_edge_roots_work(&mut self, _edges: Vec<Address>) {
+        unreachable!()
+    }
+
+    fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        self.pinning_roots.lock().unwrap().extend(nodes);
+    }
+
+    fn create_process_tpinning_roots_work(&mut self, _nodes: Vec<ObjectReference>) {
+        unreachable!()
+    }
+}
+
+#[test]
+pub fn conservative_scan_with_internal_pointers() {
+    with_mockvm(
+        setup,
+        || {
+            MUTATOR.with_fixture_mut(|fixture| {
+                // This must be smaller than `MAX_INTERIOR_POINTER_OFFSET`.
+                const SIZE: usize = 256;
+                let object = alloc_object(fixture, SIZE, AllocationSemantics::Default);
+                let start = (object.to_raw_address() - DEFAULT_OBJECT_REF_OFFSET).as_usize();
+                let words = [start, start + 17, start + SIZE - 1, start + SIZE];
+
+                let range_start = Address::from_ptr(words.as_ptr());
+                let range_end = range_start + std::mem::size_of_val(&words);
+                let mut factory = RecordingFactory::default();
+                let stats = memory_manager::scan_conservative_range::<MockVM>(
+                    range_start,
+                    range_end,
+                    &mut factory,
+                );
+
+                assert!(factory.pinning_roots.lock().unwrap().contains(&object));
+                assert!(stats.ambiguous_roots >= 3);
+            });
+        },
+        no_cleanup,
+    )
+}
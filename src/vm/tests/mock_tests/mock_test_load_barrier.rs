@@ -0,0 +1,121 @@
+// GITHUB-CI: MMTK_PLAN=NoGC
+
+use super::mock_test_prelude::*;
+
+use crate::plan::{LoadBarrier, LoadBarrierSemantics};
+use crate::util::metadata::MetadataSpec;
+use crate::util::{Address, ObjectReference};
+use atomic::{Atomic, Ordering};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+lazy_static! {
+    static ref FIXTURE: Fixture<SingleObject> = Fixture::new();
+}
+
+/// A load barrier semantics that replaces the loaded object with `replacement` in the slow-path.
+struct TestSemantics {
+    slow_path_calls: Arc<AtomicUsize>,
+    replacement: ObjectReference,
+}
+
+impl LoadBarrierSemantics for TestSemantics {
+    type VM = MockVM;
+
+    const LOAD_BARRIER_SPEC: MetadataSpec =
+        *<MockVM as VMBinding>::VMObjectModel::LOCAL_FORWARDING_BITS_SPEC.as_spec();
+
+    fn object_reference_read_slow(
+        &mut self,
+        _src: ObjectReference,
+        _slot: Address,
+        _target: ObjectReference,
+    ) -> ObjectReference {
+        self.slow_path_calls.fetch_add(1, Ordering::SeqCst);
+        self.replacement
+    }
+}
+
+#[test]
+pub fn load_barrier_fast_and_slow_path() {
+    with_mockvm(
+        default_setup,
+        || {
+            FIXTURE.with_fixture_mut(|fixture| {
+                let objref = fixture.objref;
+                let replacement =
+                    ObjectReference::from_raw_address(objref.to_raw_address() + 16usize).unwrap();
+                let slot = Atomic::new(objref);
+                let edge = Address::from_ref(&slot);
+
+                let slow_path_calls = Arc::new(AtomicUsize::new(0));
+                assert_eq!(LoadBarrier::<TestSemantics>::METADATA_NUM_OF_BITS, 2);
+
+                // Install the load barrier, and restore the original barrier at the end.
+                let mutator = fixture.mutator_mut();
+                let original = std::mem::replace(
+                    &mut mutator.barrier,
+                    Box::new(LoadBarrier::new(TestSemantics {
+                        slow_path_calls: slow_path_calls.clone(),
+                        replacement,
+                    })),
+                );
+
+                // The metadata is zero. Take the fast-path.
+                assert_eq!(
+                    memory_manager::object_reference_read(mutator, objref, edge),
+                    Some(objref)
+                );
+                assert_eq!(slow_path_calls.load(Ordering::SeqCst), 0);
+
+                // The metadata is not zero. Take the slow-path.
+                TestSemantics::LOAD_BARRIER_SPEC.store_atomic::<MockVM, u8>(
+                    objref,
+                    1,
+                    None,
+                    Ordering::SeqCst,
+                );
+                assert_eq!(
+                    memory_manager::object_reference_read(mutator, objref, edge),
+                    Some(replacement)
+                );
+                assert_eq!(slow_path_calls.load(Ordering::SeqCst), 1);
+                TestSemantics::LOAD_BARRIER_SPEC.store_atomic::<MockVM, u8>(
+                    objref,
+                    0,
+                    None,
+                    Ordering::SeqCst,
+                );
+
+                mutator.barrier = original;
+            });
+        },
+        no_cleanup,
+    )
+}
+
+#[test]
+pub fn no_load_barrier() {
+    with_mockvm(
+        default_setup,
+        || {
+            FIXTURE.with_fixture_mut(|fixture| {
+                let objref = fixture.objref;
+                let slot = Atomic::new(objref);
+                let edge = Address::from_ref(&slot);
+
+                // NoGC uses NoBarrier, which returns the loaded value as is.
+                let mutator = fixture.mutator_mut();
+                assert_eq!(
+                    memory_manager::object_reference_read(mutator, objref, edge),
+                    Some(objref)
+                );
+                assert_eq!(
+                    memory_manager::object_reference_read_slow(mutator, objref, edge, objref),
+                    objref
+                );
+            });
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,106 @@
+// GITHUB-CI: MMTK_PLAN=Immix StickyImmix
+// GITHUB-CI: FEATURES=mock_test_side_metadata
+
+// A large object is relocated by remapping its pages in a defrag GC if `los_page_remapping` is set.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::mock_test_prelude::*;
+use crate::policy::sft::SFT;
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::options::GCTriggerSelector;
+use crate::util::test_util::mock_gc::MockGC;
+use crate::util::{Address, ObjectReference};
+use crate::{AllocationSemantics, MMTKBuilder, UserCollectionKind};
+
+const OBJECT_SIZE: usize = 4 * BYTES_IN_PAGE;
+const MB: usize = 1024 * 1024;
+
+/// The old and the new addresses of the object reported to `ObjectModel::on_object_remapped`.
+static REMAPPED_FROM: AtomicUsize = AtomicUsize::new(0);
+static REMAPPED_TO: AtomicUsize = AtomicUsize::new(0);
+
+fn allocate_large_object(gc: &mut MockGC) -> ObjectReference {
+    let addr = memory_manager::alloc(gc.mutator(), OBJECT_SIZE, 8, 0, AllocationSemantics::Los);
+    assert!(!addr.is_zero());
+    let object = MockVM::address_to_ref(addr);
+    memory_manager::post_alloc(gc.mutator(), object, OBJECT_SIZE, AllocationSemantics::Los);
+    object
+}
+
+#[test]
+pub fn relocate_large_object() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut builder = MMTKBuilder::new();
+            builder
+                .options
+                .gc_trigger
+                .set(GCTriggerSelector::FixedHeapSize(32 * MB));
+            builder.options.los_page_remapping.set(true);
+            let mut gc = MockGC::new(
+                &builder,
+                MockVM {
+                    get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                    get_object_reference_when_copied_to: MockMethod::new_fixed(Box::new(
+                        |(_, to)| {
+                            ObjectReference::from_raw_address(to + DEFAULT_OBJECT_REF_OFFSET)
+                                .unwrap()
+                        },
+                    )),
+                    on_object_remapped: MockMethod::new_fixed(Box::new(|(from, to)| {
+                        REMAPPED_FROM.store(from.to_raw_address().as_usize(), Ordering::SeqCst);
+                        REMAPPED_TO.store(to.to_raw_address().as_usize(), Ordering::SeqCst);
+                    })),
+                    // The objects have no fields.
+                    scan_object: MockMethod::new_default(),
+                    ..MockVM::default()
+                },
+            );
+
+            let garbage = allocate_large_object(&mut gc);
+            let object = allocate_large_object(&mut gc);
+            assert!(garbage.to_raw_address() < object.to_raw_address());
+            let payload = object.to_object_start::<MockVM>() + 16usize;
+            unsafe { payload.store(0xdead_beef_usize) };
+
+            let mut slot = Box::new(object);
+            let edge = Address::from_mut_ptr(&mut *slot);
+            gc.set_roots(vec![edge]);
+
+            // The garbage is reclaimed.  The object is not moved, because there are no free pages
+            // below it yet.
+            gc.run_gc(UserCollectionKind::FullCompact);
+            assert_eq!(*slot, object);
+
+            // The object is moved to the pages of the garbage, and the edge is updated.
+            gc.run_gc(UserCollectionKind::FullCompact);
+            let new_object = *slot;
+            assert_eq!(new_object.to_raw_address(), garbage.to_raw_address());
+            let new_payload = new_object.to_object_start::<MockVM>() + 16usize;
+            assert_eq!(unsafe { new_payload.load::<usize>() }, 0xdead_beef);
+            // The binding is told about the move.
+            assert_eq!(
+                REMAPPED_FROM.load(Ordering::SeqCst),
+                object.to_raw_address().as_usize()
+            );
+            assert_eq!(
+                REMAPPED_TO.load(Ordering::SeqCst),
+                new_object.to_raw_address().as_usize()
+            );
+
+            // The side metadata is copied to the new address.  The stale bits there were the bits
+            // of the garbage, which was in the nursery and not marked.
+            let los = gc.mmtk.get_plan().common().get_los();
+            assert!(los.is_live(new_object));
+            assert!(!los.is_in_nursery(new_object));
+            if gc.mmtk.get_plan().constraints().needs_log_bit {
+                assert!(
+                    MockVM::GLOBAL_LOG_BIT_SPEC.is_unlogged::<MockVM>(new_object, Ordering::SeqCst)
+                );
+            }
+        },
+        no_cleanup,
+    )
+}
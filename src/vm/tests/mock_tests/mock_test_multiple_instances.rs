@@ -0,0 +1,159 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+use super::mock_test_prelude::*;
+
+use crate::util::options::{GCTriggerSelector, PlanSelector};
+use crate::util::test_util::mock_gc::MockGC;
+use crate::util::{Address, ObjectReference};
+use crate::{AllocationSemantics, MMTKBuilder, Mutator, UserCollectionKind, MMTK};
+use std::ops::Range;
+
+const MB: usize = 1024 * 1024;
+const OBJECT_SIZE: usize = 64;
+
+fn space_ranges(mmtk: &MMTK<MockVM>) -> Vec<(&'static str, Range<Address>)> {
+    let mut ranges = vec![];
+    mmtk.get_plan().for_each_space(&mut |space| {
+        let common = space.common();
+        ranges.push((space.get_name(), common.start..common.start + common.extent));
+    });
+    ranges
+}
+
+fn space_of(ranges: &[(&'static str, Range<Address>)], addr: Address) -> Option<&'static str> {
+    ranges
+        .iter()
+        .find(|(_, range)| range.contains(&addr))
+        .map(|(name, _)| *name)
+}
+
+/// Allocate an object, and fill it with `tag` after the header word.
+fn allocate_object(mutator: &mut Mutator<MockVM>, tag: usize) -> ObjectReference {
+    let addr = memory_manager::alloc(mutator, OBJECT_SIZE, 8, 0, AllocationSemantics::Default);
+    assert!(!addr.is_zero());
+    let object = MockVM::address_to_ref(addr);
+    memory_manager::post_alloc(mutator, object, OBJECT_SIZE, AllocationSemantics::Default);
+    for offset in (8..OBJECT_SIZE).step_by(8) {
+        unsafe { (addr + offset).store(tag) };
+    }
+    object
+}
+
+fn check_object(object: ObjectReference, tag: usize) {
+    let addr = MockVM::ref_to_address(object);
+    for offset in (8..OBJECT_SIZE).step_by(8) {
+        assert_eq!(unsafe { (addr + offset).load::<usize>() }, tag);
+    }
+}
+
+#[test]
+pub fn multiple_instances() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut first = MutatorFixture::create_with_heapsize(16 * MB);
+            // The second instance uses a different plan from the first one, and runs GCs.  It uses a
+            // non-moving plan, as the objects cannot be copied with `MockVM`.
+            let second_plan = match *first.mmtk().get_options().plan {
+                PlanSelector::MarkSweep => PlanSelector::PageProtect,
+                _ => PlanSelector::MarkSweep,
+            };
+            let mut builder = MMTKBuilder::new();
+            builder.options.plan.set(second_plan);
+            builder
+                .options
+                .gc_trigger
+                .set(GCTriggerSelector::FixedHeapSize(16 * MB));
+            let mut second = MockGC::new(
+                &builder,
+                MockVM {
+                    get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                    // The objects have no fields.
+                    scan_object: MockMethod::new_default(),
+                    ..MockVM::default()
+                },
+            );
+
+            // The spaces of the two instances do not overlap.
+            let first_ranges = space_ranges(first.mmtk());
+            let second_ranges = space_ranges(second.mmtk);
+            for (name1, range1) in first_ranges.iter() {
+                for (name2, range2) in second_ranges.iter() {
+                    assert!(
+                        range1.end <= range2.start || range2.end <= range1.start,
+                        "{} {:?} overlaps with {} {:?}",
+                        name1,
+                        range1,
+                        name2,
+                        range2
+                    );
+                }
+            }
+
+            // Each instance allocates in its own spaces.
+            let mut first_objects = vec![];
+            let mut second_objects = vec![];
+            for i in 0..16 {
+                let object1 = allocate_object(&mut first.mutator, i);
+                let object2 = allocate_object(second.mutator(), i);
+                first_objects.push(object1);
+                second_objects.push(object2);
+                let addr1 = object1.to_raw_address();
+                let addr2 = object2.to_raw_address();
+                assert!(space_of(&first_ranges, addr1).is_some());
+                assert!(space_of(&second_ranges, addr1).is_none());
+                assert!(space_of(&second_ranges, addr2).is_some());
+                assert!(space_of(&first_ranges, addr2).is_none());
+
+                // The global SFT map finds the space of the right instance.
+                assert!(memory_manager::is_in_mmtk_spaces::<MockVM>(object1));
+                assert!(memory_manager::is_in_mmtk_spaces::<MockVM>(object2));
+                assert_eq!(
+                    crate::mmtk::SFT_MAP.get_checked(addr1).name(),
+                    space_of(&first_ranges, addr1).unwrap()
+                );
+                assert_eq!(
+                    crate::mmtk::SFT_MAP.get_checked(addr2).name(),
+                    space_of(&second_ranges, addr2).unwrap()
+                );
+            }
+
+            // A GC in the second instance keeps its rooted objects, and does not touch the
+            // objects of the first instance.
+            let mut roots: Vec<ObjectReference> =
+                second_objects.iter().step_by(2).copied().collect();
+            second.set_roots(
+                roots
+                    .iter_mut()
+                    .map(|slot| Address::from_mut_ptr(slot))
+                    .collect(),
+            );
+            second.run_gc(UserCollectionKind::Full);
+            for (i, object) in second_objects.iter().enumerate().step_by(2) {
+                assert!(object.is_live::<MockVM>());
+                check_object(*object, i);
+            }
+            for (i, object) in first_objects.iter().enumerate() {
+                assert!(memory_manager::is_in_mmtk_spaces::<MockVM>(*object));
+                check_object(*object, i);
+            }
+            // The first instance still allocates in its own spaces after the GC.
+            let object = allocate_object(&mut first.mutator, 16);
+            assert!(space_of(&first_ranges, object.to_raw_address()).is_some());
+
+            // All the instances share the mmapper, so they must use the same mmap strategy.
+            if cfg!(target_os = "linux") {
+                let result = std::panic::catch_unwind(|| {
+                    MMTKFixture::create_with_builder(
+                        |builder| {
+                            builder.options.transparent_hugepages.set(true);
+                        },
+                        false,
+                    )
+                });
+                assert!(result.is_err());
+            }
+        },
+        no_cleanup,
+    )
+}
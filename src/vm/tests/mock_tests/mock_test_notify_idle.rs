@@ -0,0 +1,53 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+use super::mock_test_prelude::*;
+use crate::util::opaque_pointer::*;
+use crate::AllocationSemantics;
+use std::time::{Duration, Instant};
+
+// This test notifies MMTk that the mutator is idle. MMTk should trigger a GC (and block the mutator) only if
+// it has enough time, and the plan can collect garbage.
+#[test]
+pub fn notify_idle() {
+    with_mockvm(
+        || -> MockVM {
+            MockVM {
+                block_for_gc: MockMethod::new_default(),
+                ..MockVM::default()
+            }
+        },
+        || {
+            const MB: usize = 1024 * 1024;
+            let mut fixture = MutatorFixture::create_with_heapsize(MB);
+            let mmtk = fixture.mmtk();
+            let tls = VMMutatorThread(VMThread::UNINITIALIZED);
+
+            // Nothing is allocated. There is nothing to collect.
+            let future = Instant::now() + Duration::from_secs(60);
+            assert!(!memory_manager::notify_idle(mmtk, tls, future));
+
+            let addr = memory_manager::alloc(
+                &mut fixture.mutator,
+                1024,
+                8,
+                0,
+                AllocationSemantics::Default,
+            );
+            assert!(!addr.is_zero());
+
+            // The deadline has passed. There is no time for a GC.
+            let past = Instant::now() - Duration::from_millis(1);
+            assert!(!memory_manager::notify_idle(mmtk, tls, past));
+            read_mockvm(|mock| assert!(!mock.block_for_gc.is_called()));
+
+            // There is time for a GC.
+            let collects_garbage = mmtk.get_plan().constraints().collects_garbage;
+            assert_eq!(
+                memory_manager::notify_idle(mmtk, tls, future),
+                collects_garbage
+            );
+            read_mockvm(|mock| assert_eq!(mock.block_for_gc.is_called(), collects_garbage));
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,90 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+// Query the path from a root to an object through a field of another object.  The query reuses
+// the root scanning of GCs, and scans objects with `Scanning::scan_object`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::mock_test_prelude::*;
+use crate::util::options::GCTriggerSelector;
+use crate::util::retention_path::{RetentionPath, RootKind};
+use crate::util::test_util::mock_gc::MockGC;
+use crate::util::{Address, ObjectReference};
+use crate::{AllocationSemantics, MMTKBuilder};
+
+const OBJECT_SIZE: usize = 16;
+const MB: usize = 1024 * 1024;
+
+/// The parent object and its field.  The `scan_object` mock cannot call other mocks to find them.
+static PARENT: AtomicUsize = AtomicUsize::new(0);
+static FIELD: AtomicUsize = AtomicUsize::new(0);
+
+fn allocate_object(gc: &mut MockGC) -> ObjectReference {
+    let addr = memory_manager::alloc(
+        gc.mutator(),
+        OBJECT_SIZE,
+        8,
+        0,
+        AllocationSemantics::Default,
+    );
+    assert!(!addr.is_zero());
+    let object = MockVM::address_to_ref(addr);
+    memory_manager::post_alloc(
+        gc.mutator(),
+        object,
+        OBJECT_SIZE,
+        AllocationSemantics::Default,
+    );
+    object
+}
+
+#[test]
+pub fn retention_path() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut builder = MMTKBuilder::new();
+            builder
+                .options
+                .gc_trigger
+                .set(GCTriggerSelector::FixedHeapSize(32 * MB));
+            let mut gc = MockGC::new(
+                &builder,
+                MockVM {
+                    get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                    scan_object: MockMethod::new_fixed(Box::new(|(_, object, visitor)| {
+                        if object.to_raw_address().as_usize() == PARENT.load(Ordering::SeqCst) {
+                            let field =
+                                unsafe { Address::from_usize(FIELD.load(Ordering::SeqCst)) };
+                            visitor.visit_edge(field);
+                        }
+                    })),
+                    ..MockVM::default()
+                },
+            );
+
+            let parent = allocate_object(&mut gc);
+            let child = allocate_object(&mut gc);
+            let unreachable = allocate_object(&mut gc);
+            let field = parent.to_object_start::<MockVM>() + 8usize;
+            unsafe { field.store(child) };
+            PARENT.store(parent.to_raw_address().as_usize(), Ordering::SeqCst);
+            FIELD.store(field.as_usize(), Ordering::SeqCst);
+
+            let mut slot = Box::new(parent);
+            let root_edge = Address::from_mut_ptr(&mut *slot);
+            gc.set_roots(vec![root_edge]);
+
+            assert_eq!(
+                gc.find_retention_paths(child, 10),
+                vec![RetentionPath {
+                    root: RootKind::Edge(root_edge),
+                    objects: vec![parent, child],
+                    edges: vec![Some(field)],
+                }]
+            );
+            assert!(gc.find_retention_paths(unreachable, 10).is_empty());
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,36 @@
+// GITHUB-CI: MMTK_PLAN=NoGC
+
+use super::mock_test_prelude::*;
+use crate::util::opaque_pointer::*;
+use crate::util::retention_path::{QueryInProgress, RetentionQuery};
+use crate::util::{Address, ObjectReference};
+
+// Only one retention path query can be in progress. Another query returns an error without
+// blocking the mutator or requesting a GC.
+#[test]
+pub fn retention_query_in_progress() {
+    with_mockvm(
+        default_setup,
+        || {
+            let fixture = MMTKFixture::create();
+            let mmtk = fixture.get_mmtk();
+            let object =
+                ObjectReference::from_raw_address(unsafe { Address::from_usize(0x1000) }).unwrap();
+
+            // Pretend that another mutator is waiting for its query.
+            *mmtk.retention_query.lock().unwrap() = Some(RetentionQuery::new(object, 1));
+            assert_eq!(
+                memory_manager::find_retention_paths(
+                    mmtk,
+                    VMMutatorThread(VMThread::UNINITIALIZED),
+                    object,
+                    1
+                ),
+                Err(QueryInProgress)
+            );
+            assert!(mmtk.retention_query.lock().unwrap().is_some());
+            *mmtk.retention_query.lock().unwrap() = None;
+        },
+        no_cleanup,
+    )
+}
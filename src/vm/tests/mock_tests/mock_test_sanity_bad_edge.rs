@@ -0,0 +1,101 @@
+// GITHUB-CI: MMTK_PLAN=Immix
+// GITHUB-CI: FEATURES=sanity,mock_test_side_metadata
+
+// The sanity GC panics with the path from the root if it reaches an object that the GC did not
+// mark.  The binding hides a field from the GC, and only reports it to the sanity GC.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::mock_test_prelude::*;
+use crate::util::options::GCTriggerSelector;
+use crate::util::test_util::mock_gc::MockGC;
+use crate::util::{Address, ObjectReference};
+use crate::{AllocationSemantics, MMTKBuilder, UserCollectionKind};
+
+const OBJECT_SIZE: usize = 16;
+const MB: usize = 1024 * 1024;
+
+/// The field of the parent object.  The `scan_object` mock cannot call other mocks to find it.
+static FIELD: AtomicUsize = AtomicUsize::new(0);
+/// The number of times the parent object has been scanned.
+static SCANS: AtomicUsize = AtomicUsize::new(0);
+
+fn allocate_object(gc: &mut MockGC) -> ObjectReference {
+    let addr = memory_manager::alloc(
+        gc.mutator(),
+        OBJECT_SIZE,
+        8,
+        0,
+        AllocationSemantics::Default,
+    );
+    assert!(!addr.is_zero());
+    let object = MockVM::address_to_ref(addr);
+    memory_manager::post_alloc(
+        gc.mutator(),
+        object,
+        OBJECT_SIZE,
+        AllocationSemantics::Default,
+    );
+    object
+}
+
+#[test]
+#[should_panic(expected = "the object is not marked as live in space immix")]
+pub fn sanity_bad_edge() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut builder = MMTKBuilder::new();
+            builder
+                .options
+                .gc_trigger
+                .set(GCTriggerSelector::FixedHeapSize(32 * MB));
+            let mut gc = MockGC::new(
+                &builder,
+                MockVM {
+                    get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                    // Only the parent object is scanned.  Its field is hidden from the GC, and is
+                    // reported in the sanity GC that follows.
+                    scan_object: MockMethod::new_fixed(Box::new(|(_, _, visitor)| {
+                        if SCANS.fetch_add(1, Ordering::SeqCst) > 0 {
+                            let field =
+                                unsafe { Address::from_usize(FIELD.load(Ordering::SeqCst)) };
+                            visitor.visit_edge(field);
+                        }
+                    })),
+                    ..MockVM::default()
+                },
+            );
+
+            let parent = allocate_object(&mut gc);
+            let child = allocate_object(&mut gc);
+            let field = parent.to_object_start::<MockVM>() + 8usize;
+            unsafe { field.store(child) };
+            FIELD.store(field.as_usize(), Ordering::SeqCst);
+
+            let mut slot = Box::new(parent);
+            let root_edge = Address::from_mut_ptr(&mut *slot);
+            gc.set_roots(vec![root_edge]);
+
+            let payload =
+                panic::catch_unwind(AssertUnwindSafe(|| gc.run_gc(UserCollectionKind::Full)))
+                    .unwrap_err();
+            let message = payload.downcast_ref::<String>().unwrap();
+            let path: Vec<&str> = message
+                .lines()
+                .skip_while(|line| *line != "Path from root:")
+                .skip(1)
+                .collect();
+            assert_eq!(
+                path,
+                vec![
+                    format!("  root edge {:?} -> {}", root_edge, parent),
+                    format!("  {} field {:?} -> {}", parent, field, child),
+                ]
+            );
+            panic::resume_unwind(payload);
+        },
+        no_cleanup,
+    )
+}
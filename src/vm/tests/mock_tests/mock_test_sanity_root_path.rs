@@ -0,0 +1,66 @@
+// GITHUB-CI: MMTK_PLAN=NoGC
+// GITHUB-CI: FEATURES=sanity
+
+use super::mock_test_prelude::*;
+
+use crate::util::sanity::sanity_checker::{ReachedFrom, SanityChecker};
+use crate::util::*;
+
+#[test]
+pub fn root_path() {
+    with_mockvm(
+        default_setup,
+        || {
+            // The sanity checker only records the edges and does not access them.
+            let root_edge = unsafe { Address::from_usize(0x100) };
+            let obj1 =
+                ObjectReference::from_raw_address(unsafe { Address::from_usize(0x1000) }).unwrap();
+            let field = obj1.to_raw_address() + 8usize;
+            let obj2 =
+                ObjectReference::from_raw_address(unsafe { Address::from_usize(0x2000) }).unwrap();
+            let obj3 =
+                ObjectReference::from_raw_address(unsafe { Address::from_usize(0x3000) }).unwrap();
+
+            let mut checker = SanityChecker::<Address>::new();
+            assert!(checker.record(obj1, ReachedFrom::RootEdge(root_edge)));
+            let obj2_from = ReachedFrom::Object {
+                parent: obj1,
+                edge: Some(field),
+            };
+            assert!(checker.record(obj2, obj2_from));
+            // Only the first path to an object is kept.
+            assert!(!checker.record(obj2, ReachedFrom::RootNode));
+
+            // obj3 has not been recorded, as if it fails verification when it is first reached.
+            let obj3_from = ReachedFrom::Object {
+                parent: obj2,
+                edge: None,
+            };
+            let path = checker.root_path(obj3, obj3_from);
+            assert_eq!(
+                path,
+                vec![
+                    (obj1, ReachedFrom::RootEdge(root_edge)),
+                    (obj2, obj2_from),
+                    (obj3, obj3_from),
+                ]
+            );
+
+            let formatted = checker.format_root_path(obj3, obj3_from);
+            let lines: Vec<&str> = formatted.lines().collect();
+            assert_eq!(lines.len(), 3);
+            assert_eq!(lines[0], format!("  root edge {:?} -> {}", root_edge, obj1));
+            assert_eq!(
+                lines[1],
+                format!("  {} field {:?} -> {}", obj1, field, obj2)
+            );
+            assert_eq!(lines[2], format!("  {} -> {}", obj2, obj3));
+
+            assert_eq!(
+                checker.root_path(obj1, ReachedFrom::RootNode),
+                vec![(obj1, ReachedFrom::RootNode)]
+            );
+        },
+        no_cleanup,
+    )
+}
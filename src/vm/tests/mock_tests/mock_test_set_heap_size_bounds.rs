@@ -0,0 +1,37 @@
+// GITHUB-CI: MMTK_PLAN=NoGC
+
+use super::mock_test_prelude::*;
+
+use crate::util::opaque_pointer::*;
+use crate::AllocationSemantics;
+
+const MB: usize = 1024 * 1024;
+
+#[test]
+pub fn set_fixed_heap_size() {
+    with_mockvm(
+        default_setup,
+        || {
+            let mut fixture = MutatorFixture::create_with_heapsize(MB);
+            let mmtk = fixture.mmtk();
+            let tls = VMMutatorThread(VMThread::UNINITIALIZED);
+            assert_eq!(memory_manager::total_bytes(mmtk), MB);
+
+            // Grow the heap.
+            assert!(memory_manager::set_heap_size_bounds(mmtk, tls, 0, 2 * MB));
+            assert_eq!(memory_manager::total_bytes(mmtk), 2 * MB);
+
+            // Invalid bounds are rejected.
+            assert!(!memory_manager::set_heap_size_bounds(mmtk, tls, 2 * MB, MB));
+            assert_eq!(memory_manager::total_bytes(mmtk), 2 * MB);
+
+            // Use some memory. NoGC cannot collect, so a max heap size below the usage is rejected.
+            let addr =
+                memory_manager::alloc(&mut fixture.mutator, MB, 8, 0, AllocationSemantics::Default);
+            assert!(!addr.is_zero());
+            assert!(!memory_manager::set_heap_size_bounds(mmtk, tls, 0, MB / 2));
+            assert_eq!(memory_manager::total_bytes(mmtk), 2 * MB);
+        },
+        no_cleanup,
+    )
+}
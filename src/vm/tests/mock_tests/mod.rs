@@ -1,6 +1,7 @@
-// NOTE: MMTk will panic if MMTK is initialized more than once per process (this is a bug and we should fix it).
-// To work around the problem, we run each of the following modules in a separate test process
-// if the test initializes an MMTk intance.
+// NOTE: Multiple MMTk instances can coexist in a process, and an instance can be destroyed with
+// `mmtk_destroy`, but the instances share the MockVM, the virtual memory layout and the address space.
+// To keep tests independent, we run each of the following modules in a separate test process if the
+// test initializes an MMTk intance.
 
 // All the tests with prefix 'mock_test_' and with the feature 'mock_test' will use MockVM, and will initialize MMTk.
 // To avoid re-initialization, one can have only one #[test] per module,
@@ -26,24 +27,66 @@ mod mock_test_allocate_with_initialize_collection;
 mod mock_test_allocate_with_re_enable_collection;
 mod mock_test_allocate_without_initialize_collection;
 mod mock_test_allocator_info;
+// Background sweeping cannot be used with VO bits.  Immix GCs need the mark bits on the side.
+#[cfg(all(not(feature = "vo_bit"), feature = "mock_test_side_metadata"))]
+mod mock_test_background_sweeping;
 mod mock_test_barrier_slow_path_assertion;
 #[cfg(feature = "is_mmtk_object")]
 mod mock_test_conservatism;
+#[cfg(feature = "is_mmtk_object")]
+mod mock_test_conservative_roots;
+mod mock_test_custom_stage;
+// These tests inspect the address ranges of spaces, which malloc and lock-free spaces do not have.
+#[cfg(not(any(feature = "malloc_mark_sweep", feature = "nogc_lock_free")))]
+mod mock_test_destroy;
+#[cfg(feature = "malloc_mark_sweep")]
+mod mock_test_destroy_malloc_ms;
 mod mock_test_edges;
+mod mock_test_fragmentation_report;
+mod mock_test_fragmentation_report_after_gc;
 #[cfg(target_os = "linux")]
 mod mock_test_handle_mmap_conflict;
 mod mock_test_handle_mmap_oom;
 mod mock_test_init_fork;
+#[cfg(all(
+    target_pointer_width = "64",
+    not(any(feature = "malloc_mark_sweep", feature = "nogc_lock_free"))
+))]
+mod mock_test_instance_limit;
+#[cfg(feature = "is_mmtk_object")]
+mod mock_test_internal_pointer;
 mod mock_test_is_in_mmtk_spaces;
 mod mock_test_issue139_allocate_non_multiple_of_min_alignment;
 mod mock_test_issue867_allocate_unrealistically_large_object;
+mod mock_test_load_barrier;
+// Page remapping is only supported on Linux, and Immix does not defrag with `immix_non_moving`.
+// The test checks that the side metadata is copied.
+#[cfg(all(
+    target_os = "linux",
+    not(feature = "immix_non_moving"),
+    feature = "mock_test_side_metadata"
+))]
+mod mock_test_los_page_remapping;
 #[cfg(feature = "malloc_counted_size")]
 mod mock_test_malloc_counted;
 mod mock_test_malloc_ms;
 #[cfg(all(target_pointer_width = "64", feature = "vm_space"))]
 mod mock_test_mmtk_julia_pr_143;
+#[cfg(all(
+    target_pointer_width = "64",
+    not(any(feature = "malloc_mark_sweep", feature = "nogc_lock_free"))
+))]
+mod mock_test_multiple_instances;
 #[cfg(feature = "nogc_lock_free")]
 mod mock_test_nogc_lock_free;
+mod mock_test_notify_idle;
+mod mock_test_retention_path;
+mod mock_test_retention_query_in_progress;
+#[cfg(all(feature = "sanity", feature = "mock_test_side_metadata"))]
+mod mock_test_sanity_bad_edge;
+#[cfg(feature = "sanity")]
+mod mock_test_sanity_root_path;
+mod mock_test_set_heap_size_bounds;
 #[cfg(target_pointer_width = "64")]
 mod mock_test_vm_layout_compressed_pointer;
 mod mock_test_vm_layout_default;